@@ -0,0 +1,214 @@
+//module for checking each metro area (cbsa) against the minimum monitor counts of 40 CFR Part 58 Appendix D
+//pm2.5 uses table D-5 and ozone uses table D-2, both scale with cbsa population and design value
+
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use serde::Deserialize;
+use crate::monitor;
+use crate::network::MonitoringNetwork;
+
+//85% of the annual pm2.5 naaqs (9.0 µg/m³, 2024 revision)
+const PM25_DESIGN_VALUE_THRESHOLD: f64 = 7.65;
+//85% of the 8-hour ozone naaqs (0.070 ppm, 2015 revision)
+const OZONE_DESIGN_VALUE_THRESHOLD: f64 = 0.0595;
+
+//pollutants with appendix D population-based minimums
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pollutant {
+    Pm25,
+    Ozone,
+}
+
+impl Pollutant {
+    pub fn parameter_code(&self) -> &'static str {
+        match self {
+            Pollutant::Pm25 => monitor::PM25_PARAMETER,
+            Pollutant::Ozone => monitor::OZONE_PARAMETER,
+        }
+    }
+
+    //design value at or above which the higher column of the appendix D table applies
+    pub fn design_value_threshold(&self) -> f64 {
+        match self {
+            Pollutant::Pm25 => PM25_DESIGN_VALUE_THRESHOLD,
+            Pollutant::Ozone => OZONE_DESIGN_VALUE_THRESHOLD,
+        }
+    }
+}
+
+impl fmt::Display for Pollutant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pollutant::Pm25 => write!(f, "PM2.5"),
+            Pollutant::Ozone => write!(f, "O3"),
+        }
+    }
+}
+
+//function to look up the minimum number of monitoring sites for a cbsa
+//inputs: the pollutant, cbsa population and the cbsa design value if known
+//a missing design value uses the lower (< 85% of the naaqs) column, the higher one needs a design value showing it
+pub fn required_monitors(pollutant: Pollutant, population: f64, design_value: Option<f64>) -> u32 {
+    let high = design_value.is_some_and(|value| value >= pollutant.design_value_threshold());
+    //(sites if the design value is >= 85% of the naaqs, sites if below) for the cbsa's population row
+    let row = match pollutant {
+        //table D-5
+        Pollutant::Pm25 => match population {
+            p if p > 1_000_000.0 => Some((3, 2)),
+            p if p >= 500_000.0 => Some((2, 1)),
+            p if p >= 50_000.0 => Some((1, 0)),
+            _ => None,
+        },
+        //table D-2
+        Pollutant::Ozone => match population {
+            p if p > 10_000_000.0 => Some((4, 2)),
+            p if p >= 4_000_000.0 => Some((3, 1)),
+            p if p >= 350_000.0 => Some((2, 1)),
+            p if p >= 50_000.0 => Some((1, 0)),
+            _ => None,
+        },
+    };
+    match row {
+        Some((above, below)) => if high { above } else { below },
+        None => 0,
+    }
+}
+
+//population and optional design values for one cbsa
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CbsaRecord {
+    #[serde(rename = "CBSA Name")]
+    pub name: String,
+    #[serde(rename = "Population")]
+    pub population: f64,
+    #[serde(rename = "PM25 Design Value", default)]
+    pub pm25_design_value: Option<f64>,
+    #[serde(rename = "O3 Design Value", default)]
+    pub ozone_design_value: Option<f64>,
+}
+
+impl CbsaRecord {
+    pub fn design_value(&self, pollutant: Pollutant) -> Option<f64> {
+        match pollutant {
+            Pollutant::Pm25 => self.pm25_design_value,
+            Pollutant::Ozone => self.ozone_design_value,
+        }
+    }
+}
+
+//function to read cbsa populations from csv using serde
+//inputs: path to a csv with "CBSA Name" and "Population" columns, and optionally "PM25 Design Value"
+//        and "O3 Design Value"; names must match the CBSA Name column of the sites file
+//outputs: records keyed by cbsa name
+pub fn read_cbsa_population<P: AsRef<Path>>(path: P) -> Result<HashMap<String, CbsaRecord>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut csv_reader = csv::ReaderBuilder::new().has_headers(true).from_reader(reader);
+    let mut records = HashMap::new();
+
+    for result in csv_reader.deserialize::<CbsaRecord>() {
+        match result {
+            Ok(record) => {
+                records.insert(record.name.clone(), record);
+            },
+            Err(err) => {
+                //log error but continue anyway
+                eprintln!("Error deserializing cbsa record {}", err);
+            }
+        }
+    }
+    println!("Loaded population for {} CBSAs", records.len());
+    Ok(records)
+}
+
+//required versus actual monitoring sites in one cbsa
+#[derive(Debug, Clone, PartialEq)]
+pub struct CbsaAdequacy {
+    pub cbsa_name: String,
+    pub pollutant: Pollutant,
+    pub population: f64,
+    pub design_value: Option<f64>, //from the csv, otherwise the highest site value in the cbsa
+    pub required: u32,
+    pub actual: u32, //stations in the cbsa with a monitor for the pollutant
+}
+
+impl CbsaAdequacy {
+    //positive for a surplus, negative for a deficit
+    pub fn surplus(&self) -> i64 {
+        self.actual as i64 - self.required as i64
+    }
+}
+
+//function to compare each cbsa's monitor count with the appendix D minimum
+//inputs: the network (with monitors attached), cbsa records, the pollutant, and site values used as a
+//        design value fallback (e.g. site_values for pm2.5 annual means) when a cbsa has none in the csv
+//outputs: one entry per cbsa in the csv, largest deficit first, then by name
+pub fn assess_cbsas(network: &MonitoringNetwork, cbsas: &HashMap<String, CbsaRecord>, pollutant: Pollutant,
+                    site_values: &HashMap<String, f64>) -> Vec<CbsaAdequacy> {
+    let monitored = network.stations_with_parameter(pollutant.parameter_code());
+    let mut actual: HashMap<&str, u32> = HashMap::new();
+    let mut highest: HashMap<&str, f64> = HashMap::new();
+    for station in network.stations.values() {
+        let Some(cbsa) = station.cbsa_name.as_deref() else { continue };
+        if monitored.contains(&station.id) {
+            *actual.entry(cbsa).or_default() += 1;
+        }
+        if let Some(value) = site_values.get(&station.id) {
+            let entry = highest.entry(cbsa).or_insert(*value);
+            *entry = entry.max(*value);
+        }
+    }
+
+    let mut results: Vec<CbsaAdequacy> = cbsas.values().map(|record| {
+        let design_value = record.design_value(pollutant).or_else(|| highest.get(record.name.as_str()).copied());
+        CbsaAdequacy {
+            cbsa_name: record.name.clone(),
+            pollutant,
+            population: record.population,
+            design_value,
+            required: required_monitors(pollutant, record.population, design_value),
+            actual: actual.get(record.name.as_str()).copied().unwrap_or(0),
+        }
+    }).collect();
+    results.sort_by(|a, b| a.surplus().cmp(&b.surplus()).then_with(|| a.cbsa_name.cmp(&b.cbsa_name)));
+    results
+}
+
+//function to print cbsas with a deficit and a count of those meeting the minimum
+pub fn print_adequacy(results: &[CbsaAdequacy]) {
+    let Some(pollutant) = results.first().map(|r| r.pollutant) else {
+        println!("No CBSAs to assess");
+        return;
+    };
+    let deficits: Vec<&CbsaAdequacy> = results.iter().filter(|r| r.surplus() < 0).collect();
+    let mut by_surplus: BTreeMap<i64, usize> = BTreeMap::new();
+    for result in results {
+        *by_surplus.entry(result.surplus()).or_default() += 1;
+    }
+    println!("{} network adequacy (40 CFR 58 Appendix D): {} of {} CBSAs below the minimum",
+             pollutant, deficits.len(), results.len());
+    for result in deficits.iter().take(10) {
+        println!("  {}: population {:.0}, design value {}, required {}, actual {}, deficit {}",
+                 result.cbsa_name,
+                 result.population,
+                 result.design_value.map(|v| format!("{:.4}", v)).unwrap_or_else(|| "n/a".to_string()),
+                 result.required,
+                 result.actual,
+                 -result.surplus());
+    }
+    if deficits.len() > 10 {
+        println!("  ... and {} more", deficits.len() - 10);
+    }
+    let surplus: Vec<String> = by_surplus.iter()
+        .filter(|(surplus, _)| **surplus >= 0)
+        .map(|(surplus, count)| format!("{} with +{}", count, surplus))
+        .collect();
+    if !surplus.is_empty() {
+        println!("  meeting the minimum: {}", surplus.join(", "));
+    }
+}
@@ -0,0 +1,133 @@
+//module for per-state and per-county tables of network metrics, exportable to csv
+//stations are grouped by name or fips code, or by any other key through aggregate_by
+
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::Path;
+use serde::Serialize;
+use crate::network::{IsolationStatistics, MonitoringNetwork};
+use crate::population::CentroidCoverage;
+use crate::station::Station;
+
+//how stations are grouped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    State,      //state_name, e.g. "Alabama"
+    County,     //county and state name, e.g. "Jefferson, Alabama"
+    StateFips,  //two digit state code, e.g. "01"
+    CountyFips, //five digit state + county code, e.g. "01073"
+}
+
+impl GroupBy {
+    //group key of a station
+    pub fn key(&self, station: &Station) -> String {
+        match self {
+            GroupBy::State => station.state_name.clone(),
+            GroupBy::County => format!("{}, {}", station.county_name, station.state_name),
+            GroupBy::StateFips => station.state_code.clone(),
+            GroupBy::CountyFips => format!("{}{}", station.state_code, station.county_code),
+        }
+    }
+
+    //the part of a census geoid that identifies the same state or county (2 or 5 digits)
+    fn geoid_prefix_length(&self) -> usize {
+        match self {
+            GroupBy::State | GroupBy::StateFips => 2,
+            GroupBy::County | GroupBy::CountyFips => 5,
+        }
+    }
+}
+
+//one row of an aggregated table
+//flat so it serializes directly to a csv row, empty cells for missing values
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupStatistics {
+    pub group: String,
+    pub station_count: usize,
+    pub isolation_min: Option<f64>,
+    pub isolation_median: Option<f64>,
+    pub isolation_max: Option<f64>,
+    pub mean_pollution: Option<f64>,
+    pub gap_count: usize, //gaps at the network-wide default thresholds
+    pub monitored_percent: f64, //% of stations with a pollution value
+    pub population_coverage_percent: Option<f64>, //% of population within the coverage radius of a monitor
+}
+
+//function to aggregate station metrics by any key
+//inputs: the network, pollution per station and a key function, stations with no key are skipped
+//outputs: one row per key sorted by key, population coverage is left empty
+pub fn aggregate_by(network: &MonitoringNetwork, pollution_data: &HashMap<String, f64>,
+                    key: impl Fn(&Station) -> Option<String>) -> Vec<GroupStatistics> {
+    let gap_ids = network.default_gap_ids(pollution_data);
+    let mut groups: BTreeMap<String, Vec<&Station>> = BTreeMap::new();
+    for station in network.stations.values() {
+        if let Some(group) = key(station) {
+            groups.entry(group).or_default().push(station);
+        }
+    }
+
+    groups.into_iter().map(|(group, stations)| {
+        let isolation = IsolationStatistics::from_values(
+            stations.iter().filter_map(|station| network.isolation(&station.id)).collect());
+        let pollution: Vec<f64> = stations.iter().filter_map(|station| pollution_data.get(&station.id).copied()).collect();
+        GroupStatistics {
+            group,
+            station_count: stations.len(),
+            isolation_min: isolation.map(|stats| stats.min),
+            isolation_median: isolation.map(|stats| stats.median),
+            isolation_max: isolation.map(|stats| stats.max),
+            mean_pollution: if pollution.is_empty() { None } else { Some(pollution.iter().sum::<f64>() / pollution.len() as f64) },
+            gap_count: stations.iter().filter(|station| gap_ids.contains(&station.id)).count(),
+            monitored_percent: pollution.len() as f64 / stations.len() as f64 * 100.0,
+            population_coverage_percent: None,
+        }
+    }).collect()
+}
+
+//function to aggregate station metrics by state or county
+//inputs: the network, pollution per station, the grouping, and optionally nearest-monitor distances of
+//        census centroids with a radius (km) for the population coverage column
+//centroids are matched to groups through the fips codes at the start of their geoid
+pub fn aggregate(network: &MonitoringNetwork, pollution_data: &HashMap<String, f64>, group_by: GroupBy,
+                 coverage: Option<(&[CentroidCoverage], f64)>) -> Vec<GroupStatistics> {
+    let mut rows = aggregate_by(network, pollution_data, |station| Some(group_by.key(station)));
+    let Some((coverage, radius_km)) = coverage else {
+        return rows;
+    };
+
+    //fips prefix = group key, taken from the stations so names match the rows
+    let prefix_length = group_by.geoid_prefix_length();
+    let groups: HashMap<String, String> = network.stations.values()
+        .map(|station| {
+            let fips = format!("{}{}", station.state_code, station.county_code);
+            (fips[..prefix_length.min(fips.len())].to_string(), group_by.key(station))
+        })
+        .collect();
+    //group = (total population, population within the radius)
+    let mut population: HashMap<&str, (f64, f64)> = HashMap::new();
+    for record in coverage {
+        let Some(group) = record.geoid.get(..prefix_length).and_then(|prefix| groups.get(prefix)) else { continue };
+        let entry = population.entry(group.as_str()).or_default();
+        entry.0 += record.population;
+        if record.distance_km.is_some_and(|distance| distance <= radius_km) {
+            entry.1 += record.population;
+        }
+    }
+    for row in &mut rows {
+        row.population_coverage_percent = population.get(row.group.as_str())
+            .filter(|(total, _)| *total > 0.0)
+            .map(|(total, covered)| covered / total * 100.0);
+    }
+    rows
+}
+
+//function to write an aggregated table to csv, with a header row named after the fields
+pub fn write_csv<P: AsRef<Path>>(rows: &[GroupStatistics], path: P) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
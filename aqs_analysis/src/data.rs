@@ -1,41 +1,208 @@
 //module for loading and processing data from csv files containing station metadata and pollution measurements
 
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
+use chrono::NaiveDate;
 use csv;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use crate::monitor::{self, CompletenessFilter, EventSelection, EventType, Monitor, SiteAggregation};
 use crate::station::Station;
 
+//which coordinates are accepted when loading stations
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CoordinateCheck {
+    //finite, within +-90 latitude and +-180 longitude, and not (0, 0)
+    Valid,
+    //valid, with the aqs state code of a state, dc, puerto rico, the virgin islands, guam, the northern
+    //marianas or american samoa, and coordinates inside one of them
+    UsTerritory,
+}
+
+//what to do with a station at the same coordinates as one already loaded
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DuplicateCoordinates {
+    //keep every station, duplicates are only reported
+    Keep,
+    //keep the first station in file order and drop the rest
+    KeepFirst,
+}
+
+//rules applied to every station record while loading, saved in network snapshots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoadPolicy {
+    pub closed_as_of: Option<NaiveDate>, //drop sites closed on or before this date
+    pub coordinates: CoordinateCheck,
+    pub duplicates: DuplicateCoordinates,
+}
+
+//the default only rejects invalid coordinates, closed sites are kept for historical comparisons
+impl Default for LoadPolicy {
+    fn default() -> Self {
+        LoadPolicy {
+            closed_as_of: None,
+            coordinates: CoordinateCheck::Valid,
+            duplicates: DuplicateCoordinates::Keep,
+        }
+    }
+}
+
+//coordinates closer than this (in degrees, about 1 m) count as duplicates
+const DUPLICATE_PRECISION: f64 = 1e-5;
+
+//aqs state codes of the states and dc (01 to 56), american samoa, guam, the northern marianas, puerto rico
+//and the virgin islands, codes 80 and above are mexico and canada
+const US_TERRITORY_CODES: [u32; 5] = [60, 66, 69, 72, 78];
+
+//latitude/longitude boxes around us states and territories, only a sanity check on the state code since
+//border cities such as tijuana or windsor fall inside them
+//(min lat, max lat, min lon, max lon), alaska's aleutians cross the antimeridian so it has two boxes
+const US_TERRITORY_BOUNDS: [(f64, f64, f64, f64); 7] = [
+    (24.0, 50.0, -125.5, -66.0),    //contiguous states
+    (51.0, 72.0, -180.0, -129.5),   //alaska
+    (51.0, 54.0, 172.0, 180.0),     //western aleutians
+    (18.5, 22.5, -161.0, -154.5),   //hawaii
+    (17.5, 18.6, -68.0, -64.5),     //puerto rico and the virgin islands
+    (13.2, 20.6, 144.5, 146.2),     //guam and the northern mariana islands
+    (-14.6, -10.9, -171.2, -168.1), //american samoa
+];
+
+//whether an aqs state code belongs to a us state, dc or territory
+pub fn is_us_state_code(state_code: &str) -> bool {
+    match state_code.trim().parse::<u32>() {
+        Ok(code) => (1..=56).contains(&code) || US_TERRITORY_CODES.contains(&code),
+        Err(_) => false,
+    }
+}
+
+//function to check a station's coordinates against a coordinate rule
+//inputs: the station's aqs state code (only used by the territory check) and its coordinates
+pub fn coordinates_pass(state_code: &str, latitude: f64, longitude: f64, check: CoordinateCheck) -> bool {
+    let valid = latitude.is_finite() && longitude.is_finite()
+        && (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)
+        && !(latitude == 0.0 && longitude == 0.0);
+    match check {
+        CoordinateCheck::Valid => valid,
+        CoordinateCheck::UsTerritory => valid && is_us_state_code(state_code) && US_TERRITORY_BOUNDS.iter().any(|(min_lat, max_lat, min_lon, max_lon)| {
+            (*min_lat..=*max_lat).contains(&latitude) && (*min_lon..=*max_lon).contains(&longitude)
+        }),
+    }
+}
+
+//counts of station records kept and dropped by each loading rule
+//each record is counted under the first rule that rejects it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadSummary {
+    pub records: usize,
+    pub parse_errors: usize,
+    pub invalid_coordinates: usize,
+    pub outside_territory: usize,
+    pub closed: usize,
+    pub duplicate_coordinates: usize, //stations at the same coordinates as an earlier station
+    pub duplicates_dropped: usize,
+    pub duplicates: Vec<(String, String)>, //(duplicate station id, id of the earlier station)
+    pub loaded: usize,
+    pub converted_nad27: usize,
+}
+
+impl LoadSummary {
+    //function to print the summary, one line per rule that affected any record
+    pub fn print(&self) {
+        println!("Station load summary: {} of {} records loaded", self.loaded, self.records);
+        let rules = [
+            ("could not be parsed", self.parse_errors),
+            ("invalid coordinates", self.invalid_coordinates),
+            ("outside US states and territories", self.outside_territory),
+            ("closed", self.closed),
+            ("duplicate coordinates dropped", self.duplicates_dropped),
+        ];
+        for (rule, count) in rules {
+            if count > 0 {
+                println!("  {}: {}", rule, count);
+            }
+        }
+        if self.duplicate_coordinates > self.duplicates_dropped {
+            println!("  duplicate coordinates kept: {}", self.duplicate_coordinates - self.duplicates_dropped);
+        }
+        if self.converted_nad27 > 0 {
+            println!("  converted from NAD27 to WGS84: {}", self.converted_nad27);
+        }
+    }
+}
+
 //function to read the station data csv file using serde
 //inputs: path to the csv file
 //outputs: result containing a vector of station objects or an error
-//uses serde deserialization to convert csv rows to station objects
+//uses serde deserialization to convert csv rows to station objects, and the default load policy
 pub fn read_stations<P:AsRef<Path>>(path: P) -> Result<Vec<Station>, Box <dyn Error>> {
+    let (stations, _) = read_stations_with_policy(path, &LoadPolicy::default())?;
+    Ok(stations)
+}
+
+//function to read the station data csv file, applying a load policy
+//outputs: the stations that pass every rule and a summary of what was dropped
+pub fn read_stations_with_policy<P: AsRef<Path>>(path: P, policy: &LoadPolicy)
+    -> Result<(Vec<Station>, LoadSummary), Box<dyn Error>> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
     let mut csv_reader = csv::ReaderBuilder::new().has_headers(true).from_reader(reader);
     let mut stations = Vec::new();
+    let mut summary = LoadSummary::default();
+    //rounded coordinates = id of the first station loaded there
+    let mut seen: HashMap<(i64, i64), String> = HashMap::new();
 
     //using serde deseralization (lecture 33)
     for result in csv_reader.deserialize::<Station>() {
-        match result {
-            Ok(mut station) => {
-                //generate id after deserialization
-                station.generate_id();
-                stations.push(station);
-            },
+        summary.records += 1;
+        let mut station = match result {
+            Ok(station) => station,
             Err(err) => {
                 //log error but continue anyway
                 eprintln!("Error deserializing station {}", err );
+                summary.parse_errors += 1;
+                continue;
+            }
+        };
+        //generate id after deserialization
+        station.generate_id();
+
+        if !coordinates_pass(&station.state_code, station.latitude, station.longitude, CoordinateCheck::Valid) {
+            summary.invalid_coordinates += 1;
+            continue;
+        }
+        //distances assume every site is on wgs84
+        if station.normalize_datum() {
+            summary.converted_nad27 += 1;
+        }
+        if !coordinates_pass(&station.state_code, station.latitude, station.longitude, policy.coordinates) {
+            summary.outside_territory += 1;
+            continue;
+        }
+        if let (Some(reference), Some(closed)) = (policy.closed_as_of, station.date_closed) {
+            if closed <= reference {
+                summary.closed += 1;
+                continue;
+            }
+        }
+        let key = ((station.latitude / DUPLICATE_PRECISION).round() as i64,
+                   (station.longitude / DUPLICATE_PRECISION).round() as i64);
+        if let Some(first) = seen.get(&key) {
+            summary.duplicate_coordinates += 1;
+            summary.duplicates.push((station.id.clone(), first.clone()));
+            if policy.duplicates == DuplicateCoordinates::KeepFirst {
+                summary.duplicates_dropped += 1;
+                continue;
             }
+        } else {
+            seen.insert(key, station.id.clone());
         }
+        stations.push(station);
     }
-    println!("loaded {} stations with valid coordinates", stations.len());
-    Ok(stations)
+    summary.loaded = stations.len();
+    Ok((stations, summary))
 }
 
 //pollution measurement struct for deserialization
@@ -50,62 +217,118 @@ struct PollutionMeasurement {
     site_number: String,
     #[serde(rename = "Parameter Code")]
     parameter_code: String,
+    #[serde(rename = "Parameter Name", default)]
+    parameter_name: String,
+    #[serde(rename = "POC")]
+    poc: u32,
+    #[serde(rename = "Method Code", default)]
+    method_code: Option<String>,
+    #[serde(rename = "Method Name", default)]
+    method_name: Option<String>,
     #[serde(rename = "Sample Duration")]
     sample_duration: String,
     #[serde(rename = "Pollutant Standard")]
     pollutant_standard: String,
+    #[serde(rename = "Event Type", default)]
+    event_type: Option<String>,
+    #[serde(rename = "Observation Count", default)]
+    observation_count: Option<u32>,
+    #[serde(rename = "Observation Percent", default)]
+    observation_percent: Option<f64>,
+    #[serde(rename = "Completeness Indicator", default)]
+    completeness_indicator: Option<String>,
     #[serde(rename = "Arithmetic Mean")]
     arithmetic_mean: Option<f64>,
 }
 
-//function to read the pollution data from csv using serde
+impl PollutionMeasurement {
+    //convert the raw csv record into a monitor
+    fn into_monitor(self) -> Monitor {
+        Monitor {
+            // create a unique ID given a state, county, and site code
+            site_id: format!("{}-{}-{}", self.state_code, self.county_code, self.site_number),
+            parameter_code: self.parameter_code,
+            parameter_name: self.parameter_name,
+            poc: self.poc,
+            method_code: self.method_code,
+            method_name: self.method_name,
+            sample_duration: self.sample_duration,
+            pollutant_standard: self.pollutant_standard,
+            //files without an event type column have no event variants
+            event_type: self.event_type.as_deref().map(EventType::from_aqs).unwrap_or(EventType::NoEvents),
+            observation_count: self.observation_count,
+            observation_percent: self.observation_percent,
+            //a missing or blank indicator is unknown rather than incomplete
+            complete: self.completeness_indicator.as_deref().map(str::trim).filter(|text| !text.is_empty())
+                .map(|text| text == "Y"),
+            arithmetic_mean: self.arithmetic_mean,
+        }
+    }
+}
+
+//function to read every monitor record from the annual concentration csv using serde
 //inputs: path to the csv file
-//outputs: result containing a hashmap of station id to pollution level or an error
-//focuses on pm2.5 measurements with consistent averaging periods
-pub fn read_pollution<P: AsRef<Path>>(path: P) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+//outputs: result containing a hashmap of station id to the monitors at that site or an error
+//keeps all parameters, POCs and pollutant standards so nothing is collapsed on load
+pub fn read_monitors<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Vec<Monitor>>, Box<dyn Error>> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
     let mut csv_reader = csv::ReaderBuilder::new().has_headers(true).from_reader(reader);
-    let mut pollution_data = HashMap::new();
-    //next two lines:
-    //focus on PM2.5 data, which is code 88101 (most important measure of air pollution, particulate matter of certain size)
-    //and getting the 24 hour block average measurements for consistency
-    let target_parameter = "88101";
-    let target_duration = "24-HR BLK AVG";
+    let mut sites: HashMap<String, Vec<Monitor>> = HashMap::new();
+    let mut count = 0;
 
     //using serde deserialization
     for result in csv_reader.deserialize::<PollutionMeasurement>() {
         match result {
             Ok(measurement) => {
-                //check if this is a PM2.5 record with appropriate averaging time
-                if measurement.parameter_code == target_parameter && 
-                measurement.sample_duration == target_duration {
-                    
-                    // create a unique ID given a state, county, and site code
-                    let id = format!("{}-{}-{}", 
-                        measurement.state_code, 
-                        measurement.county_code, 
-                        measurement.site_number);
-                    
-                    //use arithmetic mean if available
-                    if let Some(mean) = measurement.arithmetic_mean {
-                        //use PM25 Annual measurement for consistent averaging
-                        if measurement.pollutant_standard.contains("Annual") {
-                            pollution_data.insert(id, mean);
-                        } else if !pollution_data.contains_key(&id) {
-                            //if we don't have the annual average, use any 24-hour average.
-                            pollution_data.insert(id, mean);
-                        }
-                    }
-                }
+                let monitor = measurement.into_monitor();
+                sites.entry(monitor.site_id.clone()).or_default().push(monitor);
+                count += 1;
             },
             Err(err) => {
                 //log the error but continue processing
                 eprintln!("Error deserializing pollution data: {}", err);
             }
         }
-    } 
+    }
+    println!("Loaded {} monitor records at {} sites", count, sites.len());
+
+    Ok(sites)
+}
+
+//function to read the pollution data from csv using serde
+//inputs: path to the csv file
+//outputs: result containing a hashmap of station id to pollution level or an error
+//focuses on pm2.5 measurements with consistent averaging periods, using the primary monitor at each site
+pub fn read_pollution<P: AsRef<Path>>(path: P) -> Result<HashMap<String, f64>, Box<dyn Error>> {
+    read_pollution_with_events(path, EventSelection::IncludeEvents)
+}
+
+//function to read pm2.5 pollution data using a chosen exceptional-event variant
+//inputs: path to the csv file and which event-type records feed the values
+//outputs: result containing a hashmap of station id to pollution level or an error
+pub fn read_pollution_with_events<P: AsRef<Path>>(path: P, events: EventSelection)
+    -> Result<HashMap<String, f64>, Box<dyn Error>> {
+    let sites = read_monitors(path)?;
+    let mut aggregation = SiteAggregation::pm25();
+    aggregation.events = events;
+    let pollution_data = monitor::aggregate_sites(&sites, &aggregation);
     println!("Loaded pollution data for {} stations", pollution_data.len());
-    
+
     Ok(pollution_data)
-}
\ No newline at end of file
+}
+
+//function to read several years of pollution data at once
+//inputs: list of (year, path to that year's annual concentration csv) and the completeness filter applied
+//        to every year, so trends use the same monitors as a single year's analysis
+//outputs: result containing a map of year to (station id = pollution level) or an error
+pub fn read_pollution_years<P: AsRef<Path>>(files: &[(i32, P)], filter: &CompletenessFilter)
+    -> Result<BTreeMap<i32, HashMap<String, f64>>, Box<dyn Error>> {
+    let mut yearly = BTreeMap::new();
+    for (year, path) in files {
+        println!("Reading pollution data for {}...", year);
+        let (sites, _) = monitor::apply_completeness_filter(&read_monitors(path)?, filter);
+        yearly.insert(*year, monitor::aggregate_sites(&sites, &SiteAggregation::pm25()));
+    }
+    Ok(yearly)
+}
@@ -0,0 +1,173 @@
+//module for the environmental-justice overlay: monitoring coverage stratified by demographic indicators
+//joins tract-level percentiles (e.g. EJScreen low income or people of color) to nearest-monitor distances
+
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use crate::network::MonitoringNetwork;
+use crate::population::{self, CentroidCoverage};
+
+//length of a census tract geoid (state + county + tract), block-group geoids add one digit
+const TRACT_GEOID_LENGTH: usize = 11;
+
+//demographic indicators per census geography
+#[derive(Debug, Clone, Default)]
+pub struct DemographicTable {
+    pub indicators: Vec<String>,
+    pub values: HashMap<String, HashMap<String, f64>>, //geoid = (indicator = value)
+}
+
+impl DemographicTable {
+    //look up an indicator for a geoid, falling back to the containing tract for block groups
+    pub fn get(&self, geoid: &str, indicator: &str) -> Option<f64> {
+        self.values.get(geoid)
+            .or_else(|| geoid.get(..TRACT_GEOID_LENGTH).and_then(|tract| self.values.get(tract)))
+            .and_then(|row| row.get(indicator))
+            .copied()
+    }
+}
+
+//function to read demographic indicators from a csv
+//inputs: path to the csv and the name of the geoid column (e.g. "ID" in EJScreen files)
+//outputs: every other numeric column becomes an indicator, empty or non-numeric cells are skipped
+pub fn read_demographics<P: AsRef<Path>>(path: P, geoid_column: &str) -> Result<DemographicTable, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut csv_reader = csv::ReaderBuilder::new().has_headers(true).from_reader(reader);
+    let headers = csv_reader.headers()?.clone();
+    let id_index = headers.iter().position(|h| h == geoid_column)
+        .ok_or_else(|| format!("demographics file has no {} column", geoid_column))?;
+
+    let mut table = DemographicTable {
+        indicators: headers.iter().enumerate()
+            .filter(|(i, _)| *i != id_index)
+            .map(|(_, h)| h.to_string())
+            .collect(),
+        values: HashMap::new(),
+    };
+
+    for result in csv_reader.records() {
+        match result {
+            Ok(record) => {
+                let geoid = record.get(id_index).unwrap_or_default().to_string();
+                let mut row = HashMap::new();
+                for (i, header) in headers.iter().enumerate() {
+                    if i == id_index {
+                        continue;
+                    }
+                    if let Some(Ok(value)) = record.get(i).map(|cell| cell.trim().parse::<f64>()) {
+                        row.insert(header.to_string(), value);
+                    }
+                }
+                table.values.insert(geoid, row);
+            },
+            Err(err) => {
+                //log error but continue anyway
+                eprintln!("Error reading demographics record {}", err);
+            }
+        }
+    }
+    println!("Loaded demographics for {} areas", table.values.len());
+    Ok(table)
+}
+
+//coverage and pollution statistics for one demographic decile
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecileStatistics {
+    pub decile: u8, //1 = lowest 10% of the indicator, 10 = highest
+    pub area_count: usize,
+    pub population: f64,
+    pub mean_distance_km: f64, //population-weighted distance to nearest monitor
+    pub percent_within: Vec<(f64, f64)>, //(radius km, % of population within that radius)
+    pub mean_pollution: Option<f64>, //population-weighted value at the nearest monitor
+    pub mean_isolation_km: Option<f64>, //population-weighted isolation of the nearest monitor
+    pub gap_population_percent: f64, //% of population whose nearest monitor is a monitoring gap
+}
+
+//decile (1-10) of a percentile value from 0 to 100
+fn decile_of(percentile: f64) -> u8 {
+    ((percentile / 10.0).floor().clamp(0.0, 9.0) as u8) + 1
+}
+
+//function to stratify coverage by deciles of a demographic indicator
+//inputs: nearest-monitor distances, demographics, the indicator (a 0-100 percentile), the network
+//        (for isolation and gap detection), pollution per station, and coverage radii
+//gaps use the same 75th percentile thresholds as find_and_print_monitoring_gaps
+//outputs: statistics for every decile that has at least one area, lowest decile first
+pub fn stratify_by_decile(coverage: &[CentroidCoverage], demographics: &DemographicTable, indicator: &str,
+                          network: &MonitoringNetwork, pollution_data: &HashMap<String, f64>,
+                          radii_km: &[f64]) -> Vec<DecileStatistics> {
+    let gap_ids = network.default_gap_ids(pollution_data);
+
+    let mut by_decile: Vec<Vec<&CentroidCoverage>> = vec![Vec::new(); 10];
+    for record in coverage {
+        if let Some(percentile) = demographics.get(&record.geoid, indicator) {
+            by_decile[(decile_of(percentile) - 1) as usize].push(record);
+        }
+    }
+
+    let mut results = Vec::new();
+    for (i, records) in by_decile.iter().enumerate() {
+        if records.is_empty() {
+            continue;
+        }
+        let owned: Vec<CentroidCoverage> = records.iter().map(|r| (*r).clone()).collect();
+        let summary = population::coverage_statistics("", &owned, radii_km);
+
+        //population-weighted means of values looked up at each area's nearest monitor
+        let weighted_mean = |lookup: &dyn Fn(&str) -> Option<f64>| {
+            let mut weight = 0.0;
+            let mut total = 0.0;
+            for record in records {
+                if let Some(value) = record.nearest_station.as_deref().and_then(lookup) {
+                    weight += record.population;
+                    total += record.population * value;
+                }
+            }
+            if weight > 0.0 { Some(total / weight) } else { None }
+        };
+        let mean_pollution = weighted_mean(&|id| pollution_data.get(id).copied());
+        let mean_isolation_km = weighted_mean(&|id| network.isolation(id));
+
+        let gap_population: f64 = records.iter()
+            .filter(|r| r.nearest_station.as_ref().is_some_and(|id| gap_ids.contains(id)))
+            .map(|r| r.population)
+            .sum();
+
+        results.push(DecileStatistics {
+            decile: i as u8 + 1,
+            area_count: records.len(),
+            population: summary.total_population,
+            mean_distance_km: summary.mean_distance_km,
+            percent_within: summary.percent_within,
+            mean_pollution,
+            mean_isolation_km,
+            gap_population_percent: if summary.total_population > 0.0 {
+                gap_population / summary.total_population * 100.0
+            } else {
+                0.0
+            },
+        });
+    }
+    results
+}
+
+//function to print decile statistics for one indicator
+pub fn print_decile_statistics(indicator: &str, statistics: &[DecileStatistics]) {
+    println!("Monitoring coverage by {} decile:", indicator);
+    for stats in statistics {
+        let percents: Vec<String> = stats.percent_within.iter()
+            .map(|(radius, percent)| format!("{:.1}% <{:.0} km", percent, radius))
+            .collect();
+        println!("  Decile {:>2}: population {:.0}, mean distance {:.2} km, {}, pollution {}, gap population {:.1}%",
+                 stats.decile,
+                 stats.population,
+                 stats.mean_distance_km,
+                 percents.join(", "),
+                 stats.mean_pollution.map(|p| format!("{:.2}", p)).unwrap_or_else(|| "n/a".to_string()),
+                 stats.gap_population_percent);
+    }
+}
@@ -0,0 +1,309 @@
+//module for filter expressions over station attributes, used to build sub-networks before analysis
+//
+//grammar:
+//  expression  = and ( "||" and )*
+//  and         = unary ( "&&" unary )*
+//  unary       = "!" unary | "(" expression ")" | comparison
+//  comparison  = field op value
+//  op          = "==" | "!=" | "<" | "<=" | ">" | ">="
+//  value       = "quoted text" | number
+//
+//text fields only support == and != and compare case-insensitively, numeric fields support every op
+//a station without a value for the field (no cbsa, isolation not yet calculated) never matches a comparison,
+//with either == or !=, so !(cbsa_name != "") finds sites outside any cbsa
+//parentheses and ! nest at most 64 deep, and an expression has at most 1000 comparisons
+//example: state_name == "California" && location_setting == "RURAL" && isolation > 50
+
+
+use std::error::Error;
+use std::fmt;
+use crate::metrics::{MetricsTable, ISOLATION};
+use crate::station::Station;
+
+//fields that can be used in an expression
+const TEXT_FIELDS: [&str; 13] = ["id", "state_code", "county_code", "site_number", "site_name", "state_name",
+                                 "county_name", "city_name", "land_use", "location_setting", "cbsa_name",
+                                 "tribe_name", "datum"];
+const NUMBER_FIELDS: [&str; 4] = ["latitude", "longitude", "elevation", "isolation"];
+//limits that keep the recursive parser and evaluator from overflowing the stack
+const MAX_NESTING: usize = 64;
+const MAX_COMPARISONS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Text(String),
+    Number(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare { field: String, op: Op, value: Value },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Text(String),
+    Number(f64),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+//a parsed filter expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationFilter {
+    source: String,
+    expr: Expr,
+}
+
+impl fmt::Display for StationFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl StationFilter {
+    //function to parse a filter expression
+    //outputs: an error naming the position of the first problem, or of an unknown field
+    pub fn parse(source: &str) -> Result<Self, Box<dyn Error>> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, position: 0, depth: 0, comparisons: 0 };
+        let expr = parser.expression()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(format!("unexpected {:?} after the end of the expression", token).into());
+        }
+        Ok(StationFilter { source: source.to_string(), expr })
+    }
+
+    //check whether a station passes the filter, isolation is looked up in the metrics table
+    pub fn matches(&self, station: &Station, metrics: &MetricsTable) -> bool {
+        evaluate(&self.expr, station, metrics)
+    }
+}
+
+//value of a text field, none if the station has no value or the name is not a text field
+fn text_field<'a>(station: &'a Station, field: &str) -> Option<&'a str> {
+    match field {
+        "id" => Some(&station.id),
+        "state_code" => Some(&station.state_code),
+        "county_code" => Some(&station.county_code),
+        "site_number" => Some(&station.site_number),
+        "site_name" => Some(&station.site_name),
+        "state_name" => Some(&station.state_name),
+        "county_name" => Some(&station.county_name),
+        "city_name" => Some(&station.city_name),
+        "land_use" => Some(station.land_use.as_str()),
+        "location_setting" => Some(station.location_setting.as_str()),
+        "cbsa_name" => station.cbsa_name.as_deref(),
+        "tribe_name" => station.tribe_name.as_deref(),
+        "datum" => station.datum.as_ref().map(|datum| datum.as_str()),
+        _ => None,
+    }
+}
+
+//value of a numeric field, none if the station has no value
+fn number_field(station: &Station, metrics: &MetricsTable, field: &str) -> Option<f64> {
+    match field {
+        "latitude" => Some(station.latitude),
+        "longitude" => Some(station.longitude),
+        "elevation" => station.elevation,
+        "isolation" => metrics.number(ISOLATION, &station.id),
+        _ => None,
+    }
+}
+
+fn evaluate(expr: &Expr, station: &Station, metrics: &MetricsTable) -> bool {
+    match expr {
+        Expr::And(left, right) => evaluate(left, station, metrics) && evaluate(right, station, metrics),
+        Expr::Or(left, right) => evaluate(left, station, metrics) || evaluate(right, station, metrics),
+        Expr::Not(inner) => !evaluate(inner, station, metrics),
+        Expr::Compare { field, op, value: Value::Text(text) } => {
+            text_field(station, field).is_some_and(|actual| actual.eq_ignore_ascii_case(text) == (*op == Op::Eq))
+        },
+        Expr::Compare { field, op, value: Value::Number(number) } => {
+            number_field(station, metrics, field).is_some_and(|actual| match op {
+                Op::Eq => actual == *number,
+                Op::Ne => actual != *number,
+                Op::Lt => actual < *number,
+                Op::Le => actual <= *number,
+                Op::Gt => actual > *number,
+                Op::Ge => actual >= *number,
+            })
+        },
+    }
+}
+
+//split an expression into tokens
+fn tokenize(source: &str) -> Result<Vec<Token>, Box<dyn Error>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, length) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            },
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Op(Op::Eq), 2),
+            ('!', Some('=')) => (Token::Op(Op::Ne), 2),
+            ('<', Some('=')) => (Token::Op(Op::Le), 2),
+            ('>', Some('=')) => (Token::Op(Op::Ge), 2),
+            ('<', _) => (Token::Op(Op::Lt), 1),
+            ('>', _) => (Token::Op(Op::Gt), 1),
+            ('!', _) => (Token::Not, 1),
+            ('(', _) => (Token::Open, 1),
+            (')', _) => (Token::Close, 1),
+            ('"', _) => {
+                //quoted text, \" and \\ are escapes
+                let mut text = String::new();
+                let mut j = i + 1;
+                loop {
+                    match chars.get(j) {
+                        None => return Err(format!("unterminated text starting at position {}", i).into()),
+                        Some('"') => break,
+                        Some('\\') if j + 1 < chars.len() => {
+                            text.push(chars[j + 1]);
+                            j += 2;
+                        },
+                        Some(c) => {
+                            text.push(*c);
+                            j += 1;
+                        },
+                    }
+                }
+                (Token::Text(text), j + 1 - i)
+            },
+            (c, _) if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let length = chars[i..].iter()
+                    .enumerate()
+                    .take_while(|(j, c)| c.is_ascii_digit() || **c == '.' || (*j == 0 && **c == '-'))
+                    .count();
+                let text: String = chars[i..i + length].iter().collect();
+                let number = text.parse::<f64>().map_err(|_| format!("invalid number {} at position {}", text, i))?;
+                (Token::Number(number), length)
+            },
+            (c, _) if c.is_ascii_alphabetic() || c == '_' => {
+                let length = chars[i..].iter().take_while(|c| c.is_ascii_alphanumeric() || **c == '_').count();
+                (Token::Ident(chars[i..i + length].iter().collect()), length)
+            },
+            (c, _) => return Err(format!("unexpected character '{}' at position {}", c, i).into()),
+        };
+        tokens.push(token);
+        i += length;
+    }
+    Ok(tokens)
+}
+
+//recursive descent parser over the token list
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize, //open parentheses and ! around the current position
+    comparisons: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn expression(&mut self) -> Result<Expr, Box<dyn Error>> {
+        let mut left = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, Box<dyn Error>> {
+        let mut left = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Box<dyn Error>> {
+        match self.next() {
+            Some(Token::Not) => self.nested(|parser| Ok(Expr::Not(Box::new(parser.unary()?)))),
+            Some(Token::Open) => self.nested(|parser| {
+                let inner = parser.expression()?;
+                match parser.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err("missing closing parenthesis".into()),
+                }
+            }),
+            Some(Token::Ident(field)) => self.comparison(field),
+            Some(token) => Err(format!("expected a field name, found {:?}", token).into()),
+            None => Err("expression ended early".into()),
+        }
+    }
+
+    //parse one level deeper, failing past the nesting limit
+    fn nested(&mut self, parse: impl FnOnce(&mut Self) -> Result<Expr, Box<dyn Error>>) -> Result<Expr, Box<dyn Error>> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return Err(format!("expression nests more than {} levels deep", MAX_NESTING).into());
+        }
+        let expr = parse(self)?;
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn comparison(&mut self, field: String) -> Result<Expr, Box<dyn Error>> {
+        self.comparisons += 1;
+        if self.comparisons > MAX_COMPARISONS {
+            return Err(format!("expression has more than {} comparisons", MAX_COMPARISONS).into());
+        }
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            _ => return Err(format!("expected a comparison after {}", field).into()),
+        };
+        let value = match self.next() {
+            Some(Token::Text(text)) => Value::Text(text),
+            Some(Token::Number(number)) => Value::Number(number),
+            _ => return Err(format!("expected a quoted text or number after {}", field).into()),
+        };
+        let is_text = TEXT_FIELDS.contains(&field.as_str());
+        let is_number = NUMBER_FIELDS.contains(&field.as_str());
+        match (&value, is_text, is_number) {
+            (_, false, false) => Err(format!("unknown field {} (text fields: {}; numeric fields: {})",
+                                             field, TEXT_FIELDS.join(", "), NUMBER_FIELDS.join(", ")).into()),
+            (Value::Text(_), true, _) if op != Op::Eq && op != Op::Ne => {
+                Err(format!("{} is a text field and only supports == and !=", field).into())
+            },
+            (Value::Text(_), false, _) => Err(format!("{} is a numeric field, compare it with a number", field).into()),
+            (Value::Number(_), _, false) => Err(format!("{} is a text field, compare it with quoted text", field).into()),
+            _ => Ok(Expr::Compare { field, op, value }),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//module for comparing the monitoring network as it stood on different dates
+//answers which stations opened, which closed, and how isolation changed for the rest
+
+
+use std::cmp::Ordering;
+use chrono::NaiveDate;
+use crate::network::MonitoringNetwork;
+
+//change in isolation for a station present on both dates
+#[derive(Debug, Clone, PartialEq)]
+pub struct IsolationChange {
+    pub station_id: String,
+    pub before: f64,
+    pub after: f64,
+}
+
+impl IsolationChange {
+    //positive means the station became more isolated
+    pub fn change(&self) -> f64 {
+        self.after - self.before
+    }
+}
+
+//differences between the network on two dates
+#[derive(Debug, Clone)]
+pub struct NetworkDiff {
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub stations_before: usize,
+    pub stations_after: usize,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub isolation_changes: Vec<IsolationChange>, //sorted by largest increase in isolation first
+}
+
+impl NetworkDiff {
+    //mean change in isolation across stations present on both dates
+    pub fn mean_isolation_change(&self) -> Option<f64> {
+        if self.isolation_changes.is_empty() {
+            return None;
+        }
+        let sum: f64 = self.isolation_changes.iter().map(|c| c.change()).sum();
+        Some(sum / self.isolation_changes.len() as f64)
+    }
+
+    //function to print a summary of coverage change between the two dates
+    pub fn print_summary(&self, network: &MonitoringNetwork) {
+        println!("Network change from {} to {}:", self.from_date, self.to_date);
+        println!("  Stations: {} -> {}", self.stations_before, self.stations_after);
+        println!("  Opened: {}", self.added.len());
+        println!("  Closed: {}", self.removed.len());
+        if let Some(mean) = self.mean_isolation_change() {
+            println!("  Mean isolation change for remaining stations: {:+.2} km", mean);
+        }
+        println!("Stations that became most isolated:");
+        for (i, change) in self.isolation_changes.iter().filter(|c| c.change() > 0.0).take(10).enumerate() {
+            let name = network.stations.get(&change.station_id)
+                .map(|station| format!("{} ({})", station.site_name, station.state_name))
+                .unwrap_or_else(|| change.station_id.clone());
+            println!("  {}. {}: {:.2} km -> {:.2} km ({:+.2} km)",
+                     i+1, name, change.before, change.after, change.change());
+        }
+    }
+}
+
+//function to compare two networks that already have isolation calculated
+//inputs: the earlier and later networks and their dates
+//outputs: stations added and removed (sorted by id) and isolation changes for the rest
+pub fn compare_networks(before: &MonitoringNetwork, from_date: NaiveDate,
+                        after: &MonitoringNetwork, to_date: NaiveDate) -> NetworkDiff {
+    let mut added: Vec<String> = after.stations.keys()
+        .filter(|id| !before.stations.contains_key(*id))
+        .cloned()
+        .collect();
+    let mut removed: Vec<String> = before.stations.keys()
+        .filter(|id| !after.stations.contains_key(*id))
+        .cloned()
+        .collect();
+    added.sort();
+    removed.sort();
+
+    let mut isolation_changes = Vec::new();
+    for id in before.stations.keys() {
+        if let (Some(b), Some(a)) = (before.isolation(id), after.isolation(id)) {
+            isolation_changes.push(IsolationChange { station_id: id.clone(), before: b, after: a });
+        }
+    }
+    isolation_changes.sort_by(|a, b| b.change().partial_cmp(&a.change()).unwrap_or(Ordering::Equal));
+
+    NetworkDiff {
+        from_date,
+        to_date,
+        stations_before: before.stations.len(),
+        stations_after: after.stations.len(),
+        added,
+        removed,
+        isolation_changes,
+    }
+}
+
+//function to rebuild the network as it stood on two dates and compare them
+//inputs: full network with every station ever loaded, the two dates, k for the isolation metric
+pub fn compare_dates(network: &MonitoringNetwork, from_date: NaiveDate, to_date: NaiveDate,
+                     k: usize) -> NetworkDiff {
+    let mut before = network.as_of(from_date);
+    before.build_adjacency_list();
+    before.calculate_isolation(k);
+
+    let mut after = network.as_of(to_date);
+    after.build_adjacency_list();
+    after.calculate_isolation(k);
+
+    compare_networks(&before, from_date, &after, to_date)
+}
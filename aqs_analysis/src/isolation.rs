@@ -0,0 +1,240 @@
+//module for isolation metrics beyond the mean distance to the k nearest neighbors
+//each metric is stored in the network's metrics table under its name, so conclusions (correlation with pollution,
+//which stations are gaps) can be compared across metrics
+//knn metrics use the adjacency list as built (after any wind, terrain or zone changes); neighbor counts, kernel
+//sparsity and the voronoi area use station coordinates through the spatial index, so they see every station in
+//range rather than only the edges the adjacency list kept (which stop at 300 km)
+
+
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+use crate::metrics::{self, Provenance};
+use crate::network::MonitoringNetwork;
+use crate::spatial::EARTH_RADIUS_KM;
+use crate::trend;
+
+//vertices of the circle that bounds voronoi cells at the edge of the network
+const VORONOI_CIRCLE_VERTICES: usize = 64;
+//kernel sparsity ignores stations farther than this many bandwidths, their weight is below 4e-6
+const KERNEL_CUTOFF_BANDWIDTHS: f64 = 5.0;
+
+//ways of measuring how isolated a station is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IsolationMetric {
+    MeanKnn { k: usize }, //same as calculate_isolation (km)
+    NearestNeighbor, //distance to the closest neighbor (km)
+    MedianKnn { k: usize }, //km, less sensitive to one distant neighbor than the mean
+    HarmonicMeanKnn { k: usize }, //km, dominated by the closest neighbors
+    NeighborsWithin { radius_km: f64 }, //count of stations within the radius in km, fewer means more isolated
+    VoronoiArea { max_radius_km: f64 }, //km² of the voronoi cell, cells are cut off at the radius
+    KernelSparsity { bandwidth_km: f64 }, //km² per station, the inverse of a gaussian kernel density
+}
+
+impl IsolationMetric {
+    //name the values are stored under, e.g. "median_knn_10" or "neighbors_within_50km"
+    pub fn name(&self) -> String {
+        match self {
+            IsolationMetric::MeanKnn { k } => format!("mean_knn_{}", k),
+            IsolationMetric::NearestNeighbor => "nearest_neighbor".to_string(),
+            IsolationMetric::MedianKnn { k } => format!("median_knn_{}", k),
+            IsolationMetric::HarmonicMeanKnn { k } => format!("harmonic_knn_{}", k),
+            IsolationMetric::NeighborsWithin { radius_km } => format!("neighbors_within_{}km", radius_km),
+            IsolationMetric::VoronoiArea { max_radius_km } => format!("voronoi_area_{}km", max_radius_km),
+            IsolationMetric::KernelSparsity { bandwidth_km } => format!("kernel_sparsity_{}km", bandwidth_km),
+        }
+    }
+
+    //algorithm and parameters recorded with the metric's values
+    pub fn provenance(&self) -> Provenance {
+        match self {
+            IsolationMetric::MeanKnn { k } => Provenance::new("mean_knn").with("k", k),
+            IsolationMetric::NearestNeighbor => Provenance::new("nearest_neighbor"),
+            IsolationMetric::MedianKnn { k } => Provenance::new("median_knn").with("k", k),
+            IsolationMetric::HarmonicMeanKnn { k } => Provenance::new("harmonic_knn").with("k", k),
+            IsolationMetric::NeighborsWithin { radius_km } => Provenance::new("neighbors_within").with("radius_km", radius_km),
+            IsolationMetric::VoronoiArea { max_radius_km } => Provenance::new("voronoi_area").with("max_radius_km", max_radius_km),
+            IsolationMetric::KernelSparsity { bandwidth_km } => Provenance::new("kernel_sparsity").with("bandwidth_km", bandwidth_km),
+        }
+    }
+
+    //whether larger values mean a more isolated station (false for neighbor counts)
+    pub fn larger_is_more_isolated(&self) -> bool {
+        !matches!(self, IsolationMetric::NeighborsWithin { .. })
+    }
+}
+
+//the metrics compared by default, k is the neighbor count used for the knn metrics
+pub fn standard_metrics(k: usize) -> Vec<IsolationMetric> {
+    vec![
+        IsolationMetric::MeanKnn { k },
+        IsolationMetric::NearestNeighbor,
+        IsolationMetric::MedianKnn { k },
+        IsolationMetric::HarmonicMeanKnn { k },
+        IsolationMetric::NeighborsWithin { radius_km: 50.0 },
+        IsolationMetric::VoronoiArea { max_radius_km: 100.0 },
+        IsolationMetric::KernelSparsity { bandwidth_km: 25.0 },
+    ]
+}
+
+//function to calculate a metric for every station in the network
+//outputs: station_id = value, knn metrics skip stations without neighbors
+pub fn compute_metric(network: &MonitoringNetwork, metric: &IsolationMetric) -> HashMap<String, f64> {
+    let empty = Vec::new();
+    network.stations.iter()
+        .filter_map(|(id, station)| {
+            let neighbors = network.adjacency_list.get(id).unwrap_or(&empty);
+            let distances: Vec<f64> = neighbors.iter().map(|(_, distance)| *distance).collect();
+            let value = match metric {
+                IsolationMetric::MeanKnn { k } => {
+                    let nearest = &distances[..distances.len().min(*k)];
+                    (!nearest.is_empty()).then(|| nearest.iter().sum::<f64>() / nearest.len() as f64)
+                },
+                IsolationMetric::NearestNeighbor => distances.first().copied(),
+                IsolationMetric::MedianKnn { k } => trend::median(&mut distances[..distances.len().min(*k)].to_vec()),
+                IsolationMetric::HarmonicMeanKnn { k } => {
+                    let nearest = &distances[..distances.len().min(*k)];
+                    match nearest {
+                        [] => None,
+                        _ if nearest.contains(&0.0) => Some(0.0),
+                        _ => Some(nearest.len() as f64 / nearest.iter().map(|d| 1.0 / d).sum::<f64>()),
+                    }
+                },
+                IsolationMetric::NeighborsWithin { radius_km } => {
+                    let found = network.spatial_index().within(station.latitude, station.longitude, *radius_km);
+                    Some(found.iter().filter(|(other, _)| other != id).count() as f64)
+                },
+                IsolationMetric::VoronoiArea { max_radius_km } => voronoi_area(network, id, *max_radius_km),
+                IsolationMetric::KernelSparsity { bandwidth_km } => {
+                    //the station itself contributes exp(0) = 1
+                    let cutoff = KERNEL_CUTOFF_BANDWIDTHS * bandwidth_km;
+                    let weight: f64 = 1.0 + network.spatial_index().within(station.latitude, station.longitude, cutoff).iter()
+                        .filter(|(other, _)| other != id)
+                        .map(|(_, distance)| (-distance * distance / (2.0 * bandwidth_km * bandwidth_km)).exp())
+                        .sum::<f64>();
+                    let density = weight / (2.0 * PI * bandwidth_km * bandwidth_km);
+                    Some(1.0 / density)
+                },
+            };
+            value.map(|value| (id.clone(), value))
+        })
+        .collect()
+}
+
+//area of a station's voronoi cell in km², on a local equirectangular projection centered on the station
+//the cell starts as a circle of max_radius_km and is cut by the bisector with each station within twice the
+//radius, found with the spatial index so adjacency limits and distance weighting do not change the cell
+fn voronoi_area(network: &MonitoringNetwork, id: &str, max_radius_km: f64) -> Option<f64> {
+    let station = network.stations.get(id)?;
+    let km_per_degree = EARTH_RADIUS_KM * PI / 180.0;
+    let project = |lat: f64, lon: f64| {
+        ((lon - station.longitude) * station.latitude.to_radians().cos() * km_per_degree,
+         (lat - station.latitude) * km_per_degree)
+    };
+    let mut cell: Vec<(f64, f64)> = (0..VORONOI_CIRCLE_VERTICES)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f64 / VORONOI_CIRCLE_VERTICES as f64;
+            (max_radius_km * angle.cos(), max_radius_km * angle.sin())
+        })
+        .collect();
+
+    //stations beyond twice the radius cannot cut the circle
+    for (neighbor_id, distance) in network.spatial_index().within(station.latitude, station.longitude, 2.0 * max_radius_km) {
+        //the station itself and colocated stations have no bisector
+        if distance < 1e-6 {
+            continue;
+        }
+        let Some(neighbor) = network.stations.get(&neighbor_id) else { continue };
+        let (qx, qy) = project(neighbor.latitude, neighbor.longitude);
+        cell = clip_half_plane(&cell, qx, qy, (qx * qx + qy * qy) / 2.0);
+    }
+
+    //shoelace formula
+    let area: f64 = (0..cell.len())
+        .map(|i| {
+            let (x1, y1) = cell[i];
+            let (x2, y2) = cell[(i + 1) % cell.len()];
+            x1 * y2 - x2 * y1
+        })
+        .sum::<f64>() / 2.0;
+    Some(area.abs())
+}
+
+//keep the part of a convex polygon where a*x + b*y <= c (sutherland-hodgman for one edge)
+fn clip_half_plane(polygon: &[(f64, f64)], a: f64, b: f64, c: f64) -> Vec<(f64, f64)> {
+    let inside = |(x, y): (f64, f64)| a * x + b * y <= c;
+    let mut clipped = Vec::new();
+    for i in 0..polygon.len() {
+        let current = polygon[i];
+        let next = polygon[(i + 1) % polygon.len()];
+        if inside(current) {
+            clipped.push(current);
+        }
+        if inside(current) != inside(next) {
+            let (fc, fn_) = (a * current.0 + b * current.1 - c, a * next.0 + b * next.1 - c);
+            let t = fc / (fc - fn_);
+            clipped.push((current.0 + t * (next.0 - current.0), current.1 + t * (next.1 - current.1)));
+        }
+    }
+    clipped
+}
+
+//how one metric changes the conclusions of the analysis
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricComparison {
+    pub metric: String,
+    pub station_count: usize, //stations with a value
+    pub correlation: Option<f64>, //with pollution, none with fewer than 3 pairs; negative for counts when isolation raises pollution
+    pub gap_count: usize, //top quartile of isolation (bottom for counts) and of pollution
+    pub gap_overlap: Option<f64>, //jaccard index of the gaps against the first metric, none if both have no gaps
+}
+
+//function to compare calculated metrics by their correlation with pollution and the gaps they flag
+//inputs: the network with the metrics calculated (calculate_isolation_metric), pollution per station and the
+//        metrics to compare, the first one is the reference for gap overlap
+pub fn compare_metrics(network: &MonitoringNetwork, pollution_data: &HashMap<String, f64>,
+                       metrics: &[IsolationMetric]) -> Vec<MetricComparison> {
+    let mut reference: Option<HashSet<String>> = None;
+    metrics.iter().map(|metric| {
+        let values = network.metrics.numbers(&metric.name());
+        let paired = values.keys().filter(|id| pollution_data.contains_key(*id)).count();
+        let gaps = metric_gap_ids(&values, metric.larger_is_more_isolated(), pollution_data);
+        let reference_gaps = reference.get_or_insert_with(|| gaps.clone());
+        let union = reference_gaps.union(&gaps).count();
+        MetricComparison {
+            metric: metric.name(),
+            station_count: values.len(),
+            correlation: if paired >= 3 { metrics::correlation(&values, pollution_data) } else { None },
+            gap_count: gaps.len(),
+            gap_overlap: (union > 0).then(|| reference_gaps.intersection(&gaps).count() as f64 / union as f64),
+        }
+    }).collect()
+}
+
+//stations above the 75th percentile of isolation (below the 25th for counts) and of pollution
+fn metric_gap_ids(values: &HashMap<String, f64>, larger_is_more_isolated: bool,
+                  pollution_data: &HashMap<String, f64>) -> HashSet<String> {
+    let pollution_threshold = metrics::percentile(pollution_data, 0.75);
+    let isolation_threshold = metrics::percentile(values, if larger_is_more_isolated { 0.75 } else { 0.25 });
+    let (Some(pollution_threshold), Some(isolation_threshold)) = (pollution_threshold, isolation_threshold) else {
+        return HashSet::new();
+    };
+    values.iter()
+        .filter(|(id, value)| {
+            let isolated = if larger_is_more_isolated { **value > isolation_threshold } else { **value < isolation_threshold };
+            isolated && pollution_data.get(*id).is_some_and(|pollution| *pollution > pollution_threshold)
+        })
+        .map(|(id, _)| id.clone())
+        .collect()
+}
+
+//function to print the metric comparison as a table
+pub fn print_metric_comparison(comparisons: &[MetricComparison]) {
+    println!("Isolation metric comparison (gap overlap against {}):",
+             comparisons.first().map(|c| c.metric.as_str()).unwrap_or("n/a"));
+    for comparison in comparisons {
+        let correlation = comparison.correlation.map(|r| format!("{:.4}", r)).unwrap_or_else(|| "n/a".into());
+        let overlap = comparison.gap_overlap.map(|j| format!("{:.0}%", j * 100.0)).unwrap_or_else(|| "n/a".into());
+        println!("  {}: {} stations, correlation {}, {} gaps, overlap {}",
+                 comparison.metric, comparison.station_count, correlation, comparison.gap_count, overlap);
+    }
+}
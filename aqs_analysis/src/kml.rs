@@ -0,0 +1,110 @@
+//module for exporting the network to kml/kmz so candidate gaps can be reviewed in google earth
+//stations become placemarks grouped into one folder per state, styled by gap status
+
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use crate::network::MonitoringNetwork;
+use crate::report::escape;
+use crate::station::Station;
+
+//style ids, with the icon color in kml's aabbggrr order
+const STYLES: [(&str, &str, &str); 3] = [
+    ("gap", "ff0000ff", "Monitoring gap"),
+    ("monitored", "ff00b400", "Monitored station"),
+    ("no_data", "ff999999", "No pollution data"),
+];
+//icon used for every style, tinted by the style color
+const ICON_HREF: &str = "http://maps.google.com/mapfiles/kml/shapes/placemark_circle.png";
+
+//style of a station: gaps first, then whether it has a pollution value
+fn style_id(station: &Station, pollution_data: &HashMap<String, f64>, gaps: &HashSet<String>) -> &'static str {
+    if gaps.contains(&station.id) {
+        "gap"
+    } else if pollution_data.contains_key(&station.id) {
+        "monitored"
+    } else {
+        "no_data"
+    }
+}
+
+//function to build a kml document for the network
+//inputs: the network, pollution per station, ids of stations flagged as monitoring gaps,
+//        and the document name shown in google earth
+pub fn render_kml(network: &MonitoringNetwork, pollution_data: &HashMap<String, f64>, gaps: &HashSet<String>,
+                  name: &str) -> String {
+    let mut kml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                                <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n");
+    let _ = writeln!(kml, "<name>{}</name>", escape(name));
+    for (id, color, _) in STYLES {
+        let _ = writeln!(kml, "<Style id=\"{}\"><IconStyle><color>{}</color><scale>{}</scale>\
+                               <Icon><href>{}</href></Icon></IconStyle></Style>",
+                         id, color, if id == "gap" { "1.2" } else { "0.8" }, ICON_HREF);
+    }
+
+    //one folder per state, sorted by state name then site name
+    let mut by_state: BTreeMap<&str, Vec<&Station>> = BTreeMap::new();
+    for station in network.stations.values() {
+        by_state.entry(station.state_name.as_str()).or_default().push(station);
+    }
+    for (state, mut stations) in by_state {
+        stations.sort_by(|a, b| a.site_name.cmp(&b.site_name).then_with(|| a.id.cmp(&b.id)));
+        let gap_count = stations.iter().filter(|s| gaps.contains(&s.id)).count();
+        let _ = writeln!(kml, "<Folder>\n<name>{}</name>\n<description>{} stations, {} monitoring gaps</description>",
+                         escape(state), stations.len(), gap_count);
+        for station in stations {
+            placemark(&mut kml, station, network.isolation(&station.id), pollution_data.get(&station.id).copied(),
+                      style_id(station, pollution_data, gaps));
+        }
+        kml.push_str("</Folder>\n");
+    }
+    kml.push_str("</Document>\n</kml>\n");
+    kml
+}
+
+//one station placemark with its balloon table
+fn placemark(kml: &mut String, station: &Station, isolation: Option<f64>, pollution: Option<f64>, style: &str) {
+    let status = STYLES.iter().find(|(id, _, _)| *id == style).map(|(_, _, label)| *label).unwrap_or_default();
+    let rows = [
+        ("Site", escape(&station.site_name)),
+        ("Site ID", escape(&station.id)),
+        ("City", escape(&station.city_name)),
+        ("County", escape(&station.county_name)),
+        ("Land use", escape(station.land_use.as_str())),
+        ("Location setting", escape(station.location_setting.as_str())),
+        ("Isolation", isolation.map(|v| format!("{:.2} km", v)).unwrap_or_else(|| "n/a".into())),
+        ("Pollution", pollution.map(|v| format!("{:.2}", v)).unwrap_or_else(|| "n/a".into())),
+        ("Status", status.to_string()),
+    ];
+    let _ = writeln!(kml, "<Placemark>\n<name>{}</name>\n<styleUrl>#{}</styleUrl>", escape(&station.site_name), style);
+    //values are already escaped, so the table cannot close the cdata section early
+    kml.push_str("<description><![CDATA[<table>");
+    for (label, value) in rows {
+        let _ = write!(kml, "<tr><th align=\"left\">{}</th><td>{}</td></tr>", label, value);
+    }
+    kml.push_str("</table>]]></description>\n");
+    let _ = writeln!(kml, "<Point><coordinates>{},{},0</coordinates></Point>\n</Placemark>",
+                     station.longitude, station.latitude);
+}
+
+//function to write the network as a kml file
+pub fn write_kml<P: AsRef<Path>>(network: &MonitoringNetwork, pollution_data: &HashMap<String, f64>,
+                                 gaps: &HashSet<String>, name: &str, path: P) -> Result<(), Box<dyn Error>> {
+    fs::write(path, render_kml(network, pollution_data, gaps, name))?;
+    Ok(())
+}
+
+//function to write the network as a kmz file (a zip archive holding doc.kml)
+pub fn write_kmz<P: AsRef<Path>>(network: &MonitoringNetwork, pollution_data: &HashMap<String, f64>,
+                                 gaps: &HashSet<String>, name: &str, path: P) -> Result<(), Box<dyn Error>> {
+    let mut archive = zip::ZipWriter::new(File::create(path)?);
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    archive.start_file("doc.kml", options)?;
+    archive.write_all(render_kml(network, pollution_data, gaps, name).as_bytes())?;
+    archive.finish()?;
+    Ok(())
+}
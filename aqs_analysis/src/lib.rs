@@ -1,4 +1,25 @@
 // Make modules publicly accessible for testing
 pub mod station;
 pub mod network;
-pub mod data;
\ No newline at end of file
+pub mod data;
+pub mod trend;
+pub mod history;
+pub mod monitor;
+pub mod spatial;
+pub mod population;
+pub mod equity;
+pub mod snapshot;
+pub mod server;
+pub mod report;
+pub mod render;
+pub mod kml;
+pub mod filter;
+pub mod adequacy;
+pub mod aggregate;
+pub mod wind;
+pub mod terrain;
+pub mod zones;
+pub mod isolation;
+pub mod metrics;
+#[cfg(feature = "sqlite")]
+pub mod storage;
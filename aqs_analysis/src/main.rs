@@ -1,16 +1,65 @@
 //main module that coordinates the overall analysis workflow for the air quality monitoring network
 
 
-mod station;
-mod network;
-mod data;
 use std::error::Error;
-use network::MonitoringNetwork;
-fn main() -> Result<(), Box<dyn Error>> {
+use chrono::NaiveDate;
+use aqs_analysis::adequacy::{self, Pollutant};
+use aqs_analysis::aggregate::{self, GroupBy};
+use aqs_analysis::data::{self, CoordinateCheck, DuplicateCoordinates, LoadPolicy};
+use aqs_analysis::equity;
+use aqs_analysis::filter::StationFilter;
+use aqs_analysis::isolation;
+use aqs_analysis::kml;
+use aqs_analysis::metrics::{self, Provenance};
+use aqs_analysis::monitor::{self, CompletenessFilter, ParameterStatistics, SiteAggregation};
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::population;
+use aqs_analysis::render::{self, MapOptions, StationMetric};
+use aqs_analysis::report;
+use aqs_analysis::server::{self, ApiServer};
+use aqs_analysis::snapshot;
+use aqs_analysis::terrain::{self, TerrainPenalty};
+use aqs_analysis::wind::{self, WindWeighting};
+use aqs_analysis::zones::{self, ZoneAssignment};
+//snapshot of the network built from aqs_sites.csv, reused while the csv is unchanged
+const SNAPSHOT_PATH: &str = "network_snapshot.bin";
+//html report written at the end of the gap analysis
+const REPORT_PATH: &str = "network_report.html";
+//svg map of isolation across the conus stations with gaps marked
+const MAP_PATH: &str = "network_map.svg";
+//placemarks for reviewing gaps in google earth
+const KMZ_PATH: &str = "monitoring_gaps.kmz";
+//per-state and per-county tables of the network metrics
+const STATE_TABLE_PATH: &str = "state_statistics.csv";
+const COUNTY_TABLE_PATH: &str = "county_statistics.csv";
+//radius (km) within which a census centroid counts as covered in the state and county tables
+const TABLE_COVERAGE_RADIUS_KM: f64 = 25.0;
+//optional elevation grid (esri ascii) used to penalize neighbors separated by ridges
+const DEM_PATH: &str = "elevation.asc";
+//optional wind roses (per station or gridded) used to weight neighbor distances by wind direction
+const WIND_ROSE_PATH: &str = "wind_roses.csv";
+//optional air basin polygons, the first file found is used, zone names come from the NAME property/field
+const ZONE_PATHS: [&str; 2] = ["air_basins.geojson", "air_basins.shp"];
+const ZONE_NAME_KEY: &str = "NAME";
+//per-zone table of the network metrics
+const ZONE_TABLE_PATH: &str = "zone_statistics.csv";
+//every per-station metric (isolation variants and pm2.5) with its provenance, one row per station and metric
+const METRICS_TABLE_PATH: &str = "station_metrics.csv";
+
+//load the network from a current snapshot, otherwise build it from the station csv and save a snapshot
+//a snapshot saved with a different load policy is rebuilt
+fn load_or_build_network(policy: &LoadPolicy) -> Result<MonitoringNetwork, Box<dyn Error>> {
+    if let Ok(saved) = snapshot::load_snapshot(SNAPSHOT_PATH) {
+        if saved.is_current(policy) {
+            println!("Loaded network snapshot with {} stations", saved.network.stations.len());
+            return Ok(saved.network);
+        }
+    }
+
     //read the station data
     println!("Reading station data...");
-    let stations = data::read_stations("aqs_sites.csv")?;
-    println!("Loaded {} stations", stations.len());
+    let (stations, summary) = data::read_stations_with_policy("aqs_sites.csv", policy)?;
+    summary.print();
     
     //create monitoring network
     let mut network = MonitoringNetwork::new();
@@ -27,14 +76,162 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("Calculating isolation metrics (distance to 10 nearest neighbors)...");
     network.calculate_isolation(10);
     println!("Calculated isolation metrics");
+
+    //save for the next run
+    if let Err(err) = snapshot::save_snapshot(&network, policy, &["aqs_sites.csv"], SNAPSHOT_PATH) {
+        eprintln!("Could not save network snapshot: {}", err);
+    }
+    Ok(network)
+}
+
+//restrict the network to the stations passing a --filter expression, with adjacency and isolation
+//recalculated inside the sub-network
+fn apply_filter(network: MonitoringNetwork, filter: Option<&StationFilter>) -> MonitoringNetwork {
+    let Some(filter) = filter else {
+        return network;
+    };
+    let mut filtered = network.filtered(filter);
+    println!("Filter {} kept {} of {} stations", filter, filtered.stations.len(), network.stations.len());
+    filtered.build_adjacency_list();
+    filtered.calculate_isolation(10);
+    filtered
+}
+
+//penalize edges crossing ridges and weight them by wind direction, if a dem or wind roses are available,
+//then recalculate isolation; the snapshot keeps plain distances, so the weights are applied once per run
+fn apply_edge_weights(mut network: MonitoringNetwork) -> Result<MonitoringNetwork, Box<dyn Error>> {
+    let has_dem = std::path::Path::new(DEM_PATH).exists();
+    let has_wind = std::path::Path::new(WIND_ROSE_PATH).exists();
+    if has_dem {
+        println!("Penalizing neighbor distances by ridge crossings...");
+        let grid = terrain::read_ascii_grid(DEM_PATH)?;
+        let separated = network.apply_terrain_penalty(&grid, &TerrainPenalty::default())?;
+        println!("Found {} station pairs separated by ridges", separated);
+    }
+    if has_wind {
+        let roses = wind::read_wind_roses(WIND_ROSE_PATH)?;
+        let weighted = network.apply_wind_weighting(&roses, &WindWeighting::default());
+        println!("Weighted neighbor distances by wind direction for {} of {} stations", weighted, network.stations.len());
+    }
+    if has_dem || has_wind {
+        network.calculate_isolation(10);
+    }
+    Ok(network)
+}
+
+//assign stations to air basins if a zone file is available, and unless cross_zones is set drop the edges
+//between basins and recalculate isolation
+//outputs: the network and the zone of each station, none without a zone file
+fn apply_zones(mut network: MonitoringNetwork, cross_zones: bool)
+               -> Result<(MonitoringNetwork, Option<ZoneAssignment>), Box<dyn Error>> {
+    let Some(path) = ZONE_PATHS.into_iter().find(|path| std::path::Path::new(path).exists()) else {
+        return Ok((network, None));
+    };
+    let zones = zones::read_zones(path, ZONE_NAME_KEY)?;
+    let zone_of = zones::assign_zones(&network, &zones);
+    println!("Assigned {} of {} stations to {} zones", zone_of.len(), network.stations.len(), zones.len());
+    if !cross_zones {
+        let removed = network.restrict_to_zones(&zone_of);
+        println!("Removed {} edges crossing zone boundaries", removed);
+        network.calculate_isolation(10);
+    }
+    Ok((network, Some(zone_of)))
+}
+
+//load the network and apply the filter, edge weights and zones in order
+fn prepare_network(policy: &LoadPolicy, filter: Option<&StationFilter>, cross_zones: bool)
+                   -> Result<(MonitoringNetwork, Option<ZoneAssignment>), Box<dyn Error>> {
+    let network = apply_edge_weights(apply_filter(load_or_build_network(policy)?, filter))?;
+    apply_zones(network, cross_zones)
+}
+
+//read the annual monitor file, attach it to the network and drop incomplete monitors
+fn load_monitors(network: &mut MonitoringNetwork) -> Result<Vec<ParameterStatistics>, Box<dyn Error>> {
+    //read pollution data, keeping every monitor at each site
+    println!("Reading pollution data...");
+    let monitors = data::read_monitors("annual_conc_by_monitor_2023.csv")?;
+    network.attach_monitors(monitors);
+
+    //drop monitors that did not collect enough samples during the year
+    Ok(network.apply_completeness_filter(&CompletenessFilter::epa_standard()))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = std::env::args().collect();
+
+    //`--filter EXPR` limits every analysis to a sub-network, e.g. --filter 'state_name == "Ohio"'
+    let filter = match args.iter().position(|arg| arg == "--filter") {
+        Some(i) if i + 1 < args.len() => {
+            let expression = args.remove(i + 1);
+            args.remove(i);
+            Some(StationFilter::parse(&expression)?)
+        },
+        Some(_) => return Err("--filter needs an expression".into()),
+        None => None,
+    };
+
+    //`--cross-zones` keeps edges between air basins when a zone file is present
+    let cross_zones = match args.iter().position(|arg| arg == "--cross-zones") {
+        Some(i) => {
+            args.remove(i);
+            true
+        },
+        None => false,
+    };
+
+    //station load policy, the defaults keep every site with valid coordinates
+    //`--closed-before DATE` drops sites closed on or before the date, e.g. --closed-before 2023-01-01
+    let closed_as_of = match args.iter().position(|arg| arg == "--closed-before") {
+        Some(i) if i + 1 < args.len() => {
+            let date = args.remove(i + 1);
+            args.remove(i);
+            Some(NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|err| format!("--closed-before {}: {}", date, err))?)
+        },
+        Some(_) => return Err("--closed-before needs a date (YYYY-MM-DD)".into()),
+        None => None,
+    };
+    //`--us-only` drops sites outside us states and territories
+    let coordinates = match args.iter().position(|arg| arg == "--us-only") {
+        Some(i) => {
+            args.remove(i);
+            CoordinateCheck::UsTerritory
+        },
+        None => CoordinateCheck::Valid,
+    };
+    //`--drop-duplicate-coordinates` keeps only the first site at each location
+    let duplicates = match args.iter().position(|arg| arg == "--drop-duplicate-coordinates") {
+        Some(i) => {
+            args.remove(i);
+            DuplicateCoordinates::KeepFirst
+        },
+        None => DuplicateCoordinates::Keep,
+    };
+    let policy = LoadPolicy { closed_as_of, coordinates, duplicates };
+
+    //`aqs_analysis serve [address]` answers json queries instead of running the analysis
+    if args.get(1).map(String::as_str) == Some("serve") {
+        let (mut network, _) = prepare_network(&policy, filter.as_ref(), cross_zones)?;
+        load_monitors(&mut network)?;
+        let address = args.get(2).map(String::as_str).unwrap_or("127.0.0.1:8080");
+        server::serve(&ApiServer::new(network), address)?;
+        return Ok(());
+    }
+
+    let (mut network, zone_of) = prepare_network(&policy, filter.as_ref(), cross_zones)?;
     
     //print isolation statistics
     network.print_isolation_statistics();
     
-    //read pollution data
-    println!("Reading pollution data...");
-    let pollution_data = data::read_pollution("annual_conc_by_monitor_2023.csv")?;
+    let parameter_statistics = load_monitors(&mut network)?;
+    monitor::print_parameter_statistics(&parameter_statistics);
+
+    //combine collocated pm2.5 monitors using the primary monitor at each site
+    let pollution_data = network.site_values(&SiteAggregation::pm25());
     println!("Loaded pollution data for {} stations", pollution_data.len());
+
+    //compare wildfire-influenced and event-excluded values
+    println!("Comparing event-included and event-excluded values...");
+    network.print_event_contrast(&SiteAggregation::pm25());
     
     //analyze correlation
     let correlation = network.analyze_correlation(&pollution_data);
@@ -44,6 +241,112 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("Finding monitoring gaps (high pollution, high isolation)...");
     network.find_and_print_monitoring_gaps(&pollution_data);
 
+    //the same statistics broken down by site category
+    network.print_category_statistics(&pollution_data);
+
+    //how robust the correlation and gaps are to the choice of isolation metric
+    let metrics = isolation::standard_metrics(10);
+    for metric in &metrics {
+        network.calculate_isolation_metric(metric);
+    }
+    isolation::print_metric_comparison(&isolation::compare_metrics(&network, &pollution_data, &metrics));
+    network.metrics.insert_numbers("pm25", Provenance::new("site_values").with("parameter", monitor::PM25_PARAMETER),
+                                   &pollution_data);
+    match metrics::write_csv(&network.metrics, &[], METRICS_TABLE_PATH) {
+        Ok(()) => println!("Wrote station metrics to {}", METRICS_TABLE_PATH),
+        Err(err) => eprintln!("Could not write {}: {}", METRICS_TABLE_PATH, err),
+    }
+
+    //write the same results as a standalone html report
+    match report::write_html_report(&network, &pollution_data, "PM2.5 (µg/m³)", REPORT_PATH) {
+        Ok(()) => println!("Wrote report to {}", REPORT_PATH),
+        Err(err) => eprintln!("Could not write report: {}", err),
+    }
+    let gap_ids = network.default_gap_ids(&pollution_data);
+    let map = MapOptions {
+        title: Some("Monitor isolation and PM2.5 monitoring gaps".to_string()),
+        metric: Some(StationMetric::isolation(&network)),
+        gaps: gap_ids.clone(),
+        //lower 48 states only, alaska, hawaii and the territories would shrink the albers map
+        bounding_box: Some(render::BoundingBox { min_lat: 24.0, max_lat: 50.0, min_lon: -125.0, max_lon: -66.0 }),
+        ..MapOptions::default()
+    };
+    match render::write_svg(&network, &map, MAP_PATH) {
+        Ok(()) => println!("Wrote map to {}", MAP_PATH),
+        Err(err) => eprintln!("Could not write map: {}", err),
+    }
+    match kml::write_kmz(&network, &pollution_data, &gap_ids, "PM2.5 monitoring gaps", KMZ_PATH) {
+        Ok(()) => println!("Wrote placemarks to {}", KMZ_PATH),
+        Err(err) => eprintln!("Could not write kmz: {}", err),
+    }
+
+    //population-weighted coverage, if census centroids are available
+    let coverage = if std::path::Path::new("population_centroids.csv").exists() {
+        println!("Calculating population coverage by PM2.5 monitors...");
+        let centroids = population::read_population_csv("population_centroids.csv")?;
+        let pm25_stations = network.stations_with_parameter(monitor::PM25_PARAMETER);
+        let coverage = population::nearest_monitor_distances(&centroids, &network, Some(&pm25_stations));
+        let radii = population::DEFAULT_COVERAGE_RADII_KM;
+        let national = population::coverage_statistics("United States", &coverage, &radii);
+        let states = population::coverage_by_state(&coverage, &network, &radii);
+        population::print_coverage(&national, &states);
+
+        //environmental-justice overlay using ejscreen-style percentiles
+        if std::path::Path::new("demographics.csv").exists() {
+            let demographics = equity::read_demographics("demographics.csv", "ID")?;
+            for indicator in ["P_LWINCPCT", "P_PEOPCOLORPCT"] {
+                if demographics.indicators.iter().any(|name| name == indicator) {
+                    let deciles = equity::stratify_by_decile(&coverage, &demographics, indicator,
+                                                             &network, &pollution_data, &radii);
+                    equity::print_decile_statistics(indicator, &deciles);
+                }
+            }
+        }
+        Some(coverage)
+    } else {
+        None
+    };
+
+    //state and county tables, with population coverage when centroids were loaded
+    let table_coverage = coverage.as_deref().map(|coverage| (coverage, TABLE_COVERAGE_RADIUS_KM));
+    for (group_by, path) in [(GroupBy::State, STATE_TABLE_PATH), (GroupBy::County, COUNTY_TABLE_PATH)] {
+        let rows = aggregate::aggregate(&network, &pollution_data, group_by, table_coverage);
+        match aggregate::write_csv(&rows, path) {
+            Ok(()) => println!("Wrote {} rows to {}", rows.len(), path),
+            Err(err) => eprintln!("Could not write {}: {}", path, err),
+        }
+    }
+
+    //isolation and gaps within each air basin, as agencies plan their networks
+    if let Some(zone_of) = &zone_of {
+        let rows = zones::analyze_zones(&network, &pollution_data, zone_of, 10);
+        zones::print_zone_statistics(&rows);
+        match aggregate::write_csv(&rows, ZONE_TABLE_PATH) {
+            Ok(()) => println!("Wrote {} rows to {}", rows.len(), ZONE_TABLE_PATH),
+            Err(err) => eprintln!("Could not write {}: {}", ZONE_TABLE_PATH, err),
+        }
+    }
+
+    //minimum monitor counts per metro area, if cbsa populations are available
+    if std::path::Path::new("cbsa_population.csv").exists() {
+        let cbsas = adequacy::read_cbsa_population("cbsa_population.csv")?;
+        //pm2.5 annual means stand in for missing design values, ozone annual means are not design values
+        adequacy::print_adequacy(&adequacy::assess_cbsas(&network, &cbsas, Pollutant::Pm25, &pollution_data));
+        adequacy::print_adequacy(&adequacy::assess_cbsas(&network, &cbsas, Pollutant::Ozone, &Default::default()));
+    }
+
+    //trend analysis across every year of annual data available next to the 2023 file
+    let yearly_files: Vec<(i32, String)> = (2000..=2023)
+        .map(|year| (year, format!("annual_conc_by_monitor_{}.csv", year)))
+        .filter(|(_, path)| std::path::Path::new(path).exists())
+        .collect();
+    if yearly_files.len() >= 3 {
+        println!("Calculating pollution trends across {} years...", yearly_files.len());
+        let yearly_pollution = data::read_pollution_years(&yearly_files, &CompletenessFilter::epa_standard())?;
+        network.calculate_trends(&yearly_pollution);
+        network.print_trend_summary(network.isolation_percentile(0.75).unwrap_or(0.0));
+    }
+
     println!("analysis complete");
     Ok(())
 }
@@ -0,0 +1,210 @@
+//module for the per-station metrics table
+//every computed value (isolation, its variants, pollution, or anything loaded from outside) is stored by
+//metric name and station id with the algorithm and parameters that produced it, so correlation, gap
+//detection and exports work on any pair of metrics instead of one hard-coded field
+
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+pub type StationId = String;
+
+//name of the metric written by calculate_isolation (mean distance to the k nearest neighbors)
+pub const ISOLATION: &str = "isolation";
+
+//a value of one metric at one station
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MetricValue {
+    Number(f64),
+    Category(String),
+    Flag(bool),
+}
+
+impl MetricValue {
+    //numeric view used by correlation and gap detection, flags count as 1 and 0, categories have none
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            MetricValue::Number(value) => Some(*value),
+            MetricValue::Flag(flag) => Some(if *flag { 1.0 } else { 0.0 }),
+            MetricValue::Category(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for MetricValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetricValue::Number(value) => write!(f, "{}", value),
+            MetricValue::Category(category) => write!(f, "{}", category),
+            MetricValue::Flag(flag) => write!(f, "{}", flag),
+        }
+    }
+}
+
+//what produced a metric, e.g. algorithm "mean_knn" with parameter k = 10
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub algorithm: String,
+    pub parameters: BTreeMap<String, String>,
+}
+
+impl Provenance {
+    pub fn new(algorithm: &str) -> Self {
+        Provenance { algorithm: algorithm.to_string(), parameters: BTreeMap::new() }
+    }
+
+    //add a parameter, e.g. Provenance::new("mean_knn").with("k", 10)
+    pub fn with(mut self, key: &str, value: impl ToString) -> Self {
+        self.parameters.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+impl fmt::Display for Provenance {
+    //algorithm(key=value, ...)
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parameters: Vec<String> = self.parameters.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        write!(f, "{}({})", self.algorithm, parameters.join(", "))
+    }
+}
+
+//one metric: its provenance and a value per station, stations without a value are left out
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metric {
+    pub provenance: Provenance,
+    pub values: HashMap<StationId, MetricValue>,
+}
+
+//metric name = metric, kept on the network and saved with snapshots and the sqlite store
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsTable {
+    metrics: BTreeMap<String, Metric>,
+}
+
+impl MetricsTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //store a metric, replacing any earlier metric of the same name
+    pub fn insert(&mut self, name: &str, provenance: Provenance, values: HashMap<StationId, MetricValue>) {
+        self.metrics.insert(name.to_string(), Metric { provenance, values });
+    }
+
+    //store a numeric metric, e.g. pollution per station as returned by site_values
+    pub fn insert_numbers(&mut self, name: &str, provenance: Provenance, values: &HashMap<StationId, f64>) {
+        let values = values.iter().map(|(id, value)| (id.clone(), MetricValue::Number(*value))).collect();
+        self.insert(name, provenance, values);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Metric> {
+        self.metrics.remove(name)
+    }
+
+    pub fn metric(&self, name: &str) -> Option<&Metric> {
+        self.metrics.get(name)
+    }
+
+    //metric names in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.metrics.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metrics.is_empty()
+    }
+
+    pub fn get(&self, name: &str, station_id: &str) -> Option<&MetricValue> {
+        self.metrics.get(name)?.values.get(station_id)
+    }
+
+    pub fn number(&self, name: &str, station_id: &str) -> Option<f64> {
+        self.get(name, station_id)?.as_number()
+    }
+
+    //station_id = value for every station with a numeric value, empty if the metric is missing
+    pub fn numbers(&self, name: &str) -> HashMap<StationId, f64> {
+        self.metrics.get(name)
+            .map(|metric| metric.values.iter()
+                .filter_map(|(id, value)| value.as_number().map(|number| (id.clone(), number)))
+                .collect())
+            .unwrap_or_default()
+    }
+
+    //drop the values of stations that are not kept, e.g. when building a sub-network
+    pub fn retain_stations(&mut self, keep: impl Fn(&str) -> bool) {
+        for metric in self.metrics.values_mut() {
+            metric.values.retain(|id, _| keep(id));
+        }
+    }
+}
+
+//pearson correlation between two metrics over the stations that have both
+//outputs: none with fewer than 2 stations in common or no variance in either metric
+pub fn correlation(x: &HashMap<StationId, f64>, y: &HashMap<StationId, f64>) -> Option<f64> {
+    let pairs: Vec<(f64, f64)> = x.iter()
+        .filter_map(|(id, x_value)| y.get(id).map(|y_value| (*x_value, *y_value)))
+        .collect();
+    if pairs.len() < 2 {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let (mean_x, mean_y) = (pairs.iter().map(|(x, _)| x).sum::<f64>() / n, pairs.iter().map(|(_, y)| y).sum::<f64>() / n);
+    let covariance: f64 = pairs.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance_x: f64 = pairs.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let variance_y: f64 = pairs.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    let denominator = (variance_x * variance_y).sqrt();
+    (denominator > 0.0).then(|| covariance / denominator)
+}
+
+//value at the given percentile (0.0 to 1.0) of a metric, none if it has no values
+pub fn percentile(values: &HashMap<StationId, f64>, fraction: f64) -> Option<f64> {
+    let mut sorted: Vec<f64> = values.values().copied().collect();
+    if sorted.is_empty() {
+        return None;
+    }
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    Some(sorted[((sorted.len() as f64 * fraction) as usize).min(sorted.len() - 1)])
+}
+
+//function to find stations above a threshold in both of two metrics
+//inputs: the two metrics and their thresholds, e.g. isolation and pollution at their 75th percentiles
+//outputs: (station_id, x value, y value) sorted by x descending
+pub fn find_gaps(x: &HashMap<StationId, f64>, y: &HashMap<StationId, f64>,
+                 x_threshold: f64, y_threshold: f64) -> Vec<(StationId, f64, f64)> {
+    let mut gaps: Vec<(StationId, f64, f64)> = x.iter()
+        .filter_map(|(id, x_value)| y.get(id).map(|y_value| (id.clone(), *x_value, *y_value)))
+        .filter(|(_, x_value, y_value)| *x_value > x_threshold && *y_value > y_threshold)
+        .collect();
+    gaps.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
+    gaps
+}
+
+//function to export metrics to csv in long format, one row per station and metric
+//inputs: the table, the metrics to write (all of them if empty) and the output path
+//columns: station_id, metric, value, algorithm, parameters (key=value separated by semicolons)
+pub fn write_csv<P: AsRef<Path>>(table: &MetricsTable, names: &[&str], path: P) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["station_id", "metric", "value", "algorithm", "parameters"])?;
+    for (name, metric) in &table.metrics {
+        if !names.is_empty() && !names.contains(&name.as_str()) {
+            continue;
+        }
+        let parameters: Vec<String> = metric.provenance.parameters.iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        let parameters = parameters.join(";");
+        let mut ids: Vec<&StationId> = metric.values.keys().collect();
+        ids.sort();
+        for id in ids {
+            writer.write_record([id.as_str(), name.as_str(), &metric.values[id].to_string(),
+                                 metric.provenance.algorithm.as_str(), parameters.as_str()])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
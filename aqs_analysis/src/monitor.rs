@@ -0,0 +1,369 @@
+//module for representing individual monitors at a site and combining them into one value per site
+//a site can host several collocated monitors (different POCs and methods) for the same parameter
+
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+//pm2.5 parameter code (most important measure of air pollution, particulate matter of certain size)
+pub const PM25_PARAMETER: &str = "88101";
+//24 hour block average sample duration used for pm2.5 annual values
+pub const PM25_DURATION: &str = "24-HR BLK AVG";
+//ozone parameter code
+pub const OZONE_PARAMETER: &str = "44201";
+
+//represents a single monitor record from the annual concentration file
+//one monitor (site + parameter + POC) can appear on several rows, one per pollutant standard
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Monitor {
+    pub site_id: String,
+    pub parameter_code: String,
+    pub parameter_name: String,
+    pub poc: u32,
+    pub method_code: Option<String>,
+    pub method_name: Option<String>,
+    pub sample_duration: String,
+    pub pollutant_standard: String,
+    pub event_type: EventType,
+    pub observation_count: Option<u32>,
+    pub observation_percent: Option<f64>,
+    pub complete: Option<bool>, //completeness indicator, none if the file has no indicator for the record
+    pub arithmetic_mean: Option<f64>,
+}
+
+//which exceptional events (e.g. wildfire smoke) are reflected in a record's values
+//a monitor with no events has a single No Events record, otherwise it has one record per variant
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EventType {
+    NoEvents,
+    EventsExcluded,
+    EventsIncluded,
+    ConcurredEventsExcluded,
+    Unknown(String),
+}
+
+impl EventType {
+    //parse the Event Type column of the annual concentration file
+    pub fn from_aqs(text: &str) -> Self {
+        match text.trim() {
+            "No Events" => EventType::NoEvents,
+            "Events Excluded" => EventType::EventsExcluded,
+            "Events Included" => EventType::EventsIncluded,
+            "Concurred Events Excluded" => EventType::ConcurredEventsExcluded,
+            other => EventType::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventType::NoEvents => write!(f, "No Events"),
+            EventType::EventsExcluded => write!(f, "Events Excluded"),
+            EventType::EventsIncluded => write!(f, "Events Included"),
+            EventType::ConcurredEventsExcluded => write!(f, "Concurred Events Excluded"),
+            EventType::Unknown(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+//which event-type variant of each monitor feeds the analysis
+//every selection other than All also accepts No Events records, so monitors without events are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventSelection {
+    //values including exceptional events, closest to what people actually breathed
+    IncludeEvents,
+    //values with all flagged events removed
+    ExcludeEvents,
+    //values with only EPA-concurred events removed, as used for regulatory design values
+    ExcludeConcurredEvents,
+    //every record regardless of event type
+    All,
+}
+
+impl EventSelection {
+    pub fn accepts(&self, event_type: &EventType) -> bool {
+        match self {
+            EventSelection::All => true,
+            EventSelection::IncludeEvents => matches!(event_type, EventType::NoEvents | EventType::EventsIncluded),
+            EventSelection::ExcludeEvents => matches!(event_type, EventType::NoEvents | EventType::EventsExcluded),
+            EventSelection::ExcludeConcurredEvents => {
+                matches!(event_type, EventType::NoEvents | EventType::ConcurredEventsExcluded)
+            }
+        }
+    }
+}
+
+//rule for combining several collocated monitors into a single site value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregationRule {
+    //monitor with the lowest POC, the usual primary monitor at a site
+    PrimaryMonitor,
+    //highest value among monitors
+    Max,
+    //mean of all monitors
+    Mean,
+}
+
+//which monitors feed a site value and how they are combined
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteAggregation {
+    pub parameter_code: String,
+    pub sample_duration: Option<String>, //none accepts any sample duration
+    pub events: EventSelection,
+    pub rule: AggregationRule,
+}
+
+impl SiteAggregation {
+    //pm2.5 24 hour block averages including events, from the primary monitor, the default for this analysis
+    pub fn pm25() -> Self {
+        SiteAggregation {
+            parameter_code: PM25_PARAMETER.to_string(),
+            sample_duration: Some(PM25_DURATION.to_string()),
+            events: EventSelection::IncludeEvents,
+            rule: AggregationRule::PrimaryMonitor,
+        }
+    }
+
+    //check whether a monitor record is one this aggregation uses
+    pub fn accepts(&self, monitor: &Monitor) -> bool {
+        monitor.parameter_code == self.parameter_code
+            && self.events.accepts(&monitor.event_type)
+            && self.sample_duration.as_ref().is_none_or(|duration| &monitor.sample_duration == duration)
+    }
+}
+
+//function to pick one value per monitor (POC) from its records
+//outputs: (poc, value) pairs sorted by POC
+//annual pollutant standard records are preferred for consistent averaging, otherwise the first record with a mean
+pub fn monitor_values(monitors: &[Monitor], aggregation: &SiteAggregation) -> Vec<(u32, f64)> {
+    let mut by_poc: HashMap<u32, (bool, f64)> = HashMap::new();
+    for monitor in monitors.iter().filter(|m| aggregation.accepts(m)) {
+        if let Some(mean) = monitor.arithmetic_mean {
+            let annual = monitor.pollutant_standard.contains("Annual");
+            match by_poc.get(&monitor.poc) {
+                Some((true, _)) => {}
+                Some((false, _)) if !annual => {}
+                _ => {
+                    by_poc.insert(monitor.poc, (annual, mean));
+                }
+            }
+        }
+    }
+    let mut values: Vec<(u32, f64)> = by_poc.into_iter().map(|(poc, (_, mean))| (poc, mean)).collect();
+    values.sort_by_key(|(poc, _)| *poc);
+    values
+}
+
+//function to combine the monitors at one site into a single value
+//outputs: none if no monitor at the site matches the aggregation
+pub fn aggregate_site(monitors: &[Monitor], aggregation: &SiteAggregation) -> Option<f64> {
+    let values = monitor_values(monitors, aggregation);
+    if values.is_empty() {
+        return None;
+    }
+    match aggregation.rule {
+        AggregationRule::PrimaryMonitor => Some(values[0].1),
+        AggregationRule::Max => values.iter().map(|(_, v)| *v).reduce(f64::max),
+        AggregationRule::Mean => Some(values.iter().map(|(_, v)| v).sum::<f64>() / values.len() as f64),
+    }
+}
+
+//function to combine monitors at every site
+//outputs: hashmap of station id to site value, in the same shape read_pollution returns
+pub fn aggregate_sites(sites: &HashMap<String, Vec<Monitor>>, aggregation: &SiteAggregation)
+    -> HashMap<String, f64> {
+    let mut values = HashMap::new();
+    for (id, monitors) in sites {
+        if let Some(value) = aggregate_site(monitors, aggregation) {
+            values.insert(id.clone(), value);
+        }
+    }
+    values
+}
+
+//contrast between event-included and event-excluded values at one site
+//a large difference points at sites heavily influenced by wildfire smoke or other exceptional events
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventContrast {
+    pub site_id: String,
+    pub events_included: f64,
+    pub events_excluded: f64,
+}
+
+impl EventContrast {
+    //how much the events added to the site value
+    pub fn difference(&self) -> f64 {
+        self.events_included - self.events_excluded
+    }
+}
+
+//function to contrast event-included and event-excluded values for every site that had events
+//inputs: site monitors and the aggregation to use (its event selection is overridden)
+//outputs: one contrast per site with flagged events, sorted by largest difference first
+pub fn event_contrast(sites: &HashMap<String, Vec<Monitor>>, aggregation: &SiteAggregation) -> Vec<EventContrast> {
+    let mut included = aggregation.clone();
+    included.events = EventSelection::IncludeEvents;
+    let mut excluded = aggregation.clone();
+    excluded.events = EventSelection::ExcludeEvents;
+
+    let mut contrasts = Vec::new();
+    for (id, monitors) in sites {
+        let had_events = monitors.iter()
+            .any(|m| m.event_type == EventType::EventsIncluded && included.accepts(m));
+        if !had_events {
+            continue;
+        }
+        if let (Some(with_events), Some(without_events)) =
+            (aggregate_site(monitors, &included), aggregate_site(monitors, &excluded)) {
+            contrasts.push(EventContrast {
+                site_id: id.clone(),
+                events_included: with_events,
+                events_excluded: without_events,
+            });
+        }
+    }
+    contrasts.sort_by(|a, b| {
+        b.difference().partial_cmp(&a.difference()).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    contrasts
+}
+
+//rule for dropping monitors that did not collect enough samples during the year
+//the default keeps everything, epa_standard mirrors the usual 75% completeness requirement
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CompletenessFilter {
+    pub min_observation_count: Option<u32>,
+    pub min_observation_percent: Option<f64>,
+    pub require_complete: bool, //drop records whose Completeness Indicator is not Y, records without one are kept
+}
+
+//reason a monitor was excluded by the completeness filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExclusionReason {
+    NotComplete,
+    TooFewObservations,
+    LowObservationPercent,
+    MissingObservationData,
+}
+
+impl fmt::Display for ExclusionReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            ExclusionReason::NotComplete => "completeness indicator not Y",
+            ExclusionReason::TooFewObservations => "too few observations",
+            ExclusionReason::LowObservationPercent => "observation percent too low",
+            ExclusionReason::MissingObservationData => "missing observation count or percent",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl CompletenessFilter {
+    //require completeness indicator Y and at least 75% of scheduled observations
+    pub fn epa_standard() -> Self {
+        CompletenessFilter {
+            min_observation_count: None,
+            min_observation_percent: Some(75.0),
+            require_complete: true,
+        }
+    }
+
+    //function to check a monitor record against the filter
+    //outputs: none if the record passes, otherwise the first rule it fails
+    pub fn check(&self, monitor: &Monitor) -> Option<ExclusionReason> {
+        if self.require_complete && monitor.complete == Some(false) {
+            return Some(ExclusionReason::NotComplete);
+        }
+        if let Some(min_count) = self.min_observation_count {
+            match monitor.observation_count {
+                Some(count) if count < min_count => return Some(ExclusionReason::TooFewObservations),
+                None => return Some(ExclusionReason::MissingObservationData),
+                _ => {}
+            }
+        }
+        if let Some(min_percent) = self.min_observation_percent {
+            match monitor.observation_percent {
+                Some(percent) if percent < min_percent => return Some(ExclusionReason::LowObservationPercent),
+                None => return Some(ExclusionReason::MissingObservationData),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+//counts of monitors kept and dropped by the completeness filter for one parameter
+//a monitor is one site + POC, it is kept if any of its records pass
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterStatistics {
+    pub parameter_code: String,
+    pub parameter_name: String,
+    pub total_monitors: usize,
+    pub included_monitors: usize,
+    pub excluded: BTreeMap<ExclusionReason, usize>,
+}
+
+impl ParameterStatistics {
+    pub fn excluded_monitors(&self) -> usize {
+        self.excluded.values().sum()
+    }
+}
+
+//function to apply a completeness filter to every site
+//outputs: the monitor records that pass, and per-parameter statistics sorted by parameter code
+pub fn apply_completeness_filter(sites: &HashMap<String, Vec<Monitor>>, filter: &CompletenessFilter)
+    -> (HashMap<String, Vec<Monitor>>, Vec<ParameterStatistics>) {
+    let mut kept: HashMap<String, Vec<Monitor>> = HashMap::new();
+    //(parameter, site, poc) = first exclusion reason, or none once any record passed
+    let mut outcomes: HashMap<(String, String, u32), Option<ExclusionReason>> = HashMap::new();
+    let mut names: HashMap<String, String> = HashMap::new();
+
+    for (id, monitors) in sites {
+        for monitor in monitors {
+            names.entry(monitor.parameter_code.clone()).or_insert_with(|| monitor.parameter_name.clone());
+            let key = (monitor.parameter_code.clone(), id.clone(), monitor.poc);
+            match filter.check(monitor) {
+                None => {
+                    outcomes.insert(key, None);
+                    kept.entry(id.clone()).or_default().push(monitor.clone());
+                }
+                Some(reason) => {
+                    outcomes.entry(key).or_insert(Some(reason));
+                }
+            }
+        }
+    }
+
+    let mut statistics: BTreeMap<String, ParameterStatistics> = BTreeMap::new();
+    for ((parameter_code, _, _), outcome) in outcomes {
+        let stats = statistics.entry(parameter_code.clone()).or_insert_with(|| ParameterStatistics {
+            parameter_name: names.get(&parameter_code).cloned().unwrap_or_default(),
+            parameter_code,
+            total_monitors: 0,
+            included_monitors: 0,
+            excluded: BTreeMap::new(),
+        });
+        stats.total_monitors += 1;
+        match outcome {
+            None => stats.included_monitors += 1,
+            Some(reason) => *stats.excluded.entry(reason).or_insert(0) += 1,
+        }
+    }
+
+    (kept, statistics.into_values().collect())
+}
+
+//function to print how many monitors each parameter kept and why the rest were dropped
+pub fn print_parameter_statistics(statistics: &[ParameterStatistics]) {
+    println!("Completeness filter results by parameter:");
+    for stats in statistics {
+        println!("  {} ({}): {} of {} monitors kept",
+                 stats.parameter_code, stats.parameter_name,
+                 stats.included_monitors, stats.total_monitors);
+        for (reason, count) in &stats.excluded {
+            println!("    excluded {}: {}", count, reason);
+        }
+    }
+}
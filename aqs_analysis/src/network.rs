@@ -1,14 +1,97 @@
 //module for building and analyzing the graph structure of the air quality monitoring network
 
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::cmp::Ordering;
-use crate::station::Station;
+use std::sync::OnceLock;
+use std::error::Error;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use crate::filter::StationFilter;
+use crate::isolation::{self, IsolationMetric};
+use crate::metrics::{self, MetricsTable, ISOLATION};
+use crate::monitor::{self, CompletenessFilter, EventContrast, Monitor, ParameterStatistics, SiteAggregation};
+use crate::spatial::{self, SpatialIndex};
+use crate::station::{LandUse, LocationSetting, Station};
+use crate::terrain::{ElevationGrid, TerrainPenalty};
+use crate::trend::{self, TrendResult};
+use crate::wind::{WindRose, WindRoses, WindWeighting};
+
+//summary of the isolation metric across stations (km)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct IsolationStatistics {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub median: f64,
+    pub mean: f64,
+}
+
+impl IsolationStatistics {
+    //min/max/median/mean of a set of isolation values, none if empty
+    pub fn from_values(mut isolation_values: Vec<f64>) -> Option<Self> {
+        if isolation_values.is_empty() {
+            return None;
+        }
+        isolation_values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        Some(IsolationStatistics {
+            count: isolation_values.len(),
+            min: isolation_values[0],
+            max: isolation_values[isolation_values.len() - 1],
+            median: isolation_values[isolation_values.len() / 2],
+            mean: isolation_values.iter().sum::<f64>() / isolation_values.len() as f64,
+        })
+    }
+}
+
+//isolation, correlation and gap counts for the stations in one land use or location setting category
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CategoryStatistics {
+    pub category: String,
+    pub station_count: usize,
+    pub isolation: Option<IsolationStatistics>,
+    pub correlation: Option<f64>, //isolation vs pollution, none with fewer than 3 stations having both
+    pub gap_count: usize, //gaps use the network-wide thresholds so counts are comparable across categories
+}
+
+//optional filters for nearest-station and radius queries
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StationQuery {
+    pub parameter_code: Option<String>, //only stations with an attached monitor for this parameter
+    pub land_use: Option<LandUse>,
+    pub location_setting: Option<LocationSetting>,
+}
+
+//a station found by a point query, with the distance and bearing from the query point
+#[derive(Debug, Clone)]
+pub struct NearbyStation<'a> {
+    pub station: &'a Station,
+    pub distance_km: f64,
+    pub bearing_degrees: f64, //clockwise from north, from the query point to the station
+}
+
+impl NearbyStation<'_> {
+    pub fn compass_direction(&self) -> &'static str {
+        spatial::compass_direction(self.bearing_degrees)
+    }
+}
 
 //struct to represent the graph network that connects the air quality monitors
+#[derive(Serialize, Deserialize)]
 pub struct MonitoringNetwork {
     pub stations: HashMap<String, Station>,
     pub adjacency_list: HashMap<String, Vec<(String, f64)>>, //station_id = (neighbor_id, distance)
+    pub trends: HashMap<String, TrendResult>, //station_id = trend across loaded years
+    pub monitors: HashMap<String, Vec<Monitor>>, //station_id = monitors at that site
+    pub metrics: MetricsTable, //isolation and other per-station values by metric name
+    #[serde(skip)]
+    index: OnceLock<SpatialIndex>, //built on the first point query, reset when a station is added
+}
+
+impl Default for MonitoringNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 //implementaton for graph
@@ -18,29 +101,199 @@ impl MonitoringNetwork {
         MonitoringNetwork {
             stations: HashMap::new(),
             adjacency_list: HashMap::new(),
+            trends: HashMap::new(),
+            monitors: HashMap::new(),
+            metrics: MetricsTable::new(),
+            index: OnceLock::new(),
         }
     }
     //to add a station to the network
     pub fn add_station(&mut self, station: Station) {
         let id = station.id.clone();
         self.stations.insert(id, station);
+        self.index.take();
+    }
+
+    //attach monitor records (as returned by read_monitors) to the network
+    //monitors at sites that are not stations in the network are kept but never used
+    pub fn attach_monitors(&mut self, monitors: HashMap<String, Vec<Monitor>>) {
+        for (id, mut site_monitors) in monitors {
+            self.monitors.entry(id).or_default().append(&mut site_monitors);
+        }
+    }
+
+    //function to drop attached monitors that fail the completeness filter
+    //outputs: per-parameter counts of monitors kept and excluded
+    pub fn apply_completeness_filter(&mut self, filter: &CompletenessFilter) -> Vec<ParameterStatistics> {
+        let (kept, statistics) = monitor::apply_completeness_filter(&self.monitors, filter);
+        self.monitors = kept;
+        statistics
+    }
+
+    //ids of stations with at least one attached monitor for the given parameter code
+    pub fn stations_with_parameter(&self, parameter_code: &str) -> HashSet<String> {
+        self.monitors.iter()
+            .filter(|(id, site_monitors)| {
+                self.stations.contains_key(*id)
+                    && site_monitors.iter().any(|m| m.parameter_code == parameter_code)
+            })
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    //function to combine the attached monitors into one value per station
+    //outputs: hashmap of station id to site value, usable anywhere pollution data is expected
+    pub fn site_values(&self, aggregation: &SiteAggregation) -> HashMap<String, f64> {
+        let mut values = HashMap::new();
+        for (id, site_monitors) in &self.monitors {
+            if !self.stations.contains_key(id) {
+                continue;
+            }
+            if let Some(value) = monitor::aggregate_site(site_monitors, aggregation) {
+                values.insert(id.clone(), value);
+            }
+        }
+        values
+    }
+
+    //function to contrast event-included and event-excluded values at stations in the network
+    //outputs: one contrast per station with flagged events, largest difference first
+    pub fn event_contrast(&self, aggregation: &SiteAggregation) -> Vec<EventContrast> {
+        monitor::event_contrast(&self.monitors, aggregation).into_iter()
+            .filter(|contrast| self.stations.contains_key(&contrast.site_id))
+            .collect()
+    }
+
+    //function to print the stations most influenced by exceptional events
+    pub fn print_event_contrast(&self, aggregation: &SiteAggregation) {
+        let contrasts = self.event_contrast(aggregation);
+        println!("Found {} stations with exceptional events:", contrasts.len());
+        for (i, contrast) in contrasts.iter().take(10).enumerate() {
+            let station = &self.stations[&contrast.site_id];
+            println!("  {}. {} ({}, {}): Events included: {:.2}, Events excluded: {:.2}, Difference: {:+.2}",
+                     i+1,
+                     station.site_name,
+                     station.city_name,
+                     station.state_name,
+                     contrast.events_included,
+                     contrast.events_excluded,
+                     contrast.difference());
+        }
+        if contrasts.len() > 10 {
+            println!("  ... and {} more", contrasts.len() - 10);
+        }
+    }
+
+    //check whether a station passes the filters of a point query
+    fn matches_query(&self, station: &Station, query: &StationQuery) -> bool {
+        let parameter_ok = query.parameter_code.as_ref().is_none_or(|parameter| {
+            self.monitors.get(&station.id)
+                .is_some_and(|site_monitors| site_monitors.iter().any(|m| &m.parameter_code == parameter))
+        });
+        let land_use_ok = query.land_use.as_ref().is_none_or(|land_use| &station.land_use == land_use);
+        let setting_ok = query.location_setting.as_ref().is_none_or(|setting| &station.location_setting == setting);
+        parameter_ok && land_use_ok && setting_ok
+    }
+
+    //spatial index of the station locations, built on first use and rebuilt after add_station
+    pub fn spatial_index(&self) -> &SpatialIndex {
+        self.index.get_or_init(|| SpatialIndex::from_stations(self.stations.values()))
+    }
+
+    //check whether an indexed station id passes the filters of a point query
+    fn id_matches_query(&self, id: &str, query: &StationQuery) -> bool {
+        self.stations.get(id).is_some_and(|station| self.matches_query(station, query))
+    }
+
+    //stations found by the index, with the bearing from the query point added
+    fn nearby_stations(&self, lat: f64, lon: f64, found: Vec<(String, f64)>) -> Vec<NearbyStation<'_>> {
+        found.into_iter()
+            .filter_map(|(id, distance_km)| {
+                let station = self.stations.get(&id)?;
+                Some(NearbyStation {
+                    station,
+                    distance_km,
+                    bearing_degrees: spatial::initial_bearing(lat, lon, station.latitude, station.longitude),
+                })
+            })
+            .collect()
+    }
+
+    //function to find the k nearest stations to an arbitrary point, e.g. an address's lat/lon
+    //outputs: up to k stations passing the query, nearest first
+    pub fn nearest_stations(&self, lat: f64, lon: f64, k: usize, query: &StationQuery) -> Vec<NearbyStation<'_>> {
+        let found = self.spatial_index().nearest_matching(lat, lon, k, |id| self.id_matches_query(id, query));
+        self.nearby_stations(lat, lon, found)
+    }
+
+    //function to find every station within a radius of an arbitrary point
+    //outputs: stations passing the query, nearest first
+    pub fn stations_within(&self, lat: f64, lon: f64, radius_km: f64, query: &StationQuery) -> Vec<NearbyStation<'_>> {
+        let mut found = self.spatial_index().within(lat, lon, radius_km);
+        found.retain(|(id, _)| self.id_matches_query(id, query));
+        self.nearby_stations(lat, lon, found)
+    }
+
+    //build a new network containing only the stations that were operating on the given date
+    //adjacency list and metrics are not carried over, call build_adjacency_list on the result
+    pub fn as_of(&self, date: NaiveDate) -> MonitoringNetwork {
+        self.sub_network(|station| station.is_active_on(date), false)
+    }
+
+    //build a new network containing only the stations that pass a filter expression
+    //the filter sees the current metrics, but like as_of the result has no adjacency list
+    //or metrics, call build_adjacency_list and calculate_isolation on it
+    pub fn filtered(&self, filter: &StationFilter) -> MonitoringNetwork {
+        self.sub_network(|station| filter.matches(station, &self.metrics), false)
+    }
+
+    //build a new network containing only the given stations, keeping the edges between them
+    //metrics are not carried over, call calculate_isolation on the result
+    pub fn within(&self, ids: &HashSet<String>) -> MonitoringNetwork {
+        self.sub_network(|station| ids.contains(&station.id), true)
+    }
+
+    //copy the stations that pass keep, with their monitors, into a new network
+    //with keep_edges the edges between kept stations are copied too, metrics never are
+    fn sub_network(&self, keep: impl Fn(&Station) -> bool, keep_edges: bool) -> MonitoringNetwork {
+        let mut network = MonitoringNetwork::new();
+        for station in self.stations.values().filter(|station| keep(station)) {
+            if let Some(site_monitors) = self.monitors.get(&station.id) {
+                network.monitors.insert(station.id.clone(), site_monitors.clone());
+            }
+            network.add_station(station.clone());
+        }
+        if keep_edges {
+            for (id, neighbors) in &self.adjacency_list {
+                if network.stations.contains_key(id) {
+                    let kept = neighbors.iter()
+                        .filter(|(neighbor_id, _)| network.stations.contains_key(neighbor_id))
+                        .cloned()
+                        .collect();
+                    network.adjacency_list.insert(id.clone(), kept);
+                }
+            }
+        }
+        network
+    }
+
+    //function to drop edges between stations in different zones
+    //inputs: station_id = zone name, stations outside every zone only keep edges to each other
+    //outputs: number of edges removed, recalculate isolation afterwards
+    pub fn restrict_to_zones(&mut self, zone_of: &HashMap<String, String>) -> usize {
+        let mut removed = 0;
+        for (id, neighbors) in &mut self.adjacency_list {
+            let zone = zone_of.get(id);
+            let before = neighbors.len();
+            neighbors.retain(|(neighbor_id, _)| zone_of.get(neighbor_id) == zone);
+            removed += before - neighbors.len();
+        }
+        removed
     }
 
     //calculate distance between two monitors given long and lat using Haversine distance formula
     pub fn haversine_distance(&self, lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-        //constant for earth's radius in kilometers
-        const R: f64 = 6371.0;
-        //convert degrees to radians (necessary for equation)
-        let lat1 = lat1.to_radians();
-        let lon1 = lon1.to_radians();
-        let lat2 = lat2.to_radians();
-        let lon2 = lon2.to_radians();
-        //haversine formula calculation
-        let dlat = lat2 - lat1;
-        let dlon = lon2 - lon1;
-        let a = (dlat/2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon/2.0).sin().powi(2);
-        let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());   
-        R * c
+        spatial::haversine_km(lat1, lon1, lat2, lon2)
     }
 
     // Quick distance approximation using Pythagorean theorem (in degrees)
@@ -67,7 +320,7 @@ impl MonitoringNetwork {
         for (id, station) in &self.stations {
             let grid_x = (station.longitude / GRID_SIZE_DEGREES).floor() as i32;
             let grid_y = (station.latitude / GRID_SIZE_DEGREES).floor() as i32;
-            spatial_index.entry((grid_x, grid_y)).or_insert(Vec::new()).push(id.clone());
+            spatial_index.entry((grid_x, grid_y)).or_default().push(id.clone());
         }
         
         println!("Created spatial index with {} cells", spatial_index.len());
@@ -138,156 +391,325 @@ impl MonitoringNetwork {
             }
         }
     }
-    //function to calculate average distance to k nearest neighbors, measured as isolation
-    pub fn calculate_isolation(&mut self, k: usize) {
-        for (id, station) in &mut self.stations {
-            if let Some(neighbors) = self.adjacency_list.get(id) {
-                //get distances to k nearest neighbors
-                let k_nearest: Vec<&(String, f64)> = neighbors.iter().take(k).collect();
-                //calculate avg distance
-                if !k_nearest.is_empty() {
-                    let sum: f64 = k_nearest.iter().map(|(_, dist)| dist).sum();
-                    let avg = sum / k_nearest.len() as f64;
-                    //update station with new isolation metric
-                    station.avg_distance_to_neighbors = Some(avg);
+
+    //function to replace every edge distance with a weighted distance, then re-sort each neighbor list
+    //inputs: function of (station, neighbor, distance) giving the new distance of that edge
+    //edges are directed, the station's own list is weighted from its point of view
+    fn reweight_edges(&mut self, weight: impl Fn(&Station, &Station, f64) -> f64) {
+        for (id, neighbors) in &mut self.adjacency_list {
+            let Some(station) = self.stations.get(id) else { continue };
+            for (neighbor_id, distance) in neighbors.iter_mut() {
+                if let Some(neighbor) = self.stations.get(neighbor_id) {
+                    *distance = weight(station, neighbor, *distance);
                 }
             }
+            neighbors.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
         }
     }
+
+    //function to weight edges by wind direction, neighbors upwind of a station count as closer
+    //call after build_adjacency_list and before calculate_isolation, applying it twice compounds the weights
+    //outputs: number of stations that had a wind rose
+    pub fn apply_wind_weighting(&mut self, roses: &WindRoses, weighting: &WindWeighting) -> usize {
+        let station_roses: HashMap<String, WindRose> = self.stations.values()
+            .filter_map(|station| {
+                roses.rose_for(station, weighting.max_grid_distance_km).map(|rose| (station.id.clone(), rose.clone()))
+            })
+            .collect();
+        self.reweight_edges(|station, neighbor, distance| match station_roses.get(&station.id) {
+            Some(rose) => {
+                let bearing = spatial::initial_bearing(station.latitude, station.longitude,
+                                                       neighbor.latitude, neighbor.longitude);
+                distance * weighting.factor(rose, bearing)
+            },
+            None => distance,
+        });
+        station_roses.len()
+    }
+
+    //function to lengthen edges that cross ridges of an elevation grid
+    //call after build_adjacency_list and before calculate_isolation, each pair's profile is sampled once
+    //outputs: number of station pairs separated by at least one ridge, an error if the penalty's step is invalid
+    pub fn apply_terrain_penalty(&mut self, grid: &ElevationGrid, penalty: &TerrainPenalty) -> Result<usize, Box<dyn Error>> {
+        //pairs keyed with the smaller id first
+        fn pair(a: &str, b: &str) -> (String, String) {
+            if a < b { (a.to_string(), b.to_string()) } else { (b.to_string(), a.to_string()) }
+        }
+        let mut ridges: HashMap<(String, String), usize> = HashMap::new();
+        for (id, neighbors) in &self.adjacency_list {
+            let Some(station) = self.stations.get(id) else { continue };
+            for (neighbor_id, _) in neighbors {
+                let key = pair(id, neighbor_id);
+                if ridges.contains_key(&key) {
+                    continue;
+                }
+                let Some(neighbor) = self.stations.get(neighbor_id) else { continue };
+                let count = penalty.ridges(grid, station.latitude, station.longitude,
+                                           neighbor.latitude, neighbor.longitude)?;
+                ridges.insert(key, count);
+            }
+        }
+        self.reweight_edges(|station, neighbor, distance| {
+            let count = ridges.get(&pair(&station.id, &neighbor.id)).copied().unwrap_or(0);
+            distance * penalty.factor(count)
+        });
+        Ok(ridges.values().filter(|count| **count > 0).count())
+    }
+
+    //function to calculate average distance to k nearest neighbors, measured as isolation
+    //stored as the "isolation" metric, stations without neighbors get no value
+    pub fn calculate_isolation(&mut self, k: usize) {
+        let metric = IsolationMetric::MeanKnn { k };
+        let values = isolation::compute_metric(self, &metric);
+        self.metrics.insert_numbers(ISOLATION, metric.provenance(), &values);
+    }
+    //function to calculate an isolation metric variant and store it under the metric's name
+    //outputs: number of stations with a value
+    pub fn calculate_isolation_metric(&mut self, metric: &IsolationMetric) -> usize {
+        let values = isolation::compute_metric(self, metric);
+        self.metrics.insert_numbers(&metric.name(), metric.provenance(), &values);
+        values.len()
+    }
+    //isolation of one station (km), none if not calculated or the station has no neighbors
+    pub fn isolation(&self, station_id: &str) -> Option<f64> {
+        self.metrics.number(ISOLATION, station_id)
+    }
+    //station_id = isolation for every station with a value
+    pub fn isolation_values(&self) -> HashMap<String, f64> {
+        self.metrics.numbers(ISOLATION)
+    }
+    //function to calculate summary statistics of the isolation metric
+    //outputs: none if isolation has not been calculated
+    pub fn isolation_statistics(&self) -> Option<IsolationStatistics> {
+        IsolationStatistics::from_values(self.isolation_values().into_values().collect())
+    }
     //function to print isolation statistics 
     pub fn print_isolation_statistics(&self) {
-        let mut isolation_values: Vec<f64> = Vec::new();
-        for station in self.stations.values() {
-            if let Some(isolation) = station.avg_distance_to_neighbors {
-                isolation_values.push(isolation);
+        let stats = match self.isolation_statistics() {
+            Some(stats) => stats,
+            None => {
+                println!("No isolation values calculated");
+                return;
             }
-        }
-        
-        if isolation_values.is_empty() {
-            println!("No isolation values calculated");
-            return;
-        }
-        
-        isolation_values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
-        
-        let min = isolation_values[0];
-        let max = isolation_values[isolation_values.len() - 1];
-        let median = isolation_values[isolation_values.len() / 2];
-        let mean: f64 = isolation_values.iter().sum::<f64>() / isolation_values.len() as f64;
+        };
         
         println!("Isolation statistics (km to 10 nearest neighbors):");
-        println!("  Minimum: {:.2} km", min);
-        println!("  Maximum: {:.2} km", max);
-        println!("  Median: {:.2} km", median);
-        println!("  Mean: {:.2} km", mean);
+        println!("  Minimum: {:.2} km", stats.min);
+        println!("  Maximum: {:.2} km", stats.max);
+        println!("  Median: {:.2} km", stats.median);
+        println!("  Mean: {:.2} km", stats.mean);
     }
-    //correlation analysis between calculated isolation metric and pollution levels
-    pub fn analyze_correlation(&self, pollution_data: &HashMap<String, f64>) -> f64 {
-        //extract paired data points
-        let mut pairs: Vec<(f64, f64)> = Vec::new();
-        //for each station, if we know both isolation and polution, add pair to dataset
-        for(id, station) in &self.stations {
-            if let Some(isolation) = station.avg_distance_to_neighbors {
-                if let Some(pollution) = pollution_data.get(id) {
-                    pairs.push((isolation, *pollution));
-                }
-            }
+    //isolation value at the given percentile (0.0 to 1.0), none if isolation is not calculated
+    pub fn isolation_percentile(&self, percentile: f64) -> Option<f64> {
+        metrics::percentile(&self.isolation_values(), percentile)
+    }
+    //function to break isolation, correlation and gap counts down by land use
+    //outputs: one entry per land use present in the network, in LandUse order
+    pub fn statistics_by_land_use(&self, pollution_data: &HashMap<String, f64>) -> Vec<CategoryStatistics> {
+        self.statistics_by(pollution_data, |station| station.land_use.clone())
+    }
+
+    //function to break isolation, correlation and gap counts down by location setting (urban, suburban, rural)
+    pub fn statistics_by_location_setting(&self, pollution_data: &HashMap<String, f64>) -> Vec<CategoryStatistics> {
+        self.statistics_by(pollution_data, |station| station.location_setting.clone())
+    }
+
+    //statistics per category of any ordered station attribute
+    fn statistics_by<K: Ord + std::fmt::Display>(&self, pollution_data: &HashMap<String, f64>,
+                                                 category: impl Fn(&Station) -> K) -> Vec<CategoryStatistics> {
+        let gap_ids = self.default_gap_ids(pollution_data);
+        let mut by_category: BTreeMap<K, Vec<&Station>> = BTreeMap::new();
+        for station in self.stations.values() {
+            by_category.entry(category(station)).or_default().push(station);
         }
-        //now calculate the correlation value (pearsons correlation)
-        if pairs.is_empty() {
-            return 0.0;
+
+        by_category.into_iter().map(|(key, stations)| {
+            let pollution: HashMap<String, f64> = stations.iter()
+                .filter_map(|station| pollution_data.get(&station.id).map(|value| (station.id.clone(), *value)))
+                .collect();
+            let paired = stations.iter()
+                .filter(|station| self.isolation(&station.id).is_some() && pollution.contains_key(&station.id))
+                .count();
+            CategoryStatistics {
+                category: key.to_string(),
+                station_count: stations.len(),
+                isolation: IsolationStatistics::from_values(stations.iter().filter_map(|s| self.isolation(&s.id)).collect()),
+                //analyze_correlation only pairs stations found in the pollution map, so this limits it to the category
+                correlation: if paired >= 3 { Some(self.analyze_correlation(&pollution)) } else { None },
+                gap_count: stations.iter().filter(|station| gap_ids.contains(&station.id)).count(),
+            }
+        }).collect()
+    }
+
+    //function to print isolation, correlation and gap counts by location setting and by land use
+    pub fn print_category_statistics(&self, pollution_data: &HashMap<String, f64>) {
+        for (title, statistics) in [("location setting", self.statistics_by_location_setting(pollution_data)),
+                                    ("land use", self.statistics_by_land_use(pollution_data))] {
+            println!("Statistics by {}:", title);
+            for stats in statistics {
+                let category = if stats.category.is_empty() { "(blank)" } else { stats.category.as_str() };
+                let isolation = match &stats.isolation {
+                    Some(isolation) => format!("isolation median {:.2} km, mean {:.2} km", isolation.median, isolation.mean),
+                    None => "no isolation values".to_string(),
+                };
+                let correlation = stats.correlation.map(|r| format!("{:.4}", r)).unwrap_or_else(|| "n/a".to_string());
+                println!("  {}: {} stations, {}, correlation {}, {} gaps",
+                         category, stats.station_count, isolation, correlation, stats.gap_count);
+            }
         }
-        let n = pairs.len() as f64;
-        let sum_x: f64 = pairs.iter().map(|(x, _)| x).sum();
-        let sum_y: f64 = pairs.iter().map(|(_, y)| y).sum();
-        let sum_xy: f64 = pairs.iter().map(|(x, y)| x * y).sum();
-        let sum_xx: f64 = pairs.iter().map(|(x, _)| x * x).sum();
-        let sum_yy: f64 = pairs.iter().map(|(_, y)| y * y).sum();
-        
-        let numerator = n * sum_xy - sum_x * sum_y;
-        let denominator = ((n * sum_xx - sum_x * sum_x) * (n * sum_yy - sum_y * sum_y)).sqrt();
-        
-        if denominator == 0.0 {
-            return 0.0;
+    }
+    //default thresholds for monitoring gaps: the 75th percentiles of pollution and isolation
+    //outputs: (isolation threshold, pollution threshold), none if either is unavailable
+    pub fn default_gap_thresholds(&self, pollution_data: &HashMap<String, f64>) -> Option<(f64, f64)> {
+        let pollution_threshold = metrics::percentile(pollution_data, 0.75)?;
+        let isolation_threshold = self.isolation_percentile(0.75)?;
+        Some((isolation_threshold, pollution_threshold))
+    }
+    //ids of stations flagged as monitoring gaps at the default (75th percentile) thresholds
+    pub fn default_gap_ids(&self, pollution_data: &HashMap<String, f64>) -> HashSet<String> {
+        match self.default_gap_thresholds(pollution_data) {
+            Some((isolation, pollution)) => self.find_monitoring_gaps(pollution_data, isolation, pollution)
+                .into_iter()
+                .map(|(station, _)| station.id.clone())
+                .collect(),
+            None => HashSet::new(),
         }
-        
-        numerator / denominator
+    }
+    //correlation analysis between calculated isolation metric and pollution levels
+    //outputs: pearson correlation, 0 if there are too few stations with both or no variance
+    pub fn analyze_correlation(&self, pollution_data: &HashMap<String, f64>) -> f64 {
+        metrics::correlation(&self.isolation_values(), pollution_data).unwrap_or(0.0)
+    }
+    //pearson correlation between any two numeric metrics in the metrics table
+    pub fn metric_correlation(&self, x: &str, y: &str) -> Option<f64> {
+        metrics::correlation(&self.metrics.numbers(x), &self.metrics.numbers(y))
     }
     //function to find potiental monitoring gaps (areas with high pollution but few nearby stations)
+    //outputs: (station, pollution) sorted by isolation (descending)
     pub fn find_monitoring_gaps(&self, pollution_data: &HashMap<String, f64>, 
                          isolation_threshold: f64, pollution_threshold: f64) -> Vec<(&Station, f64)> {
-        let mut gaps = Vec::new();
-        
-        for (id, station) in &self.stations {
-            //skip stations without isolation metrics or pollution data
-            if let Some(isolation) = station.avg_distance_to_neighbors {
-                if let Some(pollution) = pollution_data.get(id) {
-                    //check if this station is in a high-pollution area but far from other stations
-                    //thresholds set externally
-                    if isolation > isolation_threshold && *pollution > pollution_threshold {
-                        gaps.push((station, *pollution));
-                    }
-                }
-            }
-        }
-        //sort by isolation (descending)
-        gaps.sort_by(|(a, _), (b, _)| {
-            b.avg_distance_to_neighbors.unwrap().partial_cmp(
-                &a.avg_distance_to_neighbors.unwrap()).unwrap_or(Ordering::Equal)
-        });
-        
-        gaps
+        metrics::find_gaps(&self.isolation_values(), pollution_data, isolation_threshold, pollution_threshold)
+            .into_iter()
+            .filter_map(|(id, _, pollution)| Some((self.stations.get(&id)?, pollution)))
+            .collect()
+    }
+    //function to find stations above the thresholds in any two numeric metrics of the metrics table
+    //outputs: (station, x value, y value) sorted by x (descending)
+    pub fn find_metric_gaps(&self, x: &str, y: &str, x_threshold: f64, y_threshold: f64) -> Vec<(&Station, f64, f64)> {
+        metrics::find_gaps(&self.metrics.numbers(x), &self.metrics.numbers(y), x_threshold, y_threshold)
+            .into_iter()
+            .filter_map(|(id, x_value, y_value)| Some((self.stations.get(&id)?, x_value, y_value)))
+            .collect()
     }
     //function to find and print the calculated monitoring gaps
     pub fn find_and_print_monitoring_gaps(&self, pollution_data: &HashMap<String, f64>) {
-        //first get isolation stats
-        let mut isolation_values: Vec<f64> = Vec::new();
-        for station in self.stations.values() {
-            if let Some(isolation) = station.avg_distance_to_neighbors {
-                isolation_values.push(isolation);
-            }
-        }
+        let isolation_values = self.isolation_values();
         if isolation_values.is_empty() {
             println!("no isolation values available");
             return;
         }
-        isolation_values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
-
-        //get pollution stats
-        let mut pollution_values: Vec<f64> = pollution_data.values().copied().collect();
-        
-        if pollution_values.is_empty() {
+        if pollution_data.is_empty() {
             println!("no pollution data available");
             return;
         }
-        pollution_values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
 
         //set thresholds at 75th percentile - reasonable value that is not too restrictive or inclusive
-        let p75_index = pollution_values.len() * 3 / 4;
-        let pollution_threshold = pollution_values[p75_index];
-        
-        let i75_index = isolation_values.len() * 3 / 4;
-        let isolation_threshold = isolation_values[i75_index];
+        let Some((isolation_threshold, pollution_threshold)) = self.default_gap_thresholds(pollution_data) else {
+            return;
+        };
         
         println!("using thresholds: pollution > {:.2}, isolation > {:.2} km", 
                  pollution_threshold, isolation_threshold);
         
         //find potiential gaps
-        let gaps = self.find_monitoring_gaps(pollution_data, isolation_threshold, pollution_threshold);
+        let gaps = metrics::find_gaps(&isolation_values, pollution_data, isolation_threshold, pollution_threshold);
         
         println!("Found {} stations in areas with monitoring gaps:", gaps.len());
-        for (i, (station, pollution)) in gaps.iter().take(10).enumerate() {
+        for (i, (id, isolation, pollution)) in gaps.iter().take(10).enumerate() {
+            let Some(station) = self.stations.get(id) else { continue };
             println!("  {}. {} ({}, {}): Pollution: {:.2}, Isolation: {:.2} km", 
                      i+1, 
                      station.site_name,
                      station.city_name,
                      station.state_name,
                      pollution,
-                     station.avg_distance_to_neighbors.unwrap());
+                     isolation);
         }
         
         if gaps.len() > 10 {
             println!("  ... and {} more", gaps.len() - 10);
         }
     }
-}
\ No newline at end of file
+    //function to calculate per-station trends across several years of pollution data
+    //inputs: map of year to (station id = pollution level) as returned by read_pollution
+    //stations need at least 3 years of data to get a trend
+    pub fn calculate_trends(&mut self, yearly_pollution: &BTreeMap<i32, HashMap<String, f64>>) {
+        self.trends.clear();
+        for id in self.stations.keys() {
+            let series: Vec<(i32, f64)> = yearly_pollution.iter()
+                .filter_map(|(year, data)| data.get(id).map(|value| (*year, *value)))
+                .collect();
+            if let Some(result) = trend::mann_kendall(&series) {
+                self.trends.insert(id.clone(), result);
+            }
+        }
+    }
+    //sen's slope for every station with a trend, in the same shape as pollution data
+    //so it can be passed to analyze_correlation to relate isolation to trends
+    pub fn trend_slopes(&self) -> HashMap<String, f64> {
+        self.trends.iter().map(|(id, result)| (id.clone(), result.sens_slope)).collect()
+    }
+    //function to find isolated stations where pollution is significantly increasing
+    //sorted by slope (fastest increase first)
+    pub fn find_worsening_isolated_stations(&self, isolation_threshold: f64, alpha: f64)
+        -> Vec<(&Station, &TrendResult)> {
+        let mut results = Vec::new();
+        for (id, result) in &self.trends {
+            if let Some(station) = self.stations.get(id) {
+                if let Some(isolation) = self.isolation(id) {
+                    if isolation > isolation_threshold && result.is_increasing(alpha) {
+                        results.push((station, result));
+                    }
+                }
+            }
+        }
+        results.sort_by(|(_, a), (_, b)| {
+            b.sens_slope.partial_cmp(&a.sens_slope).unwrap_or(Ordering::Equal)
+        });
+        results
+    }
+    //function to print a summary of trends and the isolated stations that are getting worse
+    pub fn print_trend_summary(&self, isolation_threshold: f64) {
+        if self.trends.is_empty() {
+            println!("No trends calculated");
+            return;
+        }
+        let alpha = trend::DEFAULT_ALPHA;
+        let increasing = self.trends.values().filter(|t| t.is_increasing(alpha)).count();
+        let decreasing = self.trends.values().filter(|t| t.is_decreasing(alpha)).count();
+        println!("Trend statistics (Mann-Kendall, alpha = {:.2}):", alpha);
+        println!("  Stations with trends: {}", self.trends.len());
+        println!("  Significantly increasing: {}", increasing);
+        println!("  Significantly decreasing: {}", decreasing);
+
+        let correlation = self.analyze_correlation(&self.trend_slopes());
+        println!("Correlation between isolation and Sen's slope: {:.4}", correlation);
+
+        let worsening = self.find_worsening_isolated_stations(isolation_threshold, alpha);
+        println!("Found {} isolated stations (> {:.2} km) with increasing pollution:",
+                 worsening.len(), isolation_threshold);
+        for (i, (station, result)) in worsening.iter().take(10).enumerate() {
+            println!("  {}. {} ({}, {}): Slope: {:+.3}/yr, p = {:.4}, Isolation: {:.2} km",
+                     i+1,
+                     station.site_name,
+                     station.city_name,
+                     station.state_name,
+                     result.sens_slope,
+                     result.p_value,
+                     self.isolation(&station.id).unwrap_or_default());
+        }
+        if worsening.len() > 10 {
+            println!("  ... and {} more", worsening.len() - 10);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//module for population-weighted coverage of the monitoring network
+//measures how many people live far from a monitor using census tract or block-group centroids
+
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use serde::Deserialize;
+use serde_json::Value;
+use crate::network::MonitoringNetwork;
+use crate::spatial::SpatialIndex;
+
+//distances used for the "% of population within" statistics
+pub const DEFAULT_COVERAGE_RADII_KM: [f64; 3] = [25.0, 50.0, 100.0];
+
+//population-weighted center of a census tract or block group
+#[derive(Debug, Clone, PartialEq)]
+pub struct PopulationCentroid {
+    pub geoid: String,
+    pub state_code: String,
+    pub population: f64,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+//raw record from a census centers-of-population csv (tract or block-group level)
+#[derive(Debug, Deserialize)]
+struct CentroidRecord {
+    #[serde(rename = "STATEFP")]
+    state_code: String,
+    #[serde(rename = "COUNTYFP")]
+    county_code: String,
+    #[serde(rename = "TRACTCE")]
+    tract_code: String,
+    #[serde(rename = "BLKGRPCE", default)]
+    block_group_code: Option<String>,
+    #[serde(rename = "POPULATION")]
+    population: f64,
+    #[serde(rename = "LATITUDE")]
+    latitude: f64,
+    #[serde(rename = "LONGITUDE")]
+    longitude: f64,
+}
+
+//function to read census centers of population from csv using serde
+//inputs: path to a csv with STATEFP, COUNTYFP, TRACTCE, (BLKGRPCE), POPULATION, LATITUDE, LONGITUDE columns
+//outputs: result containing a vector of centroids or an error
+pub fn read_population_csv<P: AsRef<Path>>(path: P) -> Result<Vec<PopulationCentroid>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut csv_reader = csv::ReaderBuilder::new().has_headers(true).from_reader(reader);
+    let mut centroids = Vec::new();
+
+    for result in csv_reader.deserialize::<CentroidRecord>() {
+        match result {
+            Ok(record) => {
+                //geoid is the concatenation of the census codes, e.g. 01001020100
+                let geoid = format!("{}{}{}{}", record.state_code, record.county_code, record.tract_code,
+                                    record.block_group_code.unwrap_or_default());
+                centroids.push(PopulationCentroid {
+                    geoid,
+                    state_code: record.state_code,
+                    population: record.population,
+                    latitude: record.latitude,
+                    longitude: record.longitude,
+                });
+            },
+            Err(err) => {
+                //log error but continue anyway
+                eprintln!("Error deserializing population centroid {}", err);
+            }
+        }
+    }
+    println!("Loaded {} population centroids", centroids.len());
+    Ok(centroids)
+}
+
+//function to read population centroids from a geojson feature collection of points
+//inputs: path to the geojson file, each feature needs a GEOID and POPULATION property
+//the state code is taken from a STATEFP property, or the first two digits of the GEOID
+pub fn read_population_geojson<P: AsRef<Path>>(path: P) -> Result<Vec<PopulationCentroid>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let json: Value = serde_json::from_reader(BufReader::new(file))?;
+    let features = json["features"].as_array().ok_or("geojson has no features array")?;
+    let mut centroids = Vec::new();
+
+    for feature in features {
+        let properties = &feature["properties"];
+        let coordinates = &feature["geometry"]["coordinates"];
+        let geoid = property_string(properties, &["GEOID", "geoid"]);
+        let population = property_number(properties, &["POPULATION", "population", "POP"]);
+        match (geoid, population, coordinates[0].as_f64(), coordinates[1].as_f64()) {
+            (Some(geoid), Some(population), Some(longitude), Some(latitude)) => {
+                let state_code = property_string(properties, &["STATEFP", "statefp"])
+                    .unwrap_or_else(|| geoid.chars().take(2).collect());
+                centroids.push(PopulationCentroid { geoid, state_code, population, latitude, longitude });
+            },
+            _ => {
+                eprintln!("Skipping population feature without geoid, population or point geometry");
+            }
+        }
+    }
+    println!("Loaded {} population centroids", centroids.len());
+    Ok(centroids)
+}
+
+//string property under any of the given keys, numbers are converted to text
+fn property_string(properties: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| match &properties[*key] {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    })
+}
+
+//numeric property under any of the given keys, numeric strings are parsed
+fn property_number(properties: &Value, keys: &[&str]) -> Option<f64> {
+    keys.iter().find_map(|key| match &properties[*key] {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+//distance from one population centroid to its nearest monitor
+#[derive(Debug, Clone, PartialEq)]
+pub struct CentroidCoverage {
+    pub geoid: String,
+    pub state_code: String,
+    pub population: f64,
+    pub nearest_station: Option<String>,
+    pub distance_km: Option<f64>,
+}
+
+//function to find the nearest monitor to every population centroid
+//inputs: centroids, the network, and optionally the station ids that count as monitors (e.g. pm2.5 sites)
+//outputs: one coverage record per centroid, in input order
+pub fn nearest_monitor_distances(centroids: &[PopulationCentroid], network: &MonitoringNetwork,
+                                 monitor_ids: Option<&HashSet<String>>) -> Vec<CentroidCoverage> {
+    let index = SpatialIndex::from_stations(
+        network.stations.values().filter(|station| monitor_ids.is_none_or(|ids| ids.contains(&station.id)))
+    );
+    centroids.iter().map(|centroid| {
+        let nearest = index.nearest(centroid.latitude, centroid.longitude, 1).into_iter().next();
+        CentroidCoverage {
+            geoid: centroid.geoid.clone(),
+            state_code: centroid.state_code.clone(),
+            population: centroid.population,
+            nearest_station: nearest.as_ref().map(|(id, _)| id.clone()),
+            distance_km: nearest.map(|(_, distance)| distance),
+        }
+    }).collect()
+}
+
+//population-weighted coverage summary for a region (the nation or one state)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageStatistics {
+    pub region: String,
+    pub total_population: f64,
+    pub mean_distance_km: f64, //population-weighted
+    pub percent_within: Vec<(f64, f64)>, //(radius km, % of population within that radius)
+}
+
+//function to summarize population coverage for a set of centroids
+//centroids without a nearest monitor count toward the population but never as covered
+pub fn coverage_statistics(region: &str, coverage: &[CentroidCoverage], radii_km: &[f64]) -> CoverageStatistics {
+    let total_population: f64 = coverage.iter().map(|c| c.population).sum();
+    let located: Vec<(f64, f64)> = coverage.iter()
+        .filter_map(|c| c.distance_km.map(|distance| (c.population, distance)))
+        .collect();
+    let located_population: f64 = located.iter().map(|(population, _)| population).sum();
+    let weighted_distance: f64 = located.iter().map(|(population, distance)| population * distance).sum();
+
+    let percent_within = radii_km.iter().map(|radius| {
+        let covered: f64 = located.iter()
+            .filter(|(_, distance)| distance <= radius)
+            .map(|(population, _)| population)
+            .sum();
+        let percent = if total_population > 0.0 { covered / total_population * 100.0 } else { 0.0 };
+        (*radius, percent)
+    }).collect();
+
+    CoverageStatistics {
+        region: region.to_string(),
+        total_population,
+        mean_distance_km: if located_population > 0.0 { weighted_distance / located_population } else { 0.0 },
+        percent_within,
+    }
+}
+
+//function to summarize population coverage per state
+//state names come from stations in the network with the same state code, otherwise the code is used
+//outputs: one summary per state sorted by region name
+pub fn coverage_by_state(coverage: &[CentroidCoverage], network: &MonitoringNetwork, radii_km: &[f64])
+    -> Vec<CoverageStatistics> {
+    let state_names: HashMap<&str, &str> = network.stations.values()
+        .map(|station| (station.state_code.as_str(), station.state_name.as_str()))
+        .collect();
+    let mut by_state: BTreeMap<String, Vec<CentroidCoverage>> = BTreeMap::new();
+    for record in coverage {
+        let name = state_names.get(record.state_code.as_str())
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| record.state_code.clone());
+        by_state.entry(name).or_default().push(record.clone());
+    }
+    by_state.iter()
+        .map(|(name, records)| coverage_statistics(name, records, radii_km))
+        .collect()
+}
+
+//function to print national and per-state population coverage
+pub fn print_coverage(national: &CoverageStatistics, states: &[CoverageStatistics]) {
+    let header: Vec<String> = national.percent_within.iter()
+        .map(|(radius, _)| format!("<{:.0} km", radius))
+        .collect();
+    println!("Population coverage ({}):", header.join(", "));
+    for stats in std::iter::once(national).chain(states.iter()) {
+        let percents: Vec<String> = stats.percent_within.iter()
+            .map(|(_, percent)| format!("{:.1}%", percent))
+            .collect();
+        println!("  {}: population {:.0}, mean distance {:.2} km, within {}",
+                 stats.region, stats.total_population, stats.mean_distance_km, percents.join(" / "));
+    }
+}
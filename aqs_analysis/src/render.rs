@@ -0,0 +1,337 @@
+//module for drawing static svg maps of the monitoring network
+//stations are colored by any per-station metric (isolation, pollution, cluster, centrality...),
+//with optional adjacency edges, gap markers, a bounding box or state filter and a legend
+
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use crate::metrics::ISOLATION;
+use crate::network::MonitoringNetwork;
+use crate::report::escape;
+use crate::spatial::EARTH_RADIUS_KM;
+use crate::station::Station;
+
+//map projections, all return (x east, y north) in km
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    //albers equal-area conic with the usgs conus parameters (epsg:5070 on a sphere)
+    AlbersConus,
+    //spherical mercator as used by web map tiles (epsg:3857)
+    WebMercator,
+    //plate carrée, degrees scaled by the earth radius
+    Equirectangular,
+}
+
+impl Projection {
+    pub fn project(&self, lat: f64, lon: f64) -> (f64, f64) {
+        match self {
+            Projection::AlbersConus => {
+                let (phi0, phi1, phi2, lambda0) = (23.0f64.to_radians(), 29.5f64.to_radians(),
+                                                   45.5f64.to_radians(), -96.0f64.to_radians());
+                let n = (phi1.sin() + phi2.sin()) / 2.0;
+                let c = phi1.cos().powi(2) + 2.0 * n * phi1.sin();
+                let rho = |phi: f64| EARTH_RADIUS_KM * (c - 2.0 * n * phi.sin()).sqrt() / n;
+                let theta = n * (lon.to_radians() - lambda0);
+                let rho_lat = rho(lat.to_radians());
+                (rho_lat * theta.sin(), rho(phi0) - rho_lat * theta.cos())
+            },
+            Projection::WebMercator => {
+                //mercator is undefined at the poles, tiles stop at this latitude
+                let lat = lat.clamp(-85.051_128_78, 85.051_128_78).to_radians();
+                (EARTH_RADIUS_KM * lon.to_radians(), EARTH_RADIUS_KM * (PI / 4.0 + lat / 2.0).tan().ln())
+            },
+            Projection::Equirectangular => (EARTH_RADIUS_KM * lon.to_radians(), EARTH_RADIUS_KM * lat.to_radians()),
+        }
+    }
+}
+
+//how metric values become colors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorScale {
+    //perceptually uniform dark blue to yellow, for isolation, centrality and other magnitudes
+    Sequential,
+    //green to yellow to red, for pollution levels
+    GreenToRed,
+    //blue to white to red centered on zero, for trend slopes and other signed values
+    Diverging,
+    //a fixed palette by integer value, for cluster ids
+    Categorical,
+}
+
+//colors used by the categorical scale, repeated when there are more categories
+const CATEGORICAL_PALETTE: [&str; 10] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd",
+                                         "#8c564b", "#e377c2", "#7f7f7f", "#bcbd22", "#17becf"];
+//stops of the sequential scale (viridis)
+const SEQUENTIAL_STOPS: [(u8, u8, u8); 5] = [(68, 1, 84), (59, 82, 139), (33, 145, 140), (94, 201, 98), (253, 231, 37)];
+const GREEN_TO_RED_STOPS: [(u8, u8, u8); 3] = [(0, 200, 60), (255, 200, 60), (255, 0, 60)];
+const DIVERGING_STOPS: [(u8, u8, u8); 3] = [(33, 102, 172), (247, 247, 247), (178, 24, 43)];
+//fill for stations without a metric value
+const NO_DATA_COLOR: &str = "#bbbbbb";
+
+//interpolate between evenly spaced color stops, t from 0 to 1
+fn interpolate(stops: &[(u8, u8, u8)], t: f64) -> String {
+    let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let i = (position.floor() as usize).min(stops.len() - 2);
+    let f = position - i as f64;
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * f).round() as u8;
+    let ((r1, g1, b1), (r2, g2, b2)) = (stops[i], stops[i + 1]);
+    format!("#{:02x}{:02x}{:02x}", mix(r1, r2), mix(g1, g2), mix(b1, b2))
+}
+
+impl ColorScale {
+    //color of a value given the (min, max) of every value on the map
+    pub fn color(&self, value: f64, (min, max): (f64, f64)) -> String {
+        let t = if max > min { (value - min) / (max - min) } else { 0.5 };
+        match self {
+            ColorScale::Sequential => interpolate(&SEQUENTIAL_STOPS, t),
+            ColorScale::GreenToRed => interpolate(&GREEN_TO_RED_STOPS, t),
+            ColorScale::Diverging => {
+                //symmetric around zero so white always means no change
+                let extent = min.abs().max(max.abs());
+                let t = if extent > 0.0 { 0.5 + value / extent / 2.0 } else { 0.5 };
+                interpolate(&DIVERGING_STOPS, t)
+            },
+            ColorScale::Categorical => {
+                CATEGORICAL_PALETTE[(value.round() as i64).rem_euclid(CATEGORICAL_PALETTE.len() as i64) as usize]
+                    .to_string()
+            },
+        }
+    }
+}
+
+//latitude/longitude rectangle used to crop the map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        (self.min_lat..=self.max_lat).contains(&lat) && (self.min_lon..=self.max_lon).contains(&lon)
+    }
+}
+
+//per-station values to color the stations by
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationMetric {
+    pub label: String, //shown in the legend, e.g. "Isolation (km)"
+    pub values: HashMap<String, f64>, //station id = value
+    pub scale: ColorScale,
+}
+
+impl StationMetric {
+    //the isolation metric of every station where it has been calculated
+    pub fn isolation(network: &MonitoringNetwork) -> Self {
+        Self::from_metric(network, ISOLATION, "Isolation (km)", ColorScale::Sequential)
+    }
+
+    //any numeric metric from the network's metrics table, e.g. "voronoi_area_100km"
+    pub fn from_metric(network: &MonitoringNetwork, name: &str, label: &str, scale: ColorScale) -> Self {
+        StationMetric {
+            label: label.to_string(),
+            values: network.metrics.numbers(name),
+            scale,
+        }
+    }
+}
+
+//what to draw and how
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapOptions {
+    pub projection: Projection,
+    pub width: f64,
+    pub height: f64,
+    pub title: Option<String>,
+    pub metric: Option<StationMetric>, //none draws every station in one color
+    pub show_edges: bool, //draw the adjacency list
+    pub gaps: HashSet<String>, //station ids drawn with a gap marker
+    pub bounding_box: Option<BoundingBox>,
+    pub states: Option<HashSet<String>>, //state codes or names (case-insensitive) to keep
+    pub legend: bool,
+    pub station_radius: f64,
+}
+
+impl Default for MapOptions {
+    fn default() -> Self {
+        MapOptions {
+            projection: Projection::AlbersConus,
+            width: 960.0,
+            height: 600.0,
+            title: None,
+            metric: None,
+            show_edges: false,
+            gaps: HashSet::new(),
+            bounding_box: None,
+            states: None,
+            legend: true,
+            station_radius: 2.5,
+        }
+    }
+}
+
+impl MapOptions {
+    //check the bounding box and state filters
+    pub fn includes(&self, station: &Station) -> bool {
+        let in_box = self.bounding_box.is_none_or(|bbox| bbox.contains(station.latitude, station.longitude));
+        let in_state = self.states.as_ref().is_none_or(|states| {
+            states.iter().any(|state| state == &station.state_code || state.eq_ignore_ascii_case(&station.state_name))
+        });
+        in_box && in_state
+    }
+}
+
+//space around the map and to the right of it for the legend
+const PADDING: f64 = 12.0;
+const LEGEND_WIDTH: f64 = 150.0;
+const TITLE_HEIGHT: f64 = 28.0;
+
+//function to draw the network as an svg document
+//outputs: the svg markup, an empty map with a note if no station passes the filters
+pub fn render_svg(network: &MonitoringNetwork, options: &MapOptions) -> String {
+    let mut stations: Vec<&Station> = network.stations.values().filter(|s| options.includes(s)).collect();
+    //stable drawing order, stations without data first so measured stations sit on top
+    stations.sort_by(|a, b| a.id.cmp(&b.id));
+    let metric_value = |station: &Station| options.metric.as_ref().and_then(|m| m.values.get(&station.id).copied());
+    stations.sort_by_key(|station| metric_value(station).is_some());
+
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+                           viewBox=\"0 0 {} {}\" font-family=\"sans-serif\">\n",
+                          options.width, options.height, options.width, options.height);
+    let _ = writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>");
+    let top = match &options.title {
+        Some(title) => {
+            let _ = writeln!(svg, "<text x=\"{}\" y=\"20\" font-size=\"16\" text-anchor=\"middle\">{}</text>",
+                             options.width / 2.0, escape(title));
+            TITLE_HEIGHT
+        },
+        None => 0.0,
+    };
+
+    let projected: HashMap<&str, (f64, f64)> = stations.iter()
+        .map(|s| (s.id.as_str(), options.projection.project(s.latitude, s.longitude)))
+        .collect();
+    let extent = projected.values().fold(None, |extent: Option<(f64, f64, f64, f64)>, &(x, y)| match extent {
+        None => Some((x, x, y, y)),
+        Some((min_x, max_x, min_y, max_y)) => Some((min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))),
+    });
+    let Some((min_x, max_x, min_y, max_y)) = extent else {
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"14\" text-anchor=\"middle\">no stations</text>",
+                         options.width / 2.0, options.height / 2.0);
+        svg.push_str("</svg>\n");
+        return svg;
+    };
+
+    //fit the projected extent into the drawing area, keeping the aspect ratio
+    let legend_width = if options.legend { LEGEND_WIDTH } else { 0.0 };
+    let area_width = options.width - legend_width - 2.0 * PADDING;
+    let area_height = options.height - top - 2.0 * PADDING;
+    let scale = (area_width / (max_x - min_x).max(1e-9)).min(area_height / (max_y - min_y).max(1e-9));
+    let offset_x = PADDING + (area_width - (max_x - min_x) * scale) / 2.0;
+    let offset_y = top + PADDING + (area_height - (max_y - min_y) * scale) / 2.0;
+    let to_screen = |(x, y): (f64, f64)| (offset_x + (x - min_x) * scale, offset_y + (max_y - y) * scale);
+
+    if options.show_edges {
+        svg.push_str("<g stroke=\"#999999\" stroke-width=\"0.5\" stroke-opacity=\"0.5\">\n");
+        for station in &stations {
+            let Some(neighbors) = network.adjacency_list.get(&station.id) else { continue };
+            for (neighbor, _) in neighbors {
+                //each undirected edge once, and only when both ends are on the map
+                if station.id.as_str() >= neighbor.as_str() {
+                    continue;
+                }
+                if let Some(&end) = projected.get(neighbor.as_str()) {
+                    let (x1, y1) = to_screen(projected[station.id.as_str()]);
+                    let (x2, y2) = to_screen(end);
+                    let _ = writeln!(svg, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"/>", x1, y1, x2, y2);
+                }
+            }
+        }
+        svg.push_str("</g>\n");
+    }
+
+    let range = options.metric.as_ref().and_then(|metric| {
+        stations.iter().filter_map(|s| metric.values.get(&s.id).copied()).fold(None, |range, value| match range {
+            None => Some((value, value)),
+            Some((min, max)) => Some((f64::min(min, value), f64::max(max, value))),
+        })
+    });
+    for station in &stations {
+        let (x, y) = to_screen(projected[station.id.as_str()]);
+        let value = metric_value(station);
+        let fill = match (&options.metric, value, range) {
+            (Some(metric), Some(value), Some(range)) => metric.scale.color(value, range),
+            (None, _, _) => CATEGORICAL_PALETTE[0].to_string(),
+            _ => NO_DATA_COLOR.to_string(),
+        };
+        let label = value.map(|value| format!(": {:.2}", value)).unwrap_or_default();
+        let _ = writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\" fill=\"{}\"><title>{} ({}){}</title></circle>",
+                         x, y, options.station_radius, fill, escape(&station.site_name), escape(&station.id), label);
+    }
+    for station in stations.iter().filter(|s| options.gaps.contains(&s.id)) {
+        let (x, y) = to_screen(projected[station.id.as_str()]);
+        let _ = writeln!(svg, "<circle class=\"gap\" cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\" fill=\"none\" stroke=\"#000000\" \
+                               stroke-width=\"1.5\"/>", x, y, options.station_radius * 2.0);
+    }
+
+    if options.legend {
+        legend(&mut svg, options, range, options.width - legend_width, top + PADDING);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+//legend for the metric colors, no-data stations and gap markers
+fn legend(svg: &mut String, options: &MapOptions, range: Option<(f64, f64)>, x: f64, mut y: f64) {
+    let r = options.station_radius.max(3.0);
+    if let (Some(metric), Some((min, max))) = (&options.metric, range) {
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"12\">{}</text>", x, y + 10.0, escape(&metric.label));
+        y += 20.0;
+        let entries: Vec<f64> = match metric.scale {
+            //one swatch per category on the map, up to the palette size
+            ColorScale::Categorical => {
+                let mut categories: Vec<i64> = metric.values.values().map(|v| v.round() as i64).collect();
+                categories.sort_unstable();
+                categories.dedup();
+                categories.into_iter().take(CATEGORICAL_PALETTE.len()).map(|c| c as f64).collect()
+            },
+            _ => (0..5).map(|i| min + (max - min) * i as f64 / 4.0).collect(),
+        };
+        for value in entries {
+            let _ = writeln!(svg, "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>\
+                                   <text x=\"{}\" y=\"{}\" font-size=\"11\">{}</text>",
+                             x + r, y, r, metric.scale.color(value, (min, max)), x + 3.0 * r, y + 4.0,
+                             if metric.scale == ColorScale::Categorical { format!("{}", value) }
+                             else { format!("{:.2}", value) });
+            y += 16.0;
+        }
+        let _ = writeln!(svg, "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>\
+                               <text x=\"{}\" y=\"{}\" font-size=\"11\">no data</text>",
+                         x + r, y, r, NO_DATA_COLOR, x + 3.0 * r, y + 4.0);
+        y += 16.0;
+    }
+    if !options.gaps.is_empty() {
+        let _ = writeln!(svg, "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"#000000\" stroke-width=\"1.5\"/>\
+                               <text x=\"{}\" y=\"{}\" font-size=\"11\">monitoring gap</text>",
+                         x + r, y, r, x + 3.0 * r, y + 4.0);
+        y += 16.0;
+    }
+    if options.show_edges {
+        let _ = writeln!(svg, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#999999\"/>\
+                               <text x=\"{}\" y=\"{}\" font-size=\"11\">adjacency edge</text>",
+                         x, y, x + 2.0 * r, y, x + 3.0 * r, y + 4.0);
+    }
+}
+
+//function to write the svg map to disk
+pub fn write_svg<P: AsRef<Path>>(network: &MonitoringNetwork, options: &MapOptions, path: P)
+    -> Result<(), Box<dyn Error>> {
+    fs::write(path, render_svg(network, options))?;
+    Ok(())
+}
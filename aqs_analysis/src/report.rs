@@ -0,0 +1,272 @@
+//module for writing a self-contained html report of the network analysis
+//charts and the map are inline svg and the gap table sorts with a few lines of inline javascript,
+//so the report opens in any browser without other files or network access
+
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use crate::network::MonitoringNetwork;
+use crate::render::{self, ColorScale, MapOptions, StationMetric};
+use crate::station::Station;
+
+//number of bars in the isolation histogram
+const HISTOGRAM_BINS: usize = 20;
+//size of the histogram and scatter plot
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 320.0;
+//space around the plot area for axis labels
+const CHART_MARGIN: f64 = 48.0;
+
+//function to build the html report as a string
+//inputs: the network (with isolation calculated), pollution per station, and a label for the
+//        pollution values such as "PM2.5 (µg/m³)"
+//gaps use the same 75th percentile thresholds as find_and_print_monitoring_gaps
+pub fn render_html_report(network: &MonitoringNetwork, pollution_data: &HashMap<String, f64>,
+                          pollution_label: &str) -> String {
+    let thresholds = network.default_gap_thresholds(pollution_data);
+    let gaps = match thresholds {
+        Some((isolation, pollution)) => network.find_monitoring_gaps(pollution_data, isolation, pollution),
+        None => Vec::new(),
+    };
+    let correlation = network.analyze_correlation(pollution_data);
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Air quality monitoring network report</title>\n");
+    html.push_str(STYLE);
+    html.push_str("</head>\n<body>\n<h1>Air quality monitoring network report</h1>\n");
+
+    //summary tables
+    let edge_count: usize = network.adjacency_list.values().map(Vec::len).sum::<usize>() / 2;
+    html.push_str("<h2>Summary</h2>\n<table class=\"summary\">\n");
+    summary_row(&mut html, "Stations", &network.stations.len().to_string());
+    summary_row(&mut html, "Adjacency edges", &edge_count.to_string());
+    summary_row(&mut html, &format!("Stations with {}", pollution_label), &pollution_data.len().to_string());
+    summary_row(&mut html, "Correlation of isolation and pollution", &format!("{:.4}", correlation));
+    if let Some((isolation, pollution)) = thresholds {
+        summary_row(&mut html, "Gap thresholds",
+                    &format!("isolation &gt; {:.2} km, pollution &gt; {:.2}", isolation, pollution));
+    }
+    summary_row(&mut html, "Monitoring gaps", &gaps.len().to_string());
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Isolation (km to nearest neighbors)</h2>\n");
+    match network.isolation_statistics() {
+        Some(stats) => {
+            html.push_str("<table class=\"summary\">\n");
+            summary_row(&mut html, "Stations with isolation", &stats.count.to_string());
+            summary_row(&mut html, "Minimum", &format!("{:.2} km", stats.min));
+            summary_row(&mut html, "Median", &format!("{:.2} km", stats.median));
+            summary_row(&mut html, "Mean", &format!("{:.2} km", stats.mean));
+            summary_row(&mut html, "Maximum", &format!("{:.2} km", stats.max));
+            html.push_str("</table>\n");
+            let isolation: Vec<f64> = network.isolation_values().into_values().collect();
+            html.push_str(&histogram_svg(&isolation, "Isolation (km)"));
+        },
+        None => html.push_str("<p>No isolation values calculated.</p>\n"),
+    }
+
+    html.push_str(&format!("<h2>Isolation versus {}</h2>\n", escape(pollution_label)));
+    let pairs: Vec<(f64, f64)> = network.isolation_values().into_iter()
+        .filter_map(|(id, isolation)| Some((isolation, *pollution_data.get(&id)?)))
+        .collect();
+    html.push_str(&format!("<p>Pearson correlation r = {:.4} across {} stations.</p>\n", correlation, pairs.len()));
+    html.push_str(&scatter_svg(&pairs, "Isolation (km)", pollution_label));
+
+    html.push_str(&format!("<h2>Stations by {}</h2>\n", escape(pollution_label)));
+    let map = MapOptions {
+        metric: Some(StationMetric {
+            label: pollution_label.to_string(),
+            values: pollution_data.clone(),
+            scale: ColorScale::GreenToRed,
+        }),
+        gaps: gaps.iter().map(|(station, _)| station.id.clone()).collect(),
+        ..MapOptions::default()
+    };
+    html.push_str(&render::render_svg(network, &map));
+
+    html.push_str("<h2>Monitoring gaps</h2>\n");
+    html.push_str(&gap_table(network, &gaps));
+    html.push_str(SCRIPT);
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+//function to write the html report to disk
+pub fn write_html_report<P: AsRef<Path>>(network: &MonitoringNetwork, pollution_data: &HashMap<String, f64>,
+                                         pollution_label: &str, path: P) -> Result<(), Box<dyn Error>> {
+    fs::write(path, render_html_report(network, pollution_data, pollution_label))?;
+    Ok(())
+}
+
+//escape text for html element content and attribute values
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+//one label/value row of a summary table, the value is already html
+fn summary_row(html: &mut String, label: &str, value: &str) {
+    let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", escape(label), value);
+}
+
+//min and max of a set of values, none if empty
+fn value_range(values: impl Iterator<Item = f64>) -> Option<(f64, f64)> {
+    values.fold(None, |range, value| match range {
+        None => Some((value, value)),
+        Some((min, max)) => Some((min.min(value), max.max(value))),
+    })
+}
+
+//x and y axes with labels and end values around the plot area
+fn axes(svg: &mut String, x_label: &str, x_range: (f64, f64), y_label: &str, y_range: (f64, f64)) {
+    let (left, bottom) = (CHART_MARGIN, CHART_HEIGHT - CHART_MARGIN);
+    let (right, top) = (CHART_WIDTH - CHART_MARGIN / 2.0, CHART_MARGIN / 2.0);
+    let _ = writeln!(svg, "<line x1=\"{left}\" y1=\"{bottom}\" x2=\"{right}\" y2=\"{bottom}\" stroke=\"#333\"/>");
+    let _ = writeln!(svg, "<line x1=\"{left}\" y1=\"{bottom}\" x2=\"{left}\" y2=\"{top}\" stroke=\"#333\"/>");
+    let _ = writeln!(svg, "<text x=\"{left}\" y=\"{}\" font-size=\"11\">{:.1}</text>", bottom + 14.0, x_range.0);
+    let _ = writeln!(svg, "<text x=\"{right}\" y=\"{}\" font-size=\"11\" text-anchor=\"end\">{:.1}</text>",
+                     bottom + 14.0, x_range.1);
+    let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"12\" text-anchor=\"middle\">{}</text>",
+                     (left + right) / 2.0, CHART_HEIGHT - 8.0, escape(x_label));
+    let _ = writeln!(svg, "<text x=\"{}\" y=\"{bottom}\" font-size=\"11\" text-anchor=\"end\">{:.1}</text>",
+                     left - 4.0, y_range.0);
+    let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" font-size=\"11\" text-anchor=\"end\">{:.1}</text>",
+                     left - 4.0, top + 10.0, y_range.1);
+    let _ = writeln!(svg, "<text x=\"12\" y=\"{}\" font-size=\"12\" text-anchor=\"middle\" \
+                           transform=\"rotate(-90 12 {})\">{}</text>",
+                     (top + bottom) / 2.0, (top + bottom) / 2.0, escape(y_label));
+}
+
+//histogram of values as an svg bar chart
+fn histogram_svg(values: &[f64], x_label: &str) -> String {
+    let Some((_, max)) = value_range(values.iter().copied()) else {
+        return String::new();
+    };
+    let max = if max > 0.0 { max } else { 1.0 };
+    let bin_width = max / HISTOGRAM_BINS as f64;
+    let mut counts = [0usize; HISTOGRAM_BINS];
+    for value in values {
+        counts[((value / bin_width) as usize).min(HISTOGRAM_BINS - 1)] += 1;
+    }
+    let tallest = counts.iter().copied().max().unwrap_or(1).max(1);
+
+    let plot_width = CHART_WIDTH - CHART_MARGIN * 1.5;
+    let plot_height = CHART_HEIGHT - CHART_MARGIN * 1.5;
+    let bar_width = plot_width / HISTOGRAM_BINS as f64;
+    let mut svg = format!("<svg class=\"chart\" width=\"{CHART_WIDTH}\" height=\"{CHART_HEIGHT}\" \
+                           viewBox=\"0 0 {CHART_WIDTH} {CHART_HEIGHT}\" xmlns=\"http://www.w3.org/2000/svg\">\n");
+    for (i, count) in counts.iter().enumerate() {
+        let height = *count as f64 / tallest as f64 * plot_height;
+        let _ = writeln!(svg, "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#4a78b0\">\
+                               <title>{:.1}-{:.1} km: {} stations</title></rect>",
+                         CHART_MARGIN + i as f64 * bar_width + 1.0, CHART_HEIGHT - CHART_MARGIN - height,
+                         bar_width - 2.0, height, i as f64 * bin_width, (i + 1) as f64 * bin_width, count);
+    }
+    axes(&mut svg, x_label, (0.0, max), "Stations", (0.0, tallest as f64));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+//scatter plot of (x, y) pairs with a least-squares line
+fn scatter_svg(pairs: &[(f64, f64)], x_label: &str, y_label: &str) -> String {
+    let (Some(x_range), Some(y_range)) = (value_range(pairs.iter().map(|(x, _)| *x)),
+                                          value_range(pairs.iter().map(|(_, y)| *y))) else {
+        return "<p>No stations have both isolation and pollution values.</p>\n".to_string();
+    };
+    let plot_width = CHART_WIDTH - CHART_MARGIN * 1.5;
+    let plot_height = CHART_HEIGHT - CHART_MARGIN * 1.5;
+    let scale = |value: f64, (min, max): (f64, f64)| if max > min { (value - min) / (max - min) } else { 0.5 };
+    let to_x = |x: f64| CHART_MARGIN + scale(x, x_range) * plot_width;
+    let to_y = |y: f64| CHART_HEIGHT - CHART_MARGIN - scale(y, y_range) * plot_height;
+
+    let mut svg = format!("<svg class=\"chart\" width=\"{CHART_WIDTH}\" height=\"{CHART_HEIGHT}\" \
+                           viewBox=\"0 0 {CHART_WIDTH} {CHART_HEIGHT}\" xmlns=\"http://www.w3.org/2000/svg\">\n");
+    for (x, y) in pairs {
+        let _ = writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"2.5\" fill=\"#4a78b0\" fill-opacity=\"0.6\"/>",
+                         to_x(*x), to_y(*y));
+    }
+
+    //least-squares fit, drawn across the x range and clipped to the plot by the viewbox
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = pairs.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = pairs.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    if sxx > 0.0 {
+        let slope = sxy / sxx;
+        let line = |x: f64| (mean_y + slope * (x - mean_x)).clamp(y_range.0, y_range.1);
+        let _ = writeln!(svg, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#c0392b\" \
+                               stroke-width=\"1.5\"/>",
+                         to_x(x_range.0), to_y(line(x_range.0)), to_x(x_range.1), to_y(line(x_range.1)));
+    }
+    axes(&mut svg, x_label, x_range, y_label, y_range);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+//sortable table of monitoring gaps, most isolated first
+fn gap_table(network: &MonitoringNetwork, gaps: &[(&Station, f64)]) -> String {
+    if gaps.is_empty() {
+        return "<p>No monitoring gaps found.</p>\n".to_string();
+    }
+    let mut html = String::from("<table class=\"sortable\" id=\"gaps\">\n<thead><tr>");
+    for (i, (heading, numeric)) in [("Site", false), ("City", false), ("County", false), ("State", false),
+                                    ("Latitude", true), ("Longitude", true), ("Pollution", true),
+                                    ("Isolation (km)", true)].iter().enumerate() {
+        let _ = write!(html, "<th onclick=\"sortTable('gaps', {}, {})\">{}</th>", i, numeric, heading);
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for (station, pollution) in gaps {
+        let _ = writeln!(html, "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.4}</td><td>{:.4}</td>\
+                                <td>{:.2}</td><td>{:.2}</td></tr>",
+                         escape(&station.site_name), escape(&station.city_name), escape(&station.county_name),
+                         escape(&station.state_name), station.latitude, station.longitude, pollution,
+                         network.isolation(&station.id).unwrap_or(0.0));
+    }
+    html.push_str("</tbody>\n</table>\n");
+    html
+}
+
+const STYLE: &str = "<style>
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }
+table.summary th { background: #f4f4f4; }
+table.sortable th { background: #e8eef6; cursor: pointer; user-select: none; }
+svg { border: 1px solid #ddd; background: #fff; display: block; margin-bottom: 0.5em; }
+.legend { font-size: 0.9em; }
+</style>
+";
+
+//clicking a heading sorts by that column, clicking again reverses the order
+const SCRIPT: &str = "<script>
+function sortTable(id, column, numeric) {
+  var table = document.getElementById(id);
+  var body = table.tBodies[0];
+  var rows = Array.prototype.slice.call(body.rows);
+  var ascending = table.dataset.column != column || table.dataset.order != 'asc';
+  rows.sort(function (a, b) {
+    var x = a.cells[column].textContent, y = b.cells[column].textContent;
+    var order = numeric ? parseFloat(x) - parseFloat(y) : x.localeCompare(y);
+    return ascending ? order : -order;
+  });
+  rows.forEach(function (row) { body.appendChild(row); });
+  table.dataset.column = column;
+  table.dataset.order = ascending ? 'asc' : 'desc';
+}
+</script>
+";
@@ -0,0 +1,303 @@
+//module for serving read-only json queries over the monitoring network
+//requests are handled by ApiServer::handle so they can be tested without opening a socket
+//
+//endpoints (all GET):
+//  /stations/{id}                                   station lookup by id
+//  /nearest?lat=..&lon=..&k=5                       k nearest stations to a point, k is capped at 100
+//  /within?lat=..&lon=..&radius_km=..               stations within a radius of a point
+//      both accept optional parameter, land_use and location_setting filters
+//  /isolation                                       isolation statistics
+//  /gaps?parameter=88101&isolation_threshold=..&pollution_threshold=..
+//                                                   monitoring gaps, thresholds default to 75th percentiles
+
+
+use std::collections::HashMap;
+use std::error::Error;
+use serde::Serialize;
+use serde_json::json;
+use crate::monitor::{self, SiteAggregation};
+use crate::network::{MonitoringNetwork, NearbyStation, StationQuery};
+use crate::station::{LandUse, LocationSetting, Station};
+
+//number of stations returned by /nearest when k is not given
+const DEFAULT_NEAREST_K: usize = 5;
+//largest number of stations returned by /nearest, larger k values are capped
+const MAX_NEAREST_K: usize = 100;
+
+//status code and json body of a response
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl ApiResponse {
+    fn ok<T: Serialize>(value: &T) -> Self {
+        match serde_json::to_string(value) {
+            Ok(body) => ApiResponse { status: 200, body },
+            Err(err) => ApiResponse::error(500, &err.to_string()),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        ApiResponse { status, body: json!({ "error": message }).to_string() }
+    }
+}
+
+//station fields returned by the api
+#[derive(Serialize)]
+struct StationJson<'a> {
+    id: &'a str,
+    site_name: &'a str,
+    city_name: &'a str,
+    county_name: &'a str,
+    state_name: &'a str,
+    latitude: f64,
+    longitude: f64,
+    land_use: &'a str,
+    location_setting: &'a str,
+    elevation_m: Option<f64>,
+    cbsa_name: Option<&'a str>,
+    tribe_name: Option<&'a str>,
+    isolation_km: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distance_km: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bearing_degrees: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pollution: Option<f64>,
+}
+
+impl<'a> StationJson<'a> {
+    fn new(network: &MonitoringNetwork, station: &'a Station) -> Self {
+        StationJson {
+            id: &station.id,
+            site_name: &station.site_name,
+            city_name: &station.city_name,
+            county_name: &station.county_name,
+            state_name: &station.state_name,
+            latitude: station.latitude,
+            longitude: station.longitude,
+            land_use: station.land_use.as_str(),
+            location_setting: station.location_setting.as_str(),
+            elevation_m: station.elevation,
+            cbsa_name: station.cbsa_name.as_deref(),
+            tribe_name: station.tribe_name.as_deref(),
+            isolation_km: network.isolation(&station.id),
+            distance_km: None,
+            bearing_degrees: None,
+            direction: None,
+            pollution: None,
+        }
+    }
+}
+
+//the network being served
+pub struct ApiServer {
+    network: MonitoringNetwork,
+}
+
+impl ApiServer {
+    pub fn new(network: MonitoringNetwork) -> Self {
+        ApiServer { network }
+    }
+
+    pub fn network(&self) -> &MonitoringNetwork {
+        &self.network
+    }
+
+    //function to answer one request
+    //inputs: http method and the request url (path plus optional query string)
+    pub fn handle(&self, method: &str, url: &str) -> ApiResponse {
+        if method != "GET" {
+            return ApiResponse::error(405, "only GET is supported");
+        }
+        let (path, query) = match url.split_once('?') {
+            Some((path, query)) => (path, parse_query(query)),
+            None => (url, HashMap::new()),
+        };
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match segments.as_slice() {
+            ["stations", id] => self.station(&percent_decode(id)),
+            ["nearest"] => self.nearest(&query),
+            ["within"] => self.within(&query),
+            ["isolation"] => self.isolation(),
+            ["gaps"] => self.gaps(&query),
+            _ => ApiResponse::error(404, "unknown endpoint"),
+        }
+    }
+
+    fn station(&self, id: &str) -> ApiResponse {
+        match self.network.stations.get(id) {
+            Some(station) => ApiResponse::ok(&StationJson::new(&self.network, station)),
+            None => ApiResponse::error(404, &format!("station {} not found", id)),
+        }
+    }
+
+    fn nearest(&self, query: &HashMap<String, String>) -> ApiResponse {
+        let (lat, lon) = match coordinates(query) {
+            Ok(point) => point,
+            Err(response) => return response,
+        };
+        let k = match query.get("k").map(|text| text.parse::<usize>()) {
+            None => DEFAULT_NEAREST_K,
+            Some(Ok(k)) if k > 0 => k.min(MAX_NEAREST_K),
+            Some(_) => return ApiResponse::error(400, "k must be a positive integer"),
+        };
+        self.station_list(self.network.nearest_stations(lat, lon, k, &station_query(query)))
+    }
+
+    fn within(&self, query: &HashMap<String, String>) -> ApiResponse {
+        let (lat, lon) = match coordinates(query) {
+            Ok(point) => point,
+            Err(response) => return response,
+        };
+        let radius = match optional_number(query, "radius_km") {
+            Ok(Some(radius)) if radius >= 0.0 => radius,
+            Ok(_) => return ApiResponse::error(400, "radius_km must be a non-negative number"),
+            Err(response) => return response,
+        };
+        self.station_list(self.network.stations_within(lat, lon, radius, &station_query(query)))
+    }
+
+    fn station_list(&self, found: Vec<NearbyStation>) -> ApiResponse {
+        let stations: Vec<StationJson> = found.iter()
+            .map(|nearby| StationJson {
+                distance_km: Some(nearby.distance_km),
+                bearing_degrees: Some(nearby.bearing_degrees),
+                direction: Some(nearby.compass_direction()),
+                ..StationJson::new(&self.network, nearby.station)
+            })
+            .collect();
+        ApiResponse::ok(&stations)
+    }
+
+    fn isolation(&self) -> ApiResponse {
+        match self.network.isolation_statistics() {
+            Some(stats) => ApiResponse::ok(&stats),
+            None => ApiResponse::error(404, "isolation has not been calculated"),
+        }
+    }
+
+    fn gaps(&self, query: &HashMap<String, String>) -> ApiResponse {
+        let parameter = query.get("parameter").map(String::as_str).unwrap_or(monitor::PM25_PARAMETER);
+        let aggregation = if parameter == monitor::PM25_PARAMETER {
+            SiteAggregation::pm25()
+        } else {
+            SiteAggregation { parameter_code: parameter.to_string(), sample_duration: None, ..SiteAggregation::pm25() }
+        };
+        let pollution_data = self.network.site_values(&aggregation);
+        let defaults = self.network.default_gap_thresholds(&pollution_data);
+
+        let isolation_threshold = match optional_number(query, "isolation_threshold") {
+            Ok(value) => value.or(defaults.map(|(isolation, _)| isolation)),
+            Err(response) => return response,
+        };
+        let pollution_threshold = match optional_number(query, "pollution_threshold") {
+            Ok(value) => value.or(defaults.map(|(_, pollution)| pollution)),
+            Err(response) => return response,
+        };
+        let (isolation_threshold, pollution_threshold) = match (isolation_threshold, pollution_threshold) {
+            (Some(isolation), Some(pollution)) => (isolation, pollution),
+            _ => return ApiResponse::error(404, "no data available for this parameter"),
+        };
+
+        let gaps: Vec<StationJson> = self.network
+            .find_monitoring_gaps(&pollution_data, isolation_threshold, pollution_threshold)
+            .into_iter()
+            .map(|(station, pollution)| StationJson { pollution: Some(pollution), ..StationJson::new(&self.network, station) })
+            .collect();
+        ApiResponse::ok(&json!({
+            "parameter": parameter,
+            "isolation_threshold": isolation_threshold,
+            "pollution_threshold": pollution_threshold,
+            "gaps": gaps,
+        }))
+    }
+}
+
+//optional station filters shared by /nearest and /within
+fn station_query(query: &HashMap<String, String>) -> StationQuery {
+    StationQuery {
+        parameter_code: query.get("parameter").cloned(),
+        land_use: query.get("land_use").map(|text| LandUse::from(text.as_str())),
+        location_setting: query.get("location_setting").map(|text| LocationSetting::from(text.as_str())),
+    }
+}
+
+//lat and lon query parameters, both required
+fn coordinates(query: &HashMap<String, String>) -> Result<(f64, f64), ApiResponse> {
+    match (optional_number(query, "lat")?, optional_number(query, "lon")?) {
+        (Some(lat), Some(lon)) if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) => Ok((lat, lon)),
+        (Some(_), Some(_)) => Err(ApiResponse::error(400, "lat or lon out of range")),
+        _ => Err(ApiResponse::error(400, "lat and lon are required")),
+    }
+}
+
+//numeric query parameter, none if missing, an error response if it does not parse
+fn optional_number(query: &HashMap<String, String>, name: &str) -> Result<Option<f64>, ApiResponse> {
+    match query.get(name) {
+        None => Ok(None),
+        Some(text) => text.parse::<f64>()
+            .map(Some)
+            .map_err(|_| ApiResponse::error(400, &format!("{} must be a number", name))),
+    }
+}
+
+//split a query string into decoded key/value pairs
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+//decode %xx escapes and + as space
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+//function to serve the api over http until the process is stopped
+//inputs: the api server and an address such as "127.0.0.1:8080"
+pub fn serve(api: &ApiServer, address: &str) -> Result<(), Box<dyn Error>> {
+    let server = tiny_http::Server::http(address).map_err(|err| err.to_string())?;
+    println!("Serving monitoring network API on http://{}", address);
+    let content_type = tiny_http::Header::from_bytes("Content-Type", "application/json")
+        .map_err(|_| "invalid header")?;
+    for request in server.incoming_requests() {
+        let response = api.handle(request.method().as_str(), request.url());
+        let reply = tiny_http::Response::from_string(response.body)
+            .with_status_code(response.status)
+            .with_header(content_type.clone());
+        if let Err(err) = request.respond(reply) {
+            eprintln!("Error sending response: {}", err);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+//module for saving and reloading the monitoring network so repeated analyses skip csv parsing
+//a snapshot holds the stations, adjacency list and computed metrics, plus the load policy and checksums of its
+//source files
+
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::data::LoadPolicy;
+use crate::network::MonitoringNetwork;
+
+//bumped whenever the layout of the snapshot or MonitoringNetwork (or anything it contains) changes
+pub const SNAPSHOT_VERSION: u32 = 6;
+//first bytes of every binary snapshot file
+const MAGIC: &[u8; 4] = b"AQSN";
+
+//checksum of one file the network was built from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceChecksum {
+    pub path: String,
+    pub checksum: u64,
+}
+
+//a saved network with the information needed to tell whether it is still current
+#[derive(Serialize, Deserialize)]
+pub struct NetworkSnapshot {
+    pub version: u32,
+    pub policy: LoadPolicy, //policy the stations were loaded with
+    pub sources: Vec<SourceChecksum>,
+    pub network: MonitoringNetwork,
+}
+
+//borrowed form of NetworkSnapshot so saving does not need to clone the network
+//field order must match NetworkSnapshot for the binary format
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    policy: &'a LoadPolicy,
+    sources: &'a [SourceChecksum],
+    network: &'a MonitoringNetwork,
+}
+
+impl NetworkSnapshot {
+    //check that the snapshot was loaded with the given policy and that every source file still exists and
+    //has the checksum recorded in the snapshot
+    pub fn is_current(&self, policy: &LoadPolicy) -> bool {
+        self.policy == *policy && self.sources.iter().all(|source| {
+            checksum_file(&source.path).is_ok_and(|checksum| checksum == source.checksum)
+        })
+    }
+}
+
+//function to compute a 64 bit FNV-1a checksum of a file's contents
+pub fn checksum_file<P: AsRef<Path>>(path: P) -> std::io::Result<u64> {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buffer = [0u8; 64 * 1024];
+    let mut hash = FNV_OFFSET;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        for byte in &buffer[..read] {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+    Ok(hash)
+}
+
+//checksums for a list of source files
+fn source_checksums<P: AsRef<Path>>(sources: &[P]) -> Result<Vec<SourceChecksum>, Box<dyn Error>> {
+    let mut checksums = Vec::new();
+    for source in sources {
+        checksums.push(SourceChecksum {
+            path: source.as_ref().to_string_lossy().to_string(),
+            checksum: checksum_file(source)?,
+        });
+    }
+    Ok(checksums)
+}
+
+//function to save a binary snapshot of the network
+//inputs: the network, the policy its stations were loaded with, the files it was built from, and the output path
+pub fn save_snapshot<P: AsRef<Path>, S: AsRef<Path>>(network: &MonitoringNetwork, policy: &LoadPolicy, sources: &[S], path: P)
+    -> Result<(), Box<dyn Error>> {
+    let sources = source_checksums(sources)?;
+    let snapshot = SnapshotRef { version: SNAPSHOT_VERSION, policy, sources: &sources, network };
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    bincode::serialize_into(&mut writer, &snapshot)?;
+    writer.flush()?;
+    Ok(())
+}
+
+//function to load a binary snapshot
+//outputs: an error if the file is not a snapshot or was written by a different snapshot version
+pub fn load_snapshot<P: AsRef<Path>>(path: P) -> Result<NetworkSnapshot, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    if bytes.len() < 8 || &bytes[..4] != MAGIC {
+        return Err("not a network snapshot file".into());
+    }
+    //version is the first field of the payload, check it before decoding the rest
+    let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    if version != SNAPSHOT_VERSION {
+        return Err(format!("snapshot version {} is not supported (expected {})", version, SNAPSHOT_VERSION).into());
+    }
+    Ok(bincode::deserialize(&bytes[4..])?)
+}
+
+//function to save the snapshot as json, slower and larger but readable by other tools
+pub fn save_snapshot_json<P: AsRef<Path>, S: AsRef<Path>>(network: &MonitoringNetwork, policy: &LoadPolicy, sources: &[S],
+                                                          path: P)
+    -> Result<(), Box<dyn Error>> {
+    let sources = source_checksums(sources)?;
+    let snapshot = SnapshotRef { version: SNAPSHOT_VERSION, policy, sources: &sources, network };
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, &snapshot)?;
+    writer.flush()?;
+    Ok(())
+}
+
+//function to load a json snapshot
+pub fn load_snapshot_json<P: AsRef<Path>>(path: P) -> Result<NetworkSnapshot, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    let snapshot: NetworkSnapshot = serde_json::from_reader(reader)?;
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(format!("snapshot version {} is not supported (expected {})",
+                           snapshot.version, SNAPSHOT_VERSION).into());
+    }
+    Ok(snapshot)
+}
@@ -0,0 +1,285 @@
+//module for fast nearest-station lookups at arbitrary coordinates
+//uses the same grid-cell idea as build_adjacency_list, searching outward ring by ring
+//longitude cells wrap around the antimeridian, so aleutian, guam and samoa sites find neighbors on both sides
+
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use crate::station::Station;
+
+//constant for earth's radius in kilometers
+pub const EARTH_RADIUS_KM: f64 = 6371.0;
+//length of one degree of latitude in kilometers
+const KM_PER_DEGREE: f64 = EARTH_RADIUS_KM * std::f64::consts::PI / 180.0;
+
+//calculate distance between two points given long and lat using Haversine distance formula
+pub fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    //convert degrees to radians (necessary for equation)
+    let lat1 = lat1.to_radians();
+    let lon1 = lon1.to_radians();
+    let lat2 = lat2.to_radians();
+    let lon2 = lon2.to_radians();
+    //haversine formula calculation
+    let dlat = lat2 - lat1;
+    let dlon = lon2 - lon1;
+    let a = (dlat/2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon/2.0).sin().powi(2);
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+    EARTH_RADIUS_KM * c
+}
+
+//clarke 1866 ellipsoid used by nad27, and wgs84
+const CLARKE_1866_A: f64 = 6_378_206.4;
+const CLARKE_1866_F: f64 = 1.0 / 294.978_698_214;
+const WGS84_A: f64 = 6_378_137.0;
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+//datum shift (dx, dy, dz) in meters
+type DatumShift = (f64, f64, f64);
+//regional nad27 to wgs84 datum shifts in meters (nima tr8350.2), as (lat min, lat max, lon min, lon max, shift)
+//the aleutian boxes come before mainland alaska so they take precedence where they overlap
+const NAD27_REGIONS: [(f64, f64, f64, f64, DatumShift); 4] = [
+    (24.0, 50.0, -125.5, -66.0, (-8.0, 160.0, 176.0)),   //conus mean
+    (50.5, 55.5, -180.0, -164.0, (-2.0, 152.0, 149.0)),  //aleutian islands east of 180
+    (50.5, 55.5, 170.0, 180.0, (2.0, 204.0, 105.0)),     //aleutian islands west of 180
+    (54.0, 72.0, -180.0, -129.5, (-5.0, 135.0, 172.0)),  //alaska
+];
+
+//function to convert nad27 coordinates to wgs84 with the abridged molodensky transformation
+//inputs: latitude and longitude in degrees and ellipsoidal height in meters
+//outputs: converted coordinates, none outside the conus and alaska where no regional shift applies
+//accurate to a few meters, well below the distances used for isolation
+pub fn nad27_to_wgs84(lat: f64, lon: f64, height: f64) -> Option<(f64, f64)> {
+    let (dx, dy, dz) = NAD27_REGIONS.iter()
+        .find(|(lat_min, lat_max, lon_min, lon_max, _)| (*lat_min..=*lat_max).contains(&lat) && (*lon_min..=*lon_max).contains(&lon))
+        .map(|region| region.4)?;
+    let da = WGS84_A - CLARKE_1866_A;
+    let df = WGS84_F - CLARKE_1866_F;
+    let (phi, lambda) = (lat.to_radians(), lon.to_radians());
+    let e2 = 2.0 * CLARKE_1866_F - CLARKE_1866_F * CLARKE_1866_F;
+    let w = (1.0 - e2 * phi.sin().powi(2)).sqrt();
+    let m = CLARKE_1866_A * (1.0 - e2) / w.powi(3); //meridian radius of curvature
+    let n = CLARKE_1866_A / w; //prime vertical radius of curvature
+
+    let dphi = (-dx * phi.sin() * lambda.cos() - dy * phi.sin() * lambda.sin() + dz * phi.cos()
+                + (CLARKE_1866_A * df + CLARKE_1866_F * da) * (2.0 * phi).sin()) / (m + height);
+    let dlambda = (-dx * lambda.sin() + dy * lambda.cos()) / ((n + height) * phi.cos());
+    Some((lat + dphi.to_degrees(), normalize_longitude(lon + dlambda.to_degrees())))
+}
+
+//longitude in degrees wrapped to [-180, 180)
+pub fn normalize_longitude(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+//initial great-circle bearing from the first point to the second, degrees clockwise from north (0-360)
+pub fn initial_bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let lat1 = lat1.to_radians();
+    let lat2 = lat2.to_radians();
+    let dlon = (lon2 - lon1).to_radians();
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+//point a fraction (0-1) of the way along the great circle from the first point to the second, in degrees
+pub fn intermediate_point(lat1: f64, lon1: f64, lat2: f64, lon2: f64, fraction: f64) -> (f64, f64) {
+    let delta = haversine_km(lat1, lon1, lat2, lon2) / EARTH_RADIUS_KM; //angular distance
+    if delta == 0.0 {
+        return (lat1, lon1);
+    }
+    let (phi1, lambda1, phi2, lambda2) = (lat1.to_radians(), lon1.to_radians(), lat2.to_radians(), lon2.to_radians());
+    let a = ((1.0 - fraction) * delta).sin() / delta.sin();
+    let b = (fraction * delta).sin() / delta.sin();
+    let x = a * phi1.cos() * lambda1.cos() + b * phi2.cos() * lambda2.cos();
+    let y = a * phi1.cos() * lambda1.sin() + b * phi2.cos() * lambda2.sin();
+    let z = a * phi1.sin() + b * phi2.sin();
+    (z.atan2((x * x + y * y).sqrt()).to_degrees(), normalize_longitude(y.atan2(x).to_degrees()))
+}
+
+//16-point compass direction for a bearing in degrees, e.g. 100 -> "E"
+pub fn compass_direction(bearing: f64) -> &'static str {
+    const DIRECTIONS: [&str; 16] = ["N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE",
+                                    "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW"];
+    let index = ((bearing.rem_euclid(360.0) / 22.5) + 0.5).floor() as usize % 16;
+    DIRECTIONS[index]
+}
+
+//an indexed location: (station id, lat, lon)
+type IndexEntry = (String, f64, f64);
+
+//grid of station locations for nearest-neighbor and radius queries
+//cells are (longitude column, latitude row), columns are numbered 0 to columns - 1 eastward from -180
+pub struct SpatialIndex {
+    cell_size: f64, //degrees of latitude per row
+    columns: i32,
+    column_size: f64, //degrees of longitude per column, the cell size adjusted to divide 360
+    cells: HashMap<(i32, i32), Vec<IndexEntry>>,
+    min_cell: (i32, i32),
+    max_cell: (i32, i32),
+    len: usize,
+}
+
+impl SpatialIndex {
+    //create an empty index with the given cell size in degrees
+    pub fn new(cell_size_degrees: f64) -> Self {
+        let columns = (360.0 / cell_size_degrees).ceil().max(1.0) as i32;
+        SpatialIndex {
+            cell_size: cell_size_degrees,
+            columns,
+            column_size: 360.0 / columns as f64,
+            cells: HashMap::new(),
+            min_cell: (i32::MAX, i32::MAX),
+            max_cell: (i32::MIN, i32::MIN),
+            len: 0,
+        }
+    }
+
+    //build an index with 1 degree cells (about 100km) from a set of stations
+    pub fn from_stations<'a, I: IntoIterator<Item = &'a Station>>(stations: I) -> Self {
+        let mut index = SpatialIndex::new(1.0);
+        for station in stations {
+            index.insert(&station.id, station.latitude, station.longitude);
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn cell_of(&self, lat: f64, lon: f64) -> (i32, i32) {
+        (self.column_of(lon).rem_euclid(self.columns), (lat / self.cell_size).floor() as i32)
+    }
+
+    //column of a longitude before wrapping, so a range of longitudes maps to a contiguous range of columns
+    fn column_of(&self, lon: f64) -> i32 {
+        ((lon + 180.0) / self.column_size).floor() as i32
+    }
+
+    //columns apart going the short way around
+    fn column_distance(&self, a: i32, b: i32) -> i32 {
+        let d = (a - b).rem_euclid(self.columns);
+        d.min(self.columns - d)
+    }
+
+    //rings needed from a center column to reach every occupied column
+    fn column_reach(&self, center: i32) -> i32 {
+        let antipode = (center + self.columns / 2).rem_euclid(self.columns);
+        if (self.min_cell.0..=self.max_cell.0).contains(&antipode) {
+            self.columns / 2
+        } else {
+            self.column_distance(center, self.min_cell.0).max(self.column_distance(center, self.max_cell.0))
+        }
+    }
+
+    //add a location to the index
+    pub fn insert(&mut self, id: &str, lat: f64, lon: f64) {
+        let cell = self.cell_of(lat, lon);
+        self.min_cell = (self.min_cell.0.min(cell.0), self.min_cell.1.min(cell.1));
+        self.max_cell = (self.max_cell.0.max(cell.0), self.max_cell.1.max(cell.1));
+        self.cells.entry(cell).or_default().push((id.to_string(), lat, lon));
+        self.len += 1;
+    }
+
+    //function to find the k nearest indexed locations to a point
+    //outputs: (station id, distance in km) sorted by distance
+    pub fn nearest(&self, lat: f64, lon: f64, k: usize) -> Vec<(String, f64)> {
+        self.nearest_matching(lat, lon, k, |_| true)
+    }
+
+    //function to find the k nearest indexed locations to a point that pass a filter on the id
+    //the search keeps growing ring by ring until k accepted locations are found or the index is exhausted
+    //outputs: (station id, distance in km) sorted by distance
+    pub fn nearest_matching(&self, lat: f64, lon: f64, k: usize, accept: impl Fn(&str) -> bool) -> Vec<(String, f64)> {
+        if k == 0 || self.is_empty() {
+            return Vec::new();
+        }
+        let center = self.cell_of(lat, lon);
+        //number of rings needed to cover every occupied cell
+        let max_ring = [
+            self.column_reach(center.0), center.1 - self.min_cell.1, self.max_cell.1 - center.1,
+        ].into_iter().max().unwrap_or(0).max(0);
+
+        let mut found: Vec<(String, f64)> = Vec::new();
+        for ring in 0..=max_ring {
+            for (cx, cy) in ring_cells(center, ring, self.columns) {
+                if let Some(entries) = self.cells.get(&(cx, cy)) {
+                    for (id, slat, slon) in entries.iter().filter(|(id, _, _)| accept(id)) {
+                        found.push((id.clone(), haversine_km(lat, lon, *slat, *slon)));
+                    }
+                }
+            }
+            if found.len() >= k {
+                found.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+                //anything outside this ring is at least this far away
+                //(with a margin since great circles are shorter than paths along a parallel)
+                let farthest_lat = (lat.abs() + (ring + 1) as f64 * self.cell_size).min(89.9);
+                let bound = 0.9 * ring as f64 * self.cell_size.min(self.column_size) * KM_PER_DEGREE
+                    * farthest_lat.to_radians().cos();
+                if found[k - 1].1 <= bound {
+                    break;
+                }
+            }
+        }
+        found.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+        found.truncate(k);
+        found
+    }
+
+    //function to find every indexed location within a radius of a point
+    //outputs: (station id, distance in km) sorted by distance
+    pub fn within(&self, lat: f64, lon: f64, radius_km: f64) -> Vec<(String, f64)> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let lat_span = radius_km / KM_PER_DEGREE;
+        let farthest_lat = (lat.abs() + lat_span).min(89.9);
+        let lon_span = lat_span / farthest_lat.to_radians().cos();
+        let (_, y0) = self.cell_of(lat - lat_span, lon);
+        let (_, y1) = self.cell_of(lat + lat_span, lon);
+        //columns are wrapped, a span of a full circle or more visits each column once
+        let (x0, x1) = (self.column_of(lon - lon_span), self.column_of(lon + lon_span));
+        let x1 = x1.min(x0 + self.columns - 1);
+
+        let mut found = Vec::new();
+        for cx in (x0..=x1).map(|x| x.rem_euclid(self.columns)) {
+            for cy in y0.max(self.min_cell.1)..=y1.min(self.max_cell.1) {
+                if let Some(entries) = self.cells.get(&(cx, cy)) {
+                    for (id, slat, slon) in entries {
+                        let distance = haversine_km(lat, lon, *slat, *slon);
+                        if distance <= radius_km {
+                            found.push((id.clone(), distance));
+                        }
+                    }
+                }
+            }
+        }
+        found.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+        found
+    }
+}
+
+//cells on the border of the square ring at the given distance from the center cell
+//columns wrap around, once the ring reaches halfway around the earth each column is still visited once
+fn ring_cells(center: (i32, i32), ring: i32, columns: i32) -> Vec<(i32, i32)> {
+    if ring == 0 {
+        return vec![center];
+    }
+    let mut cells = Vec::with_capacity(8 * ring as usize);
+    for offset in 0..=ring.min(columns / 2) {
+        let mut xs = vec![(center.0 - offset).rem_euclid(columns), (center.0 + offset).rem_euclid(columns)];
+        xs.dedup();
+        for x in xs {
+            cells.push((x, center.1 - ring));
+            cells.push((x, center.1 + ring));
+            //the sides of the ring
+            if offset == ring {
+                cells.extend(((-ring + 1)..ring).map(|dy| (x, center.1 + dy)));
+            }
+        }
+    }
+    cells
+}
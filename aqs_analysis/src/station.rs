@@ -1,11 +1,115 @@
 //module for representing air quality monitoring stations and their attributes
 
 
-use serde::Deserialize;
+use std::fmt;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use crate::spatial;
+
+//defines an enum of aqs text values, with an Unknown variant that keeps any other text as written
+//generates as_str, a case-insensitive From<&str> that ignores surrounding whitespace, From<String>,
+//Into<String> and Display, and serializes the enum as the aqs text
+macro_rules! aqs_text_enum {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident => $text:literal,)+ }) => {
+        $(#[$meta])*
+        #[serde(from = "String", into = "String")]
+        pub enum $name {
+            $($variant,)+
+            Unknown(String),
+        }
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $text,)+
+                    $name::Unknown(text) => text,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(text: &str) -> Self {
+                match text.trim().to_ascii_uppercase().as_str() {
+                    $($text => $name::$variant,)+
+                    _ => $name::Unknown(text.trim().to_string()),
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(text: String) -> Self {
+                $name::from(text.as_str())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.as_str().to_string()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", self.as_str())
+            }
+        }
+    };
+}
+
+aqs_text_enum! {
+    //aqs land use category of the area around a site, blank or unlisted values are Unknown
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+    LandUse {
+        Residential => "RESIDENTIAL",
+        Commercial => "COMMERCIAL",
+        Industrial => "INDUSTRIAL",
+        Agricultural => "AGRICULTURAL",
+        Forest => "FOREST",
+        Desert => "DESERT",
+        Mobile => "MOBILE",
+        Blighted => "BLIGHTED",
+    }
+}
+
+aqs_text_enum! {
+    //aqs location setting of a site, blank or unlisted values are Unknown
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+    LocationSetting {
+        UrbanAndCenterCity => "URBAN AND CENTER CITY",
+        Suburban => "SUBURBAN",
+        Rural => "RURAL",
+    }
+}
+
+aqs_text_enum! {
+    //geodetic datum of a site's coordinates as reported in the aqs sites file
+    //nad83 is within a meter or two of wgs84 across the conus and is used as is
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    Datum {
+        Wgs84 => "WGS84",
+        Nad83 => "NAD83",
+        Nad27 => "NAD27",
+    }
+}
+
+//a blank land use, as read from an empty csv field
+impl Default for LandUse {
+    fn default() -> Self {
+        LandUse::Unknown(String::new())
+    }
+}
+
+//a blank location setting, as read from an empty csv field
+impl Default for LocationSetting {
+    fn default() -> Self {
+        LocationSetting::Unknown(String::new())
+    }
+}
 
 //represents an air quality monitoring station with its metadata and isolation metrics
 //used as nodes in the monitoring network graph
-#[derive(Debug, Deserialize)]
+//the default is a blank record at (0, 0), mainly for building stations field by field
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Station {
     #[serde(rename = "State Code")]
     pub state_code: String,
@@ -17,10 +121,19 @@ pub struct Station {
     pub latitude: f64,
     #[serde(rename = "Longitude")]
     pub longitude: f64,
+    //datum of latitude/longitude, read_stations converts nad27 sites in the conus and alaska to wgs84
+    #[serde(rename = "Datum", default)]
+    pub datum: Option<Datum>,
+    #[serde(rename = "Elevation", default)]
+    pub elevation: Option<f64>, //meters above sea level
+    #[serde(rename = "Site Established Date", default)]
+    pub date_established: Option<NaiveDate>,
+    #[serde(rename = "Site Closed Date", default)]
+    pub date_closed: Option<NaiveDate>,
     #[serde(rename = "Land Use")]
-    pub land_use: String,
+    pub land_use: LandUse,
     #[serde(rename = "Location Setting")]
-    pub location_setting: String,
+    pub location_setting: LocationSetting,
     #[serde(rename = "Local Site Name")]
     pub site_name: String,
     #[serde(rename = "State Name")]
@@ -29,10 +142,26 @@ pub struct Station {
     pub county_name: String,
     #[serde(rename = "City Name")]
     pub city_name: String,
-    #[serde(skip)]
+    #[serde(rename = "CBSA Name", default)]
+    pub cbsa_name: Option<String>,
+    #[serde(rename = "Tribe Name", default)]
+    pub tribe_name: Option<String>,
+    //meteorological site whose data represents this site
+    #[serde(rename = "Met Site State Code", default)]
+    pub met_site_state_code: Option<String>,
+    #[serde(rename = "Met Site County Code", default)]
+    pub met_site_county_code: Option<String>,
+    #[serde(rename = "Met Site Site Number", default)]
+    pub met_site_number: Option<String>,
+    #[serde(rename = "Met Site Type", default)]
+    pub met_site_type: Option<String>,
+    #[serde(rename = "Met Site Distance", default)]
+    pub met_site_distance: Option<f64>, //meters
+    #[serde(rename = "Met Site Direction", default)]
+    pub met_site_direction: Option<String>,
+    //not a column in the csv, but kept in network snapshots
+    #[serde(default)]
     pub id: String,
-    #[serde(skip)]
-    pub avg_distance_to_neighbors: Option<f64>,
 }
 
 impl Station {
@@ -41,4 +170,38 @@ impl Station {
     pub fn generate_id(&mut self) {
         self.id = format!("{}-{}-{}", self.state_code, self.county_code, self.site_number);
     }
-}
\ No newline at end of file
+
+    //id of the meteorological site in the same state-county-site format, none if any part is missing
+    pub fn met_site_id(&self) -> Option<String> {
+        match (&self.met_site_state_code, &self.met_site_county_code, &self.met_site_number) {
+            (Some(state), Some(county), Some(site)) => Some(format!("{}-{}-{}", state, county, site)),
+            _ => None,
+        }
+    }
+
+    //convert nad27 coordinates to wgs84 in place so distances between sites are consistent
+    //outputs: true if the coordinates were converted, other datums are left unchanged
+    //nad27 sites outside the conus and alaska have no regional shift and stay nad27 with a warning
+    pub fn normalize_datum(&mut self) -> bool {
+        if self.datum != Some(Datum::Nad27) {
+            return false;
+        }
+        let Some((latitude, longitude)) = spatial::nad27_to_wgs84(self.latitude, self.longitude, self.elevation.unwrap_or(0.0)) else {
+            eprintln!("Warning: no NAD27 datum shift for station {} at ({}, {}), coordinates left unconverted",
+                      self.id, self.latitude, self.longitude);
+            return false;
+        };
+        self.latitude = latitude;
+        self.longitude = longitude;
+        self.datum = Some(Datum::Wgs84);
+        true
+    }
+
+    //check whether the station was operating on a given date
+    //a missing established date is treated as always open before, a missing closed date as still open
+    pub fn is_active_on(&self, date: NaiveDate) -> bool {
+        let opened = self.date_established.is_none_or(|established| established <= date);
+        let not_closed = self.date_closed.is_none_or(|closed| closed > date);
+        opened && not_closed
+    }
+}
@@ -0,0 +1,419 @@
+//module for persisting the monitoring network and analysis results in a local sqlite database
+//only built with the `sqlite` cargo feature
+//
+//schema (created by open_database if missing):
+//  stations(id TEXT PRIMARY KEY, state_code, county_code, site_number, latitude REAL, longitude REAL,
+//           date_established TEXT, date_closed TEXT, land_use, location_setting, site_name,
+//           state_name, county_name, city_name, datum, elevation REAL, cbsa_name, tribe_name,
+//           met_site_state_code, met_site_county_code, met_site_number, met_site_type,
+//           met_site_distance REAL, met_site_direction)
+//      one row per station
+//  edges(station_id, neighbor_id, distance_km REAL, PRIMARY KEY(station_id, neighbor_id))
+//      the adjacency list, one row per directed edge
+//  monitors(year INTEGER, site_id, parameter_code, parameter_name, poc INTEGER, method_code, method_name,
+//           sample_duration, pollutant_standard, event_type, observation_count INTEGER,
+//           observation_percent REAL, complete INTEGER, arithmetic_mean REAL)
+//      raw monitor records from each year's annual concentration file
+//  pollution(year INTEGER, parameter_code, station_id, value REAL, PRIMARY KEY(year, parameter_code, station_id))
+//      aggregated site values, as produced by site_values or read_pollution
+//  trends(station_id TEXT PRIMARY KEY, n INTEGER, first_year INTEGER, last_year INTEGER, s INTEGER,
+//         variance REAL, z REAL, p_value REAL, sens_slope REAL)
+//      mann-kendall results from calculate_trends
+//  metrics(name TEXT PRIMARY KEY, algorithm, parameters)
+//      provenance of each metric in the network's metrics table, parameters as a json object
+//  metric_values(metric, station_id, number REAL, category TEXT, flag INTEGER, PRIMARY KEY(metric, station_id))
+//      per-station metric values (isolation and its variants, ...), exactly one of the value columns is set
+//dates are stored as YYYY-MM-DD text, so results can be queried directly with sql
+
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::Path;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
+use crate::metrics::{MetricValue, Provenance};
+use crate::monitor::{EventType, Monitor};
+use crate::network::MonitoringNetwork;
+use crate::station::{Datum, Station};
+use crate::trend::TrendResult;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS stations (
+    id TEXT PRIMARY KEY,
+    state_code TEXT NOT NULL,
+    county_code TEXT NOT NULL,
+    site_number TEXT NOT NULL,
+    latitude REAL NOT NULL,
+    longitude REAL NOT NULL,
+    date_established TEXT,
+    date_closed TEXT,
+    land_use TEXT NOT NULL,
+    location_setting TEXT NOT NULL,
+    site_name TEXT NOT NULL,
+    state_name TEXT NOT NULL,
+    county_name TEXT NOT NULL,
+    city_name TEXT NOT NULL,
+    datum TEXT,
+    elevation REAL,
+    cbsa_name TEXT,
+    tribe_name TEXT,
+    met_site_state_code TEXT,
+    met_site_county_code TEXT,
+    met_site_number TEXT,
+    met_site_type TEXT,
+    met_site_distance REAL,
+    met_site_direction TEXT
+);
+CREATE TABLE IF NOT EXISTS edges (
+    station_id TEXT NOT NULL,
+    neighbor_id TEXT NOT NULL,
+    distance_km REAL NOT NULL,
+    PRIMARY KEY (station_id, neighbor_id)
+);
+CREATE TABLE IF NOT EXISTS monitors (
+    year INTEGER NOT NULL,
+    site_id TEXT NOT NULL,
+    parameter_code TEXT NOT NULL,
+    parameter_name TEXT NOT NULL,
+    poc INTEGER NOT NULL,
+    method_code TEXT,
+    method_name TEXT,
+    sample_duration TEXT NOT NULL,
+    pollutant_standard TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    observation_count INTEGER,
+    observation_percent REAL,
+    complete INTEGER,
+    arithmetic_mean REAL
+);
+CREATE INDEX IF NOT EXISTS monitors_year_site ON monitors (year, site_id);
+CREATE TABLE IF NOT EXISTS pollution (
+    year INTEGER NOT NULL,
+    parameter_code TEXT NOT NULL,
+    station_id TEXT NOT NULL,
+    value REAL NOT NULL,
+    PRIMARY KEY (year, parameter_code, station_id)
+);
+CREATE TABLE IF NOT EXISTS trends (
+    station_id TEXT PRIMARY KEY,
+    n INTEGER NOT NULL,
+    first_year INTEGER NOT NULL,
+    last_year INTEGER NOT NULL,
+    s INTEGER NOT NULL,
+    variance REAL NOT NULL,
+    z REAL NOT NULL,
+    p_value REAL NOT NULL,
+    sens_slope REAL NOT NULL
+);
+CREATE TABLE IF NOT EXISTS metrics (
+    name TEXT PRIMARY KEY,
+    algorithm TEXT NOT NULL,
+    parameters TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS metric_values (
+    metric TEXT NOT NULL,
+    station_id TEXT NOT NULL,
+    number REAL,
+    category TEXT,
+    flag INTEGER,
+    PRIMARY KEY (metric, station_id)
+);
+";
+
+//column lists shared by the inserts and selects, so the row indices below follow this order
+const STATION_COLUMNS: &str = "id, state_code, county_code, site_number, latitude, longitude, date_established, \
+    date_closed, land_use, location_setting, site_name, state_name, county_name, city_name, datum, elevation, \
+    cbsa_name, tribe_name, met_site_state_code, met_site_county_code, met_site_number, met_site_type, \
+    met_site_distance, met_site_direction";
+const TREND_COLUMNS: &str = "station_id, n, first_year, last_year, s, variance, z, p_value, sens_slope";
+const MONITOR_COLUMNS: &str = "year, site_id, parameter_code, parameter_name, poc, method_code, method_name, \
+    sample_duration, pollutant_standard, event_type, observation_count, observation_percent, complete, arithmetic_mean";
+
+//numbered placeholders ?1 to ?n for an insert statement
+fn placeholders(n: usize) -> String {
+    (1..=n).map(|i| format!("?{}", i)).collect::<Vec<String>>().join(", ")
+}
+
+//function to open (or create) a database file and make sure the schema exists
+pub fn open_database<P: AsRef<Path>>(path: P) -> Result<Connection, Box<dyn Error>> {
+    let conn = Connection::open(path)?;
+    create_schema(&conn)?;
+    Ok(conn)
+}
+
+//create any missing tables, safe to call on an existing database
+pub fn create_schema(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute_batch(SCHEMA)?;
+    Ok(())
+}
+
+//function to write stations, adjacency edges, trends and metrics, replacing what was stored before
+//monitors and pollution values are kept per year and written with save_monitors and save_pollution
+pub fn save_network(conn: &mut Connection, network: &MonitoringNetwork) -> Result<(), Box<dyn Error>> {
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM stations", [])?;
+    tx.execute("DELETE FROM edges", [])?;
+    tx.execute("DELETE FROM trends", [])?;
+    tx.execute("DELETE FROM metrics", [])?;
+    tx.execute("DELETE FROM metric_values", [])?;
+    {
+        let mut insert_station = tx.prepare(
+            &format!("INSERT INTO stations ({}) VALUES ({})", STATION_COLUMNS, placeholders(24)))?;
+        for station in network.stations.values() {
+            insert_station.execute(params![
+                station.id,
+                station.state_code,
+                station.county_code,
+                station.site_number,
+                station.latitude,
+                station.longitude,
+                station.date_established.map(|d| d.to_string()),
+                station.date_closed.map(|d| d.to_string()),
+                station.land_use.as_str(),
+                station.location_setting.as_str(),
+                station.site_name,
+                station.state_name,
+                station.county_name,
+                station.city_name,
+                station.datum.as_ref().map(|datum| datum.as_str()),
+                station.elevation,
+                station.cbsa_name,
+                station.tribe_name,
+                station.met_site_state_code,
+                station.met_site_county_code,
+                station.met_site_number,
+                station.met_site_type,
+                station.met_site_distance,
+                station.met_site_direction,
+            ])?;
+        }
+
+        let mut insert_edge = tx.prepare("INSERT INTO edges (station_id, neighbor_id, distance_km) VALUES (?1, ?2, ?3)")?;
+        for (id, neighbors) in &network.adjacency_list {
+            for (neighbor, distance) in neighbors {
+                insert_edge.execute(params![id, neighbor, distance])?;
+            }
+        }
+
+        let mut insert_trend = tx.prepare(&format!("INSERT INTO trends ({}) VALUES ({})", TREND_COLUMNS, placeholders(9)))?;
+        for (id, trend) in &network.trends {
+            insert_trend.execute(params![
+                id, trend.n as i64, trend.first_year, trend.last_year, trend.s,
+                trend.variance, trend.z, trend.p_value, trend.sens_slope,
+            ])?;
+        }
+
+        let mut insert_metric = tx.prepare("INSERT INTO metrics (name, algorithm, parameters) VALUES (?1, ?2, ?3)")?;
+        let mut insert_value = tx.prepare(
+            "INSERT INTO metric_values (metric, station_id, number, category, flag) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+        for name in network.metrics.names() {
+            let Some(metric) = network.metrics.metric(name) else { continue };
+            insert_metric.execute(params![name, metric.provenance.algorithm,
+                                          serde_json::to_string(&metric.provenance.parameters)?])?;
+            for (id, value) in &metric.values {
+                let (number, category, flag) = match value {
+                    MetricValue::Number(number) => (Some(*number), None, None),
+                    MetricValue::Category(category) => (None, Some(category.as_str()), None),
+                    MetricValue::Flag(flag) => (None, None, Some(*flag)),
+                };
+                insert_value.execute(params![name, id, number, category, flag])?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+//function to store one year of monitor records, replacing that year if it was loaded before
+//this is the incremental update path when a new annual file arrives
+pub fn save_monitors(conn: &mut Connection, year: i32, monitors: &HashMap<String, Vec<Monitor>>)
+    -> Result<(), Box<dyn Error>> {
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM monitors WHERE year = ?1", params![year])?;
+    {
+        let mut insert = tx.prepare(&format!("INSERT INTO monitors ({}) VALUES ({})", MONITOR_COLUMNS, placeholders(14)))?;
+        for site_monitors in monitors.values() {
+            for monitor in site_monitors {
+                insert.execute(params![
+                    year,
+                    monitor.site_id,
+                    monitor.parameter_code,
+                    monitor.parameter_name,
+                    monitor.poc,
+                    monitor.method_code,
+                    monitor.method_name,
+                    monitor.sample_duration,
+                    monitor.pollutant_standard,
+                    monitor.event_type.to_string(),
+                    monitor.observation_count,
+                    monitor.observation_percent,
+                    monitor.complete,
+                    monitor.arithmetic_mean,
+                ])?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+//function to store aggregated site values for one year and parameter, replacing earlier values
+pub fn save_pollution(conn: &mut Connection, year: i32, parameter_code: &str,
+                      pollution_data: &HashMap<String, f64>) -> Result<(), Box<dyn Error>> {
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM pollution WHERE year = ?1 AND parameter_code = ?2", params![year, parameter_code])?;
+    {
+        let mut insert = tx.prepare("INSERT INTO pollution (year, parameter_code, station_id, value) VALUES (?1, ?2, ?3, ?4)")?;
+        for (id, value) in pollution_data {
+            insert.execute(params![year, parameter_code, id, value])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+//parse an optional YYYY-MM-DD column
+fn parse_date(text: Option<String>) -> Option<NaiveDate> {
+    text.and_then(|t| NaiveDate::parse_from_str(&t, "%Y-%m-%d").ok())
+}
+
+//function to load a network back from the database
+//inputs: the connection and optionally a year of monitor records to attach
+//outputs: network with stations, adjacency list (sorted by distance), trends, metrics and monitors
+pub fn load_network(conn: &Connection, monitor_year: Option<i32>) -> Result<MonitoringNetwork, Box<dyn Error>> {
+    let mut network = MonitoringNetwork::new();
+
+    let mut query = conn.prepare(&format!("SELECT {} FROM stations", STATION_COLUMNS))?;
+    let stations = query.query_map([], |row| {
+        Ok(Station {
+            id: row.get(0)?,
+            state_code: row.get(1)?,
+            county_code: row.get(2)?,
+            site_number: row.get(3)?,
+            latitude: row.get(4)?,
+            longitude: row.get(5)?,
+            date_established: parse_date(row.get(6)?),
+            date_closed: parse_date(row.get(7)?),
+            land_use: row.get::<_, String>(8)?.into(),
+            location_setting: row.get::<_, String>(9)?.into(),
+            site_name: row.get(10)?,
+            state_name: row.get(11)?,
+            county_name: row.get(12)?,
+            city_name: row.get(13)?,
+            datum: row.get::<_, Option<String>>(14)?.map(Datum::from),
+            elevation: row.get(15)?,
+            cbsa_name: row.get(16)?,
+            tribe_name: row.get(17)?,
+            met_site_state_code: row.get(18)?,
+            met_site_county_code: row.get(19)?,
+            met_site_number: row.get(20)?,
+            met_site_type: row.get(21)?,
+            met_site_distance: row.get(22)?,
+            met_site_direction: row.get(23)?,
+        })
+    })?;
+    for station in stations {
+        network.add_station(station?);
+    }
+
+    let mut query = conn.prepare("SELECT station_id, neighbor_id, distance_km FROM edges ORDER BY station_id, distance_km")?;
+    let edges = query.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?)))?;
+    for edge in edges {
+        let (id, neighbor, distance) = edge?;
+        network.adjacency_list.entry(id).or_default().push((neighbor, distance));
+    }
+
+    let mut query = conn.prepare(&format!("SELECT {} FROM trends", TREND_COLUMNS))?;
+    let trends = query.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, TrendResult {
+            n: row.get::<_, i64>(1)? as usize,
+            first_year: row.get(2)?,
+            last_year: row.get(3)?,
+            s: row.get(4)?,
+            variance: row.get(5)?,
+            z: row.get(6)?,
+            p_value: row.get(7)?,
+            sens_slope: row.get(8)?,
+        }))
+    })?;
+    for trend in trends {
+        let (id, result) = trend?;
+        network.trends.insert(id, result);
+    }
+
+    let mut values: HashMap<String, HashMap<String, MetricValue>> = HashMap::new();
+    let mut query = conn.prepare("SELECT metric, station_id, number, category, flag FROM metric_values")?;
+    let rows = query.query_map([], |row| {
+        let value = match (row.get::<_, Option<f64>>(2)?, row.get::<_, Option<String>>(3)?, row.get::<_, Option<bool>>(4)?) {
+            (Some(number), _, _) => Some(MetricValue::Number(number)),
+            (_, Some(category), _) => Some(MetricValue::Category(category)),
+            (_, _, Some(flag)) => Some(MetricValue::Flag(flag)),
+            _ => None,
+        };
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, value))
+    })?;
+    for row in rows {
+        if let (name, id, Some(value)) = row? {
+            values.entry(name).or_default().insert(id, value);
+        }
+    }
+    let mut query = conn.prepare("SELECT name, algorithm, parameters FROM metrics")?;
+    let metrics = query.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?;
+    for metric in metrics {
+        let (name, algorithm, parameters) = metric?;
+        let provenance = Provenance { algorithm, parameters: serde_json::from_str(&parameters)? };
+        let metric_values = values.remove(&name).unwrap_or_default();
+        network.metrics.insert(&name, provenance, metric_values);
+    }
+
+    if let Some(year) = monitor_year {
+        network.attach_monitors(load_monitors(conn, year)?);
+    }
+    Ok(network)
+}
+
+//function to load one year of monitor records grouped by site
+pub fn load_monitors(conn: &Connection, year: i32) -> Result<HashMap<String, Vec<Monitor>>, Box<dyn Error>> {
+    let mut query = conn.prepare(
+        "SELECT site_id, parameter_code, parameter_name, poc, method_code, method_name, sample_duration,
+                pollutant_standard, event_type, observation_count, observation_percent, complete, arithmetic_mean
+         FROM monitors WHERE year = ?1")?;
+    let rows = query.query_map(params![year], |row| {
+        Ok(Monitor {
+            site_id: row.get(0)?,
+            parameter_code: row.get(1)?,
+            parameter_name: row.get(2)?,
+            poc: row.get(3)?,
+            method_code: row.get(4)?,
+            method_name: row.get(5)?,
+            sample_duration: row.get(6)?,
+            pollutant_standard: row.get(7)?,
+            event_type: EventType::from_aqs(&row.get::<_, String>(8)?),
+            observation_count: row.get(9)?,
+            observation_percent: row.get(10)?,
+            complete: row.get(11)?,
+            arithmetic_mean: row.get(12)?,
+        })
+    })?;
+    let mut sites: HashMap<String, Vec<Monitor>> = HashMap::new();
+    for row in rows {
+        let monitor = row?;
+        sites.entry(monitor.site_id.clone()).or_default().push(monitor);
+    }
+    Ok(sites)
+}
+
+//function to load stored site values for a parameter across every year, ready for calculate_trends
+pub fn load_pollution_years(conn: &Connection, parameter_code: &str)
+    -> Result<BTreeMap<i32, HashMap<String, f64>>, Box<dyn Error>> {
+    let mut query = conn.prepare("SELECT year, station_id, value FROM pollution WHERE parameter_code = ?1")?;
+    let rows = query.query_map(params![parameter_code], |row| {
+        Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?))
+    })?;
+    let mut yearly: BTreeMap<i32, HashMap<String, f64>> = BTreeMap::new();
+    for row in rows {
+        let (year, id, value) = row?;
+        yearly.entry(year).or_default().insert(id, value);
+    }
+    Ok(yearly)
+}
@@ -0,0 +1,146 @@
+//module for terrain-aware distances from a digital elevation model
+//reads esri ascii grids in geographic coordinates (degrees); a geotiff dem can be converted with
+//`gdal_translate -of AAIGrid dem.tif dem.asc`
+//stations separated by ridges often sit in different airsheds, so each ridge crossed lengthens the edge
+
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use crate::spatial;
+
+//elevation raster, rows stored from north to south as in the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElevationGrid {
+    pub ncols: usize,
+    pub nrows: usize,
+    pub xllcorner: f64, //longitude of the western edge
+    pub yllcorner: f64, //latitude of the southern edge
+    pub cellsize: f64, //degrees
+    pub values: Vec<Option<f64>>, //meters, none for nodata cells
+}
+
+impl ElevationGrid {
+    //function to look up the elevation of the cell containing a point
+    //outputs: none outside the grid or in a nodata cell
+    pub fn elevation(&self, lat: f64, lon: f64) -> Option<f64> {
+        let col = ((lon - self.xllcorner) / self.cellsize).floor();
+        let row_from_south = ((lat - self.yllcorner) / self.cellsize).floor();
+        if col < 0.0 || row_from_south < 0.0 || col >= self.ncols as f64 || row_from_south >= self.nrows as f64 {
+            return None;
+        }
+        let row = self.nrows - 1 - row_from_south as usize;
+        self.values[row * self.ncols + col as usize]
+    }
+
+    //function to sample elevations along the great-circle path between two points
+    //inputs: the two points and the spacing of samples in km
+    //outputs: elevations in order from the first point, samples outside the grid or in nodata cells are skipped,
+    //         an error if the spacing is not a positive number
+    pub fn profile(&self, lat1: f64, lon1: f64, lat2: f64, lon2: f64, step_km: f64) -> Result<Vec<f64>, Box<dyn Error>> {
+        if !(step_km > 0.0 && step_km.is_finite()) {
+            return Err(format!("profile step must be a positive number of km, got {}", step_km).into());
+        }
+        let distance = spatial::haversine_km(lat1, lon1, lat2, lon2);
+        let steps = ((distance / step_km).ceil() as usize).max(1);
+        Ok((0..=steps)
+            .filter_map(|i| {
+                let (lat, lon) = spatial::intermediate_point(lat1, lon1, lat2, lon2, i as f64 / steps as f64);
+                self.elevation(lat, lon)
+            })
+            .collect())
+    }
+}
+
+//function to read an esri ascii grid
+//inputs: path to a .asc file with ncols, nrows, xllcorner/xllcenter, yllcorner/yllcenter, cellsize and an
+//        optional nodata_value header, followed by nrows lines of ncols elevations from north to south
+pub fn read_ascii_grid<P: AsRef<Path>>(path: P) -> Result<ElevationGrid, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let mut tokens = text.split_whitespace().peekable();
+    let (mut ncols, mut nrows, mut cellsize, mut nodata) = (None, None, None, None);
+    let (mut x, mut y, mut centered) = (None, None, false);
+
+    //header lines are "key value" until the first numeric token
+    while let Some(key) = tokens.next_if(|token| token.parse::<f64>().is_err()) {
+        let value: f64 = tokens.next()
+            .ok_or_else(|| format!("missing value for {}", key))?
+            .parse()
+            .map_err(|_| format!("invalid value for {}", key))?;
+        match key.to_ascii_lowercase().as_str() {
+            "ncols" => ncols = Some(value as usize),
+            "nrows" => nrows = Some(value as usize),
+            "xllcorner" => x = Some(value),
+            "yllcorner" => y = Some(value),
+            "xllcenter" => { x = Some(value); centered = true; },
+            "yllcenter" => { y = Some(value); centered = true; },
+            "cellsize" => cellsize = Some(value),
+            "nodata_value" => nodata = Some(value),
+            _ => return Err(format!("unknown ascii grid header {}", key).into()),
+        }
+    }
+    let (Some(ncols), Some(nrows), Some(x), Some(y), Some(cellsize)) = (ncols, nrows, x, y, cellsize) else {
+        return Err("ascii grid header needs ncols, nrows, xllcorner, yllcorner and cellsize".into());
+    };
+    let offset = if centered { cellsize / 2.0 } else { 0.0 };
+
+    let values = tokens
+        .map(|token| {
+            let value: f64 = token.parse().map_err(|_| format!("invalid elevation {}", token))?;
+            Ok(if Some(value) == nodata { None } else { Some(value) })
+        })
+        .collect::<Result<Vec<Option<f64>>, Box<dyn Error>>>()?;
+    if values.len() != ncols * nrows {
+        return Err(format!("ascii grid has {} values, expected {} x {}", values.len(), nrows, ncols).into());
+    }
+    Ok(ElevationGrid { ncols, nrows, xllcorner: x - offset, yllcorner: y - offset, cellsize, values })
+}
+
+//function to count ridges along an elevation profile
+//a ridge is a climb of at least min_prominence_m from the lowest point since the last ridge, followed by a
+//descent of at least min_prominence_m from the top, so a path that only climbs onto a plateau crosses none
+pub fn ridge_crossings(profile: &[f64], min_prominence_m: f64) -> usize {
+    let Some(&first) = profile.first() else { return 0 };
+    let mut crossings = 0;
+    let mut low = first;
+    let mut high: Option<f64> = None; //top of the current climb once it is high enough to be a ridge
+    for &elevation in &profile[1..] {
+        match high {
+            Some(top) if elevation <= top - min_prominence_m => {
+                crossings += 1;
+                high = None;
+                low = elevation;
+            },
+            Some(top) => high = Some(top.max(elevation)),
+            None if elevation >= low + min_prominence_m => high = Some(elevation),
+            None => low = low.min(elevation),
+        }
+    }
+    crossings
+}
+
+//how ridge crossings lengthen an edge
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainPenalty {
+    pub step_km: f64, //spacing of elevation samples along each edge
+    pub min_prominence_m: f64, //smallest rise and fall that counts as a ridge
+    pub penalty_per_ridge: f64, //each ridge adds this multiple of the straight-line distance
+}
+
+impl Default for TerrainPenalty {
+    fn default() -> Self {
+        TerrainPenalty { step_km: 1.0, min_prominence_m: 300.0, penalty_per_ridge: 1.0 }
+    }
+}
+
+impl TerrainPenalty {
+    //function to count the ridges crossed by the great-circle path between two points
+    pub fn ridges(&self, grid: &ElevationGrid, lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> Result<usize, Box<dyn Error>> {
+        Ok(ridge_crossings(&grid.profile(lat1, lon1, lat2, lon2, self.step_km)?, self.min_prominence_m))
+    }
+
+    //factor applied to an edge distance for a number of ridges crossed, 1 with none
+    pub fn factor(&self, ridges: usize) -> f64 {
+        1.0 + self.penalty_per_ridge * ridges as f64
+    }
+}
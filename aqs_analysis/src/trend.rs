@@ -0,0 +1,145 @@
+//module for detecting temporal trends in pollution levels across several years of data
+
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+//significance level used when a caller does not pick one
+pub const DEFAULT_ALPHA: f64 = 0.05;
+
+//result of a mann-kendall trend test combined with sen's slope for one station
+//slope is in pollution units per year (e.g. ug/m3 per year for pm2.5)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrendResult {
+    pub n: usize,
+    pub first_year: i32,
+    pub last_year: i32,
+    pub s: i64,
+    pub variance: f64,
+    pub z: f64,
+    pub p_value: f64,
+    pub sens_slope: f64,
+}
+
+impl TrendResult {
+    //true if the trend is significant at the given level
+    pub fn is_significant(&self, alpha: f64) -> bool {
+        self.p_value < alpha
+    }
+
+    //true if levels are significantly rising (getting worse)
+    pub fn is_increasing(&self, alpha: f64) -> bool {
+        self.is_significant(alpha) && self.s > 0
+    }
+
+    //true if levels are significantly falling (getting better)
+    pub fn is_decreasing(&self, alpha: f64) -> bool {
+        self.is_significant(alpha) && self.s < 0
+    }
+}
+
+//function to run the mann-kendall test and sen's slope on a yearly series
+//inputs: (year, value) pairs, in any order, several values for the same year are averaged
+//outputs: none if there are fewer than 3 distinct years, otherwise the trend result
+//uses the normal approximation with a correction for tied values
+pub fn mann_kendall(series: &[(i32, f64)]) -> Option<TrendResult> {
+    //year = (sum, count)
+    let mut years: BTreeMap<i32, (f64, usize)> = BTreeMap::new();
+    for (year, value) in series {
+        let entry = years.entry(*year).or_insert((0.0, 0));
+        entry.0 += value;
+        entry.1 += 1;
+    }
+    let points: Vec<(i32, f64)> = years.into_iter().map(|(year, (sum, count))| (year, sum / count as f64)).collect();
+
+    let n = points.len();
+    if n < 3 {
+        return None;
+    }
+
+    //s statistic: count of increasing pairs minus count of decreasing pairs
+    let mut s: i64 = 0;
+    let mut slopes: Vec<f64> = Vec::with_capacity(n * (n - 1) / 2);
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let diff = points[j].1 - points[i].1;
+            if diff > 0.0 {
+                s += 1;
+            } else if diff < 0.0 {
+                s -= 1;
+            }
+            slopes.push(diff / (points[j].0 - points[i].0) as f64);
+        }
+    }
+
+    //variance of s, adjusted for groups of tied values
+    let mut values: Vec<f64> = points.iter().map(|(_, v)| *v).collect();
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < values.len() {
+        let mut j = i;
+        while j + 1 < values.len() && values[j + 1] == values[i] {
+            j += 1;
+        }
+        let t = (j - i + 1) as f64;
+        tie_term += t * (t - 1.0) * (2.0 * t + 5.0);
+        i = j + 1;
+    }
+    let nf = n as f64;
+    let variance = (nf * (nf - 1.0) * (2.0 * nf + 5.0) - tie_term) / 18.0;
+
+    //continuity-corrected z score
+    let z = if variance <= 0.0 {
+        0.0
+    } else if s > 0 {
+        (s as f64 - 1.0) / variance.sqrt()
+    } else if s < 0 {
+        (s as f64 + 1.0) / variance.sqrt()
+    } else {
+        0.0
+    };
+    let p_value = 2.0 * (1.0 - standard_normal_cdf(z.abs()));
+
+    Some(TrendResult {
+        n,
+        first_year: points[0].0,
+        last_year: points[n - 1].0,
+        s,
+        variance,
+        z,
+        p_value: p_value.clamp(0.0, 1.0),
+        sens_slope: median(&mut slopes).unwrap_or(0.0),
+    })
+}
+
+//cumulative distribution function of the standard normal distribution
+fn standard_normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+//error function approximation (abramowitz and stegun 7.1.26, max error 1.5e-7)
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741
+        + t * (-1.453152027 + t * 1.061405429))));
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+//median of a list of values, sorts the input in place
+//outputs: none if there are no values
+pub fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[mid - 1] + values[mid]) / 2.0)
+    } else {
+        Some(values[mid])
+    }
+}
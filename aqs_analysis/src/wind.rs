@@ -0,0 +1,179 @@
+//module for wind-aware neighbor weighting
+//a wind rose gives the share of time the wind blows from each direction, at a station or at a grid point
+//a neighbor lying upwind along frequent winds samples the same air masses, so its edge counts as shorter
+
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use serde::Deserialize;
+use crate::spatial;
+use crate::station::Station;
+
+//(direction, frequency) pairs as read, before normalizing
+type Sectors = Vec<(f64, f64)>;
+
+//distribution of wind directions at one location
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindRose {
+    sectors: Vec<(f64, f64)>, //(direction the wind blows from in degrees, share of time), shares sum to 1
+}
+
+impl WindRose {
+    //function to build a rose from direction and frequency pairs, frequencies are normalized
+    //outputs: none if the frequencies do not add up to more than zero
+    pub fn new(sectors: Sectors) -> Option<Self> {
+        let total: f64 = sectors.iter().map(|(_, frequency)| frequency.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        Some(WindRose {
+            sectors: sectors.into_iter()
+                .filter(|(_, frequency)| *frequency > 0.0)
+                .map(|(direction, frequency)| (direction.rem_euclid(360.0), frequency / total))
+                .collect(),
+        })
+    }
+
+    //a rose where the wind always blows from one direction
+    pub fn prevailing(direction: f64) -> Self {
+        WindRose { sectors: vec![(direction.rem_euclid(360.0), 1.0)] }
+    }
+
+    pub fn sectors(&self) -> &[(f64, f64)] {
+        &self.sectors
+    }
+
+    //function to split the rose into its along-wind components for a bearing
+    //inputs: bearing from the station to a neighbor (degrees clockwise from north)
+    //outputs: (upwind, downwind) shares in 0-1, weighted by the cosine between the bearing and each wind
+    //         direction; a neighbor straight upwind of a constant wind gives (1, 0), crosswind gives (0, 0)
+    pub fn alignment(&self, bearing: f64) -> (f64, f64) {
+        let mut upwind = 0.0;
+        let mut downwind = 0.0;
+        for (direction, frequency) in &self.sectors {
+            let cosine = (bearing - direction).to_radians().cos();
+            if cosine > 0.0 {
+                upwind += frequency * cosine;
+            } else {
+                downwind -= frequency * cosine;
+            }
+        }
+        (upwind, downwind)
+    }
+}
+
+//how strongly wind alignment shortens an edge
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindWeighting {
+    pub upwind_gain: f64, //an upwind neighbor under a constant wind counts as 1 + gain times closer
+    pub downwind_gain: f64, //same for a downwind neighbor, usually weaker
+    pub max_grid_distance_km: f64, //stations without their own rose use the nearest grid rose within this distance
+}
+
+impl Default for WindWeighting {
+    fn default() -> Self {
+        WindWeighting { upwind_gain: 1.0, downwind_gain: 0.5, max_grid_distance_km: 150.0 }
+    }
+}
+
+impl WindWeighting {
+    //function to calculate the factor applied to an edge distance
+    //outputs: a factor in (0, 1], 1 for crosswind neighbors or calm roses
+    pub fn factor(&self, rose: &WindRose, bearing: f64) -> f64 {
+        let (upwind, downwind) = rose.alignment(bearing);
+        1.0 / (1.0 + self.upwind_gain * upwind + self.downwind_gain * downwind)
+    }
+}
+
+//wind roses for individual stations and for points of a regular or irregular grid
+#[derive(Debug, Clone, Default)]
+pub struct WindRoses {
+    pub stations: HashMap<String, WindRose>, //station_id = rose measured at or assigned to that station
+    pub grid: Vec<(f64, f64, WindRose)>, //(latitude, longitude, rose)
+}
+
+impl WindRoses {
+    //function to find the rose that applies to a station
+    //outputs: the station's own rose, otherwise the nearest grid rose within the distance, otherwise none
+    pub fn rose_for(&self, station: &Station, max_grid_distance_km: f64) -> Option<&WindRose> {
+        if let Some(rose) = self.stations.get(&station.id) {
+            return Some(rose);
+        }
+        self.grid.iter()
+            .map(|(lat, lon, rose)| (spatial::haversine_km(station.latitude, station.longitude, *lat, *lon), rose))
+            .filter(|(distance, _)| *distance <= max_grid_distance_km)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, rose)| rose)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty() && self.grid.is_empty()
+    }
+}
+
+//one direction of one rose
+#[derive(Debug, Deserialize)]
+struct WindRoseRecord {
+    #[serde(rename = "Site ID", default)]
+    site_id: Option<String>,
+    #[serde(rename = "Latitude", default)]
+    latitude: Option<f64>,
+    #[serde(rename = "Longitude", default)]
+    longitude: Option<f64>,
+    #[serde(rename = "Direction")]
+    direction: f64,
+    #[serde(rename = "Frequency")]
+    frequency: f64,
+}
+
+//function to read wind roses from csv using serde
+//inputs: path to a csv with one row per direction: "Direction" (degrees the wind blows from) and "Frequency"
+//        (any unit, normalized per rose), plus either "Site ID" (state-county-site) for a station rose or
+//        "Latitude" and "Longitude" for a grid point; a single row per location gives a prevailing wind
+//outputs: station and grid roses, rows with neither a site id nor coordinates are skipped
+pub fn read_wind_roses<P: AsRef<Path>>(path: P) -> Result<WindRoses, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut csv_reader = csv::ReaderBuilder::new().has_headers(true).from_reader(reader);
+    let mut station_sectors: HashMap<String, Sectors> = HashMap::new();
+    //grid points in file order, keyed by the coordinate bits so repeated rows join the same point
+    let mut grid_sectors: Vec<(f64, f64, Sectors)> = Vec::new();
+    let mut grid_index: HashMap<(u64, u64), usize> = HashMap::new();
+
+    for result in csv_reader.deserialize::<WindRoseRecord>() {
+        match result {
+            Ok(record) => {
+                let sector = (record.direction, record.frequency);
+                match (record.site_id, record.latitude, record.longitude) {
+                    (Some(site_id), _, _) => station_sectors.entry(site_id).or_default().push(sector),
+                    (None, Some(lat), Some(lon)) => {
+                        let index = *grid_index.entry((lat.to_bits(), lon.to_bits())).or_insert_with(|| {
+                            grid_sectors.push((lat, lon, Vec::new()));
+                            grid_sectors.len() - 1
+                        });
+                        grid_sectors[index].2.push(sector);
+                    },
+                    _ => eprintln!("Skipping wind rose row without a site id or coordinates"),
+                }
+            },
+            Err(err) => {
+                //log error but continue anyway
+                eprintln!("Error deserializing wind rose record {}", err);
+            }
+        }
+    }
+
+    let roses = WindRoses {
+        stations: station_sectors.into_iter()
+            .filter_map(|(id, sectors)| WindRose::new(sectors).map(|rose| (id, rose)))
+            .collect(),
+        grid: grid_sectors.into_iter()
+            .filter_map(|(lat, lon, sectors)| WindRose::new(sectors).map(|rose| (lat, lon, rose)))
+            .collect(),
+    };
+    println!("Loaded {} station and {} grid wind roses", roses.stations.len(), roses.grid.len());
+    Ok(roses)
+}
@@ -0,0 +1,270 @@
+//module for partitioning the network into air basins or other regulatory zones
+//zones are polygons read from geojson or an esri shapefile in longitude/latitude (reproject other
+//coordinate systems first, e.g. `ogr2ogr -t_srs EPSG:4326 basins.shp source.shp`)
+//each station is assigned to the zone containing it, and isolation and gaps can be analyzed per zone
+
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use serde_json::Value;
+use crate::aggregate::{self, GroupStatistics};
+use crate::network::MonitoringNetwork;
+
+//station_id = zone name
+pub type ZoneAssignment = HashMap<String, String>;
+
+//a ring of (longitude, latitude) vertices
+type Ring = Vec<(f64, f64)>;
+
+//one zone made of one or more polygons
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zone {
+    pub name: String,
+    pub rings: Vec<Ring>, //outer rings and holes of every polygon, combined with the even-odd rule
+    bounds: (f64, f64, f64, f64), //(min lon, min lat, max lon, max lat)
+}
+
+impl Zone {
+    pub fn new(name: String, rings: Vec<Ring>) -> Self {
+        let mut bounds = (f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for &(lon, lat) in rings.iter().flatten() {
+            bounds = (bounds.0.min(lon), bounds.1.min(lat), bounds.2.max(lon), bounds.3.max(lat));
+        }
+        Zone { name, rings, bounds }
+    }
+
+    //function to check whether a point is inside the zone by ray casting
+    //a point inside a hole crosses its outer ring and the hole, so it counts as outside
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        let (min_lon, min_lat, max_lon, max_lat) = self.bounds;
+        if lon < min_lon || lon > max_lon || lat < min_lat || lat > max_lat {
+            return false;
+        }
+        let mut inside = false;
+        for ring in &self.rings {
+            for i in 0..ring.len() {
+                let (x1, y1) = ring[i];
+                let (x2, y2) = ring[(i + 1) % ring.len()];
+                if (y1 > lat) != (y2 > lat) && lon < x1 + (lat - y1) / (y2 - y1) * (x2 - x1) {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+}
+
+//function to read zones from a geojson feature collection of polygons and multipolygons
+//inputs: path to the geojson file and the property holding the zone name, features without the
+//        property are named by their position in the file
+pub fn read_zones_geojson<P: AsRef<Path>>(path: P, name_property: &str) -> Result<Vec<Zone>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let json: Value = serde_json::from_reader(BufReader::new(file))?;
+    let features = json["features"].as_array().ok_or("geojson has no features array")?;
+    let mut zones = Vec::new();
+
+    for (i, feature) in features.iter().enumerate() {
+        let geometry = &feature["geometry"];
+        let polygons: Vec<&Value> = match geometry["type"].as_str() {
+            Some("Polygon") => vec![&geometry["coordinates"]],
+            Some("MultiPolygon") => geometry["coordinates"].as_array().map(|p| p.iter().collect()).unwrap_or_default(),
+            _ => {
+                eprintln!("Skipping zone feature {} without polygon geometry", i);
+                continue;
+            }
+        };
+        let rings: Vec<Ring> = polygons.into_iter()
+            .filter_map(Value::as_array)
+            .flatten()
+            .filter_map(|ring| {
+                ring.as_array().map(|points| {
+                    points.iter().filter_map(|point| Some((point[0].as_f64()?, point[1].as_f64()?))).collect()
+                })
+            })
+            .collect();
+        zones.push(Zone::new(zone_name(feature["properties"][name_property].clone(), i), rings));
+    }
+    println!("Loaded {} zones", zones.len());
+    Ok(zones)
+}
+
+//zone name from a property value, numbers are converted to text
+fn zone_name(value: Value, index: usize) -> String {
+    match value {
+        Value::String(text) if !text.trim().is_empty() => text.trim().to_string(),
+        Value::Number(number) => number.to_string(),
+        _ => format!("zone {}", index + 1),
+    }
+}
+
+//function to read zones from an esri shapefile, names come from the .dbf next to the .shp
+//inputs: path to the .shp file and the dbf field holding the zone name
+//only polygon shapes (plain, z and m) are read, other records are skipped
+pub fn read_zones_shapefile<P: AsRef<Path>>(path: P, name_field: &str) -> Result<Vec<Zone>, Box<dyn Error>> {
+    let shp = fs::read(path.as_ref())?;
+    let names = read_dbf_column(path.as_ref().with_extension("dbf"), name_field).unwrap_or_else(|err| {
+        eprintln!("Could not read zone names from the dbf: {}", err);
+        Vec::new()
+    });
+    if shp.len() < 100 || be_i32(&shp, 0)? != 9994 {
+        return Err("not a shapefile".into());
+    }
+    let mut zones = Vec::new();
+    let mut offset = 100;
+    let mut index = 0;
+    while offset + 8 <= shp.len() {
+        let content_length = be_count(&shp, offset + 4)?.checked_mul(2).ok_or("shapefile record is too long")?; //16-bit words
+        let end = (offset + 8).checked_add(content_length).ok_or("shapefile record is too long")?;
+        let content = shp.get(offset + 8..end).ok_or("truncated shapefile record")?;
+        offset = end;
+        let name = names.get(index).cloned().map(Value::String).unwrap_or(Value::Null);
+        index += 1;
+
+        //polygon, polygonz and polygonm share the same layout up to the points
+        if !matches!(le_i32(content, 0)?, 5 | 15 | 25) {
+            continue;
+        }
+        let part_count = le_count(content, 36)?;
+        let point_count = le_count(content, 40)?;
+        //every offset is checked so corrupt counts give an error instead of overflowing
+        let points_start = part_count.checked_mul(4).and_then(|length| length.checked_add(44))
+            .ok_or("too many parts in shapefile record")?;
+        let points_end = point_count.checked_mul(16).and_then(|length| length.checked_add(points_start))
+            .ok_or("too many points in shapefile record")?;
+        if points_end > content.len() {
+            return Err("truncated shapefile record".into());
+        }
+        let mut starts = (0..part_count).map(|i| le_count(content, 44 + 4 * i))
+            .collect::<Result<Vec<usize>, Box<dyn Error>>>()?;
+        starts.push(point_count);
+        if starts.windows(2).any(|part| part[0] > part[1]) {
+            return Err("shapefile part offsets are out of order".into());
+        }
+        let mut rings = Vec::new();
+        for part in starts.windows(2) {
+            let ring = (part[0]..part[1])
+                .map(|i| Ok((le_f64(content, points_start + 16 * i)?, le_f64(content, points_start + 16 * i + 8)?)))
+                .collect::<Result<Ring, Box<dyn Error>>>()?;
+            rings.push(ring);
+        }
+        zones.push(Zone::new(zone_name(name, index - 1), rings));
+    }
+    println!("Loaded {} zones", zones.len());
+    Ok(zones)
+}
+
+//function to read one text column of a dbase (.dbf) table, one value per record
+fn read_dbf_column<P: AsRef<Path>>(path: P, field: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let dbf = fs::read(path)?;
+    if dbf.len() < 32 {
+        return Err("dbf header is truncated".into());
+    }
+    let record_count = u32::from_le_bytes([dbf[4], dbf[5], dbf[6], dbf[7]]) as usize;
+    let header_length = u16::from_le_bytes([dbf[8], dbf[9]]) as usize;
+    let record_length = u16::from_le_bytes([dbf[10], dbf[11]]) as usize;
+
+    //32-byte field descriptors until the 0x0d terminator, values start after the deletion flag
+    let mut column = None;
+    let mut position = 1;
+    let mut descriptor = 32;
+    while descriptor + 32 <= dbf.len() && dbf[descriptor] != 0x0d {
+        let name: String = dbf[descriptor..descriptor + 11].iter().take_while(|b| **b != 0).map(|b| *b as char).collect();
+        let length = dbf[descriptor + 16] as usize;
+        if name.eq_ignore_ascii_case(field) {
+            column = Some((position, length));
+        }
+        position += length;
+        descriptor += 32;
+    }
+    let (start, length) = column.ok_or_else(|| format!("dbf has no field {}", field))?;
+    (0..record_count)
+        .map(|i| {
+            let record = header_length + i * record_length;
+            let bytes = dbf.get(record + start..record + start + length).ok_or("truncated dbf record")?;
+            Ok(String::from_utf8_lossy(bytes).trim().to_string())
+        })
+        .collect()
+}
+
+fn be_i32(bytes: &[u8], at: usize) -> Result<i32, Box<dyn Error>> {
+    let slice = bytes.get(at..at + 4).ok_or("truncated shapefile")?;
+    Ok(i32::from_be_bytes(slice.try_into()?))
+}
+
+fn le_i32(bytes: &[u8], at: usize) -> Result<i32, Box<dyn Error>> {
+    let slice = bytes.get(at..at + 4).ok_or("truncated shapefile")?;
+    Ok(i32::from_le_bytes(slice.try_into()?))
+}
+
+//non-negative lengths and counts, an error for negative values
+fn be_count(bytes: &[u8], at: usize) -> Result<usize, Box<dyn Error>> {
+    usize::try_from(be_i32(bytes, at)?).map_err(|_| "negative length in shapefile".into())
+}
+
+fn le_count(bytes: &[u8], at: usize) -> Result<usize, Box<dyn Error>> {
+    usize::try_from(le_i32(bytes, at)?).map_err(|_| "negative count in shapefile".into())
+}
+
+fn le_f64(bytes: &[u8], at: usize) -> Result<f64, Box<dyn Error>> {
+    let slice = bytes.get(at..at + 8).ok_or("truncated shapefile")?;
+    Ok(f64::from_le_bytes(slice.try_into()?))
+}
+
+//function to read zones from geojson or a shapefile, chosen by the file extension
+pub fn read_zones<P: AsRef<Path>>(path: P, name_key: &str) -> Result<Vec<Zone>, Box<dyn Error>> {
+    match path.as_ref().extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.eq_ignore_ascii_case("shp") => read_zones_shapefile(path, name_key),
+        _ => read_zones_geojson(path, name_key),
+    }
+}
+
+//function to assign stations to zones by point-in-polygon
+//outputs: the zone of each station, stations outside every zone are left out; where zones overlap the
+//         first zone in the file wins
+pub fn assign_zones(network: &MonitoringNetwork, zones: &[Zone]) -> ZoneAssignment {
+    network.stations.values()
+        .filter_map(|station| {
+            zones.iter()
+                .find(|zone| zone.contains(station.latitude, station.longitude))
+                .map(|zone| (station.id.clone(), zone.name.clone()))
+        })
+        .collect()
+}
+
+//function to analyze each zone as its own network
+//inputs: the network (edges already restricted to zones, or not if crossing edges are wanted), pollution
+//        per station, the zone assignment and the number of neighbors used for isolation
+//outputs: one row per zone sorted by name, with isolation and gap thresholds taken within the zone
+pub fn analyze_zones(network: &MonitoringNetwork, pollution_data: &HashMap<String, f64>,
+                     zone_of: &ZoneAssignment, k: usize) -> Vec<GroupStatistics> {
+    let mut members: BTreeMap<&str, HashSet<String>> = BTreeMap::new();
+    for (id, zone) in zone_of {
+        members.entry(zone.as_str()).or_default().insert(id.clone());
+    }
+    members.into_iter()
+        .flat_map(|(zone, ids)| {
+            let mut zone_network = network.within(&ids);
+            zone_network.calculate_isolation(k);
+            //the pollution percentile for the gap threshold only counts the zone's own stations
+            let zone_pollution: HashMap<String, f64> = pollution_data.iter()
+                .filter(|(id, _)| ids.contains(*id))
+                .map(|(id, value)| (id.clone(), *value))
+                .collect();
+            aggregate::aggregate_by(&zone_network, &zone_pollution, |_| Some(zone.to_string()))
+        })
+        .collect()
+}
+
+//function to print per-zone statistics
+pub fn print_zone_statistics(rows: &[GroupStatistics]) {
+    println!("Statistics by zone:");
+    for row in rows {
+        let median = row.isolation_median.map(|value| format!("{:.2} km", value)).unwrap_or_else(|| "n/a".into());
+        let pollution = row.mean_pollution.map(|value| format!("{:.2}", value)).unwrap_or_else(|| "n/a".into());
+        println!("  {}: {} stations, median isolation {}, mean pollution {}, {} gaps",
+                 row.group, row.station_count, median, pollution, row.gap_count);
+    }
+}
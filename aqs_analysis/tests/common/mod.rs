@@ -0,0 +1,24 @@
+//fixtures shared by the integration tests, included with `mod common;`
+
+
+use aqs_analysis::station::{LandUse, LocationSetting, Station};
+
+//a residential, urban station at the given coordinates, the site number is the last part of the id
+//tests change other fields with struct update syntax, e.g. Station { state_code: "06".to_string(), ..station(id, lat, lon) }
+pub fn station(id: &str, lat: f64, lon: f64) -> Station {
+    Station {
+        id: id.to_string(),
+        state_code: "01".to_string(),
+        county_code: "001".to_string(),
+        site_number: id.split('-').next_back().unwrap_or("0001").to_string(),
+        latitude: lat,
+        longitude: lon,
+        land_use: LandUse::Residential,
+        location_setting: LocationSetting::UrbanAndCenterCity,
+        site_name: id.to_string(),
+        state_name: "Test State".to_string(),
+        county_name: "Test County".to_string(),
+        city_name: "Test City".to_string(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use aqs_analysis::adequacy::{self, CbsaRecord, Pollutant};
+use aqs_analysis::monitor::{EventType, Monitor};
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::station::Station;
+
+mod common;
+
+fn create_test_station(id: &str, cbsa: Option<&str>) -> Station {
+    Station {
+        state_name: "Alabama".to_string(),
+        county_name: "Jefferson".to_string(),
+        city_name: "Birmingham".to_string(),
+        cbsa_name: cbsa.map(str::to_string),
+        ..common::station(id, 33.5, -86.8)
+    }
+}
+
+fn create_test_monitor(site_id: &str, parameter_code: &str) -> Monitor {
+    Monitor {
+        site_id: site_id.to_string(),
+        parameter_code: parameter_code.to_string(),
+        parameter_name: String::new(),
+        poc: 1,
+        method_code: None,
+        method_name: None,
+        sample_duration: String::new(),
+        pollutant_standard: String::new(),
+        event_type: EventType::NoEvents,
+        observation_count: None,
+        observation_percent: None,
+        complete: Some(true),
+        arithmetic_mean: None,
+    }
+}
+
+#[test]
+fn test_required_monitors() {
+    //table D-5
+    assert_eq!(adequacy::required_monitors(Pollutant::Pm25, 2_000_000.0, Some(10.0)), 3);
+    assert_eq!(adequacy::required_monitors(Pollutant::Pm25, 2_000_000.0, Some(7.0)), 2);
+    assert_eq!(adequacy::required_monitors(Pollutant::Pm25, 600_000.0, Some(7.0)), 1);
+    assert_eq!(adequacy::required_monitors(Pollutant::Pm25, 100_000.0, Some(7.0)), 0);
+    assert_eq!(adequacy::required_monitors(Pollutant::Pm25, 100_000.0, None), 0);
+    assert_eq!(adequacy::required_monitors(Pollutant::Pm25, 40_000.0, None), 0);
+    //table D-2
+    assert_eq!(adequacy::required_monitors(Pollutant::Ozone, 12_000_000.0, Some(0.075)), 4);
+    assert_eq!(adequacy::required_monitors(Pollutant::Ozone, 5_000_000.0, Some(0.050)), 1);
+    assert_eq!(adequacy::required_monitors(Pollutant::Ozone, 400_000.0, Some(0.0595)), 2);
+    assert_eq!(adequacy::required_monitors(Pollutant::Ozone, 200_000.0, Some(0.050)), 0);
+}
+
+#[test]
+fn test_assess_cbsas() {
+    let mut network = MonitoringNetwork::new();
+    network.add_station(create_test_station("a", Some("Birmingham-Hoover, AL")));
+    network.add_station(create_test_station("b", Some("Birmingham-Hoover, AL")));
+    network.add_station(create_test_station("c", Some("Gadsden, AL")));
+    network.add_station(create_test_station("d", None));
+    let mut monitors = HashMap::new();
+    for id in ["a", "b", "c", "d"] {
+        monitors.insert(id.to_string(), vec![create_test_monitor(id, "88101")]);
+    }
+    monitors.get_mut("a").unwrap().push(create_test_monitor("a", "44201"));
+    network.attach_monitors(monitors);
+
+    let cbsas = HashMap::from([
+        ("Birmingham-Hoover, AL".to_string(), CbsaRecord {
+            name: "Birmingham-Hoover, AL".to_string(), population: 1_100_000.0,
+            pm25_design_value: None, ozone_design_value: Some(0.065),
+        }),
+        ("Gadsden, AL".to_string(), CbsaRecord {
+            name: "Gadsden, AL".to_string(), population: 103_000.0,
+            pm25_design_value: Some(7.0), ozone_design_value: None,
+        }),
+    ]);
+    //birmingham has no pm2.5 design value in the csv, so its highest site value (10.1) is used
+    let site_values = HashMap::from([("a".to_string(), 8.0), ("b".to_string(), 10.1), ("c".to_string(), 9.5)]);
+
+    let pm25 = adequacy::assess_cbsas(&network, &cbsas, Pollutant::Pm25, &site_values);
+    assert_eq!(pm25[0].cbsa_name, "Birmingham-Hoover, AL");
+    assert_eq!(pm25[0].design_value, Some(10.1));
+    assert_eq!((pm25[0].required, pm25[0].actual, pm25[0].surplus()), (3, 2, -1));
+    assert_eq!(pm25[1].design_value, Some(7.0));
+    assert_eq!((pm25[1].required, pm25[1].actual, pm25[1].surplus()), (0, 1, 1));
+
+    let ozone = adequacy::assess_cbsas(&network, &cbsas, Pollutant::Ozone, &HashMap::new());
+    let birmingham = ozone.iter().find(|r| r.cbsa_name.starts_with("Birmingham")).unwrap();
+    assert_eq!((birmingham.required, birmingham.actual), (2, 1));
+    let gadsden = ozone.iter().find(|r| r.cbsa_name.starts_with("Gadsden")).unwrap();
+    //no ozone design value, so the lower column applies
+    assert_eq!((gadsden.required, gadsden.actual), (0, 0));
+}
+
+#[test]
+fn test_read_cbsa_population_skips_malformed_rows() {
+    let path = std::env::temp_dir().join("aqs_test_cbsa_population.csv");
+    std::fs::write(&path, "CBSA Name,Population,PM25 Design Value
+\
+                           \"Birmingham-Hoover, AL\",1115289,9.8\n\
+                           \"Gadsden, AL\",not a number,\n\
+                           \"Mobile, AL\",411411\n").unwrap();
+    let records = adequacy::read_cbsa_population(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    //rows with a non-numeric population or missing columns are skipped
+    assert_eq!(records.len(), 1);
+    assert_eq!(records["Birmingham-Hoover, AL"].design_value(Pollutant::Pm25), Some(9.8));
+    assert!(adequacy::read_cbsa_population(std::env::temp_dir().join("aqs_test_missing_cbsa.csv")).is_err());
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use aqs_analysis::aggregate::{self, GroupBy};
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::population::CentroidCoverage;
+use aqs_analysis::station::Station;
+
+mod common;
+
+fn create_test_station(id: &str, lat: f64, state: (&str, &str), county: (&str, &str)) -> Station {
+    Station {
+        state_code: state.0.to_string(),
+        county_code: county.0.to_string(),
+        state_name: state.1.to_string(),
+        county_name: county.1.to_string(),
+        ..common::station(id, lat, -86.0)
+    }
+}
+
+fn create_coverage(geoid: &str, population: f64, distance_km: f64) -> CentroidCoverage {
+    CentroidCoverage {
+        geoid: geoid.to_string(),
+        state_code: geoid[..2].to_string(),
+        population,
+        nearest_station: None,
+        distance_km: Some(distance_km),
+    }
+}
+
+//a dense cluster in one alabama county and sparse stations in one georgia county
+fn create_test_network() -> (MonitoringNetwork, HashMap<String, f64>) {
+    let mut network = MonitoringNetwork::new();
+    for (i, sparse_lat) in [35.0, 35.6, 36.3, 37.2].into_iter().enumerate() {
+        network.add_station(create_test_station(&format!("a{}", i), 33.0 + i as f64 * 0.1,
+                                                ("01", "Alabama"), ("073", "Jefferson")));
+        network.add_station(create_test_station(&format!("g{}", i), sparse_lat,
+                                                ("13", "Georgia"), ("001", "Appling")));
+    }
+    network.build_adjacency_list();
+    network.calculate_isolation(1);
+    //g0 has no pollution value
+    let pollution: HashMap<String, f64> = network.stations.keys()
+        .filter(|id| id.as_str() != "g0")
+        .map(|id| (id.clone(), if id.starts_with('g') { 12.0 } else { 8.0 } + id[1..].parse::<f64>().unwrap()))
+        .collect();
+    (network, pollution)
+}
+
+#[test]
+fn test_aggregate_by_state_and_county() {
+    let (network, pollution) = create_test_network();
+
+    let states = aggregate::aggregate(&network, &pollution, GroupBy::State, None);
+    let groups: Vec<&str> = states.iter().map(|row| row.group.as_str()).collect();
+    assert_eq!(groups, vec!["Alabama", "Georgia"]);
+    let (alabama, georgia) = (&states[0], &states[1]);
+    assert_eq!((alabama.station_count, georgia.station_count), (4, 4));
+    assert!(alabama.isolation_min.unwrap() <= alabama.isolation_median.unwrap());
+    assert!(alabama.isolation_median.unwrap() <= alabama.isolation_max.unwrap());
+    assert!(georgia.isolation_median.unwrap() > alabama.isolation_max.unwrap());
+    assert!((alabama.mean_pollution.unwrap() - 9.5).abs() < 1e-9);
+    assert!((georgia.mean_pollution.unwrap() - 14.0).abs() < 1e-9);
+    assert_eq!(alabama.gap_count, 0);
+    assert!(georgia.gap_count > 0);
+    assert_eq!((alabama.monitored_percent, georgia.monitored_percent), (100.0, 75.0));
+    assert_eq!(alabama.population_coverage_percent, None);
+
+    //county and fips groupings hold the same stations under different keys
+    let counties = aggregate::aggregate(&network, &pollution, GroupBy::County, None);
+    assert_eq!(counties[0].group, "Appling, Georgia");
+    assert_eq!(counties[1].group, "Jefferson, Alabama");
+    let fips = aggregate::aggregate(&network, &pollution, GroupBy::CountyFips, None);
+    let groups: Vec<&str> = fips.iter().map(|row| row.group.as_str()).collect();
+    assert_eq!(groups, vec!["01073", "13001"]);
+    assert_eq!(fips[1].station_count, counties[0].station_count);
+    assert_eq!(fips[1].gap_count, counties[0].gap_count);
+}
+
+#[test]
+fn test_aggregate_coverage_and_csv() {
+    let (network, pollution) = create_test_network();
+    let coverage = vec![
+        create_coverage("01073000100", 1000.0, 5.0),
+        create_coverage("13001000100", 500.0, 10.0),
+        create_coverage("13001000200", 500.0, 40.0),
+        //no stations in this county, ignored
+        create_coverage("13003000100", 800.0, 90.0),
+    ];
+
+    let counties = aggregate::aggregate(&network, &pollution, GroupBy::CountyFips, Some((&coverage, 25.0)));
+    assert_eq!(counties[0].population_coverage_percent, Some(100.0));
+    assert_eq!(counties[1].population_coverage_percent, Some(50.0));
+    //at state level the unmatched county counts towards georgia
+    let states = aggregate::aggregate(&network, &pollution, GroupBy::StateFips, Some((&coverage, 25.0)));
+    assert!((states[1].population_coverage_percent.unwrap() - 500.0 / 1800.0 * 100.0).abs() < 1e-9);
+
+    let path = std::env::temp_dir().join("aqs_test_county_statistics.csv");
+    aggregate::write_csv(&counties, &path).unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "group,station_count,isolation_min,isolation_median,isolation_max,mean_pollution,\
+                          gap_count,monitored_percent,population_coverage_percent");
+    assert!(lines[1].starts_with("01073,4,"));
+    assert!(lines[2].ends_with(",75.0,50.0"));
+}
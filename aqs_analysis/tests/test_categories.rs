@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::station::{LandUse, LocationSetting, Station};
+
+mod common;
+
+fn create_test_station(id: &str, lat: f64, land_use: LandUse, setting: LocationSetting) -> Station {
+    Station { land_use, location_setting: setting, ..common::station(id, lat, -86.0) }
+}
+
+#[test]
+fn test_category_parsing() {
+    assert_eq!(LandUse::from("RESIDENTIAL"), LandUse::Residential);
+    assert_eq!(LandUse::from(" blighted "), LandUse::Blighted);
+    assert_eq!(LandUse::from("UNKNOWN"), LandUse::Unknown("UNKNOWN".to_string()));
+    assert_eq!(LocationSetting::from("Urban and Center City"), LocationSetting::UrbanAndCenterCity);
+    assert_eq!(LocationSetting::from(""), LocationSetting::Unknown(String::new()));
+    assert_eq!(LocationSetting::Suburban.to_string(), "SUBURBAN");
+
+    //serialized as the aqs text, unknown values round-trip unchanged
+    let json = serde_json::to_string(&(LandUse::Forest, LocationSetting::Unknown("Other".to_string()))).unwrap();
+    assert_eq!(json, r#"["FOREST","Other"]"#);
+    let (land_use, setting): (LandUse, LocationSetting) = serde_json::from_str(&json).unwrap();
+    assert_eq!((land_use, setting), (LandUse::Forest, LocationSetting::Unknown("Other".to_string())));
+}
+
+#[test]
+fn test_statistics_by_location_setting() {
+    let mut network = MonitoringNetwork::new();
+    //a dense urban cluster 0.1 degree apart and sparse rural stations 0.6 to 0.9 degree apart
+    for (i, rural_lat) in [35.0, 35.6, 36.3, 37.2].into_iter().enumerate() {
+        network.add_station(create_test_station(&format!("u{}", i), 33.0 + i as f64 * 0.1,
+                                                LandUse::Commercial, LocationSetting::UrbanAndCenterCity));
+        network.add_station(create_test_station(&format!("r{}", i), rural_lat,
+                                                LandUse::Agricultural, LocationSetting::Rural));
+    }
+    network.build_adjacency_list();
+    network.calculate_isolation(1);
+    let pollution: HashMap<String, f64> = network.stations.keys()
+        .map(|id| (id.clone(), if id.starts_with('r') { 12.0 } else { 8.0 } + id[1..].parse::<f64>().unwrap()))
+        .collect();
+
+    let by_setting = network.statistics_by_location_setting(&pollution);
+    assert_eq!(by_setting.len(), 2);
+    let (urban, rural) = (&by_setting[0], &by_setting[1]);
+    assert_eq!(urban.category, "URBAN AND CENTER CITY");
+    assert_eq!(rural.category, "RURAL");
+    assert_eq!((urban.station_count, rural.station_count), (4, 4));
+    assert!(rural.isolation.unwrap().median > urban.isolation.unwrap().median * 5.0);
+    assert!(urban.correlation.is_some());
+    assert_eq!(urban.gap_count, 0);
+    assert!(rural.gap_count > 0);
+
+    let by_land_use = network.statistics_by_land_use(&pollution);
+    let categories: Vec<&str> = by_land_use.iter().map(|stats| stats.category.as_str()).collect();
+    assert_eq!(categories, vec!["COMMERCIAL", "AGRICULTURAL"]);
+}
@@ -0,0 +1,226 @@
+use std::io::Write;
+use std::path::PathBuf;
+use chrono::NaiveDate;
+use aqs_analysis::data::{read_monitors, read_pollution, read_pollution_years, read_stations, read_stations_with_policy,
+                         CoordinateCheck, DuplicateCoordinates, LoadPolicy};
+use aqs_analysis::monitor::CompletenessFilter;
+use aqs_analysis::spatial;
+use aqs_analysis::station::Datum;
+
+// Write csv content to a uniquely named file in the system temp directory
+fn write_temp_csv(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("aqs_analysis_{}_{}.csv", name, std::process::id()));
+    let mut file = std::fs::File::create(&path).unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    path
+}
+
+const SITES_HEADER: &str = "State Code,County Code,Site Number,Latitude,Longitude,Land Use,Location Setting,Site Established Date,Site Closed Date,Local Site Name,State Name,County Name,City Name\n";
+
+#[test]
+fn test_read_stations_parses_dates() {
+    let content = format!("{}{}{}",
+        SITES_HEADER,
+        "01,003,0010,30.497478,-87.880258,AGRICULTURAL,RURAL,1983-01-01,2005-12-31,FAIRHOPE,Alabama,Baldwin,Fairhope\n",
+        "01,073,0023,33.553056,-86.815,INDUSTRIAL,URBAN AND CENTER CITY,1977-01-01,,North Birmingham,Alabama,Jefferson,Birmingham\n");
+    let path = write_temp_csv("sites_dates", &content);
+
+    let stations = read_stations(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(stations.len(), 2);
+    assert_eq!(stations[0].id, "01-003-0010");
+    assert_eq!(stations[0].date_closed.unwrap().to_string(), "2005-12-31");
+    assert_eq!(stations[1].date_established.unwrap().to_string(), "1977-01-01");
+    assert!(stations[1].date_closed.is_none());
+}
+
+#[test]
+fn test_read_stations_without_date_columns() {
+    let content = "State Code,County Code,Site Number,Latitude,Longitude,Land Use,Location Setting,Local Site Name,State Name,County Name,City Name\n\
+                   01,073,0023,33.553056,-86.815,INDUSTRIAL,URBAN AND CENTER CITY,North Birmingham,Alabama,Jefferson,Birmingham\n";
+    let path = write_temp_csv("sites_no_dates", content);
+
+    let stations = read_stations(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(stations.len(), 1);
+    assert!(stations[0].date_established.is_none() && stations[0].date_closed.is_none());
+}
+
+#[test]
+fn test_load_policy_summary() {
+    let content = format!("{}{}{}{}{}{}{}{}{}",
+        SITES_HEADER,
+        "01,003,0010,30.497478,-87.880258,AGRICULTURAL,RURAL,1983-01-01,2005-12-31,FAIRHOPE,Alabama,Baldwin,Fairhope\n",
+        "01,073,0023,33.553056,-86.815,INDUSTRIAL,URBAN AND CENTER CITY,1977-01-01,,North Birmingham,Alabama,Jefferson,Birmingham\n",
+        "01,073,0024,33.553056,-86.815,INDUSTRIAL,URBAN AND CENTER CITY,1990-01-01,,North Birmingham 2,Alabama,Jefferson,Birmingham\n",
+        "01,073,9999,0,0,INDUSTRIAL,URBAN AND CENTER CITY,1990-01-01,,Null Island,Alabama,Jefferson,Birmingham\n",
+        "01,073,9998,95.0,-86.8,INDUSTRIAL,URBAN AND CENTER CITY,1990-01-01,,Bad Latitude,Alabama,Jefferson,Birmingham\n",
+        "80,002,0001,19.43,-99.13,COMMERCIAL,URBAN AND CENTER CITY,1990-01-01,,Mexico City,Country Of Mexico,Distrito Federal,Mexico City\n",
+        "80,004,0002,32.53,-117.03,COMMERCIAL,URBAN AND CENTER CITY,1990-01-01,,Tijuana,Country Of Mexico,Baja California,Tijuana\n",
+        "72,127,0003,18.42,-66.06,COMMERCIAL,URBAN AND CENTER CITY,1990-01-01,,San Juan,Puerto Rico,San Juan,San Juan\n");
+    let path = write_temp_csv("sites_policy", &content);
+
+    //the default only drops invalid coordinates and reports duplicates
+    let (stations, summary) = read_stations_with_policy(&path, &LoadPolicy::default()).unwrap();
+    assert_eq!(stations.len(), 6);
+    assert_eq!(summary.invalid_coordinates, 2);
+    assert_eq!(summary.duplicate_coordinates, 1);
+    assert_eq!(summary.duplicates, vec![("01-073-0024".to_string(), "01-073-0023".to_string())]);
+    assert_eq!(read_stations(&path).unwrap().len(), 6);
+
+    let policy = LoadPolicy {
+        closed_as_of: NaiveDate::from_ymd_opt(2023, 1, 1),
+        coordinates: CoordinateCheck::UsTerritory,
+        duplicates: DuplicateCoordinates::KeepFirst,
+    };
+    let (stations, summary) = read_stations_with_policy(&path, &policy).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let ids: Vec<&str> = stations.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, vec!["01-073-0023", "72-127-0003"]);
+    assert_eq!((summary.records, summary.loaded), (8, 2));
+    //tijuana is inside the contiguous states' box but has a mexican state code
+    assert_eq!((summary.invalid_coordinates, summary.outside_territory, summary.closed), (2, 2, 1));
+    assert_eq!((summary.duplicate_coordinates, summary.duplicates_dropped), (1, 1));
+}
+
+#[test]
+fn test_read_stations_malformed_rows() {
+    let content = format!("{}{}{}{}{}",
+        SITES_HEADER,
+        "01,073,0023,33.553056,-86.815,INDUSTRIAL,URBAN AND CENTER CITY,1977-01-01,,North Birmingham,Alabama,Jefferson,Birmingham\n",
+        "01,073,0025,north,-86.815,INDUSTRIAL,URBAN AND CENTER CITY,1977-01-01,,Bad Latitude,Alabama,Jefferson,Birmingham\n",
+        "01,073,0026,33.5,-86.8,INDUSTRIAL,URBAN AND CENTER CITY,01/01/1977,,Bad Date,Alabama,Jefferson,Birmingham\n",
+        "01,073,0027,33.5,-86.8,INDUSTRIAL\n");
+    let path = write_temp_csv("sites_malformed", &content);
+
+    let (stations, summary) = read_stations_with_policy(&path, &LoadPolicy::default()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    //a non-numeric latitude, a date in another format and a short row are counted and skipped
+    assert_eq!(stations.len(), 1);
+    assert_eq!((summary.records, summary.parse_errors, summary.loaded), (4, 3, 1));
+    assert!(read_stations(std::env::temp_dir().join("aqs_analysis_missing_sites.csv")).is_err());
+}
+
+const FULL_SITES_HEADER: &str = "State Code,County Code,Site Number,Latitude,Longitude,Datum,Elevation,Land Use,Location Setting,Site Established Date,Site Closed Date,Met Site State Code,Met Site County Code,Met Site Site Number,Met Site Type,Met Site Distance,Met Site Direction,GMT Offset,Owning Agency,Local Site Name,Address,Zip Code,State Name,County Name,City Name,CBSA Name,Tribe Name,Extraction Date\n";
+
+#[test]
+fn test_read_stations_metadata_and_datum() {
+    let content = format!("{}{}{}",
+        FULL_SITES_HEADER,
+        "01,073,0023,33.553056,-86.815,WGS84,189,INDUSTRIAL,URBAN AND CENTER CITY,1977-01-01,,01,073,0023,ON-SITE MET EQUIP,0,,-6,Jefferson County,North Birmingham,3009 28th St,35207,Alabama,Jefferson,Birmingham,\"Birmingham-Hoover, AL\",,2024-05-01\n",
+        "04,001,0010,35.9,-109.5,NAD27,1900.5,AGRICULTURAL,RURAL,1990-01-01,2001-06-30,,,,,,,-7,Navajo Nation,Ganado,,,Arizona,Apache,Not in a city,,Navajo Nation,2024-05-01\n");
+    let path = write_temp_csv("sites_metadata", &content);
+
+    let stations = read_stations(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(stations.len(), 2);
+    let birmingham = &stations[0];
+    assert_eq!(birmingham.datum, Some(Datum::Wgs84));
+    assert_eq!(birmingham.latitude, 33.553056);
+    assert_eq!(birmingham.elevation, Some(189.0));
+    assert_eq!(birmingham.cbsa_name.as_deref(), Some("Birmingham-Hoover, AL"));
+    assert!(birmingham.tribe_name.is_none());
+    assert_eq!(birmingham.met_site_id().as_deref(), Some("01-073-0023"));
+    assert_eq!(birmingham.met_site_distance, Some(0.0));
+
+    //nad27 coordinates are shifted by tens of meters and relabeled as wgs84
+    let ganado = &stations[1];
+    assert_eq!(ganado.datum, Some(Datum::Wgs84));
+    assert_eq!(ganado.tribe_name.as_deref(), Some("Navajo Nation"));
+    assert!(ganado.met_site_id().is_none());
+    assert_eq!(ganado.date_closed.unwrap().to_string(), "2001-06-30");
+    let shift_m = spatial::haversine_km(35.9, -109.5, ganado.latitude, ganado.longitude) * 1000.0;
+    assert!(shift_m > 10.0 && shift_m < 150.0, "unexpected datum shift {} m", shift_m);
+}
+
+#[test]
+fn test_read_stations_nad27_regions() {
+    let content = format!("{}{}{}",
+        FULL_SITES_HEADER,
+        "02,020,0018,61.2,-149.8,NAD27,40,RESIDENTIAL,URBAN AND CENTER CITY,1990-01-01,,,,,,,,-9,Alaska DEC,Anchorage,,,Alaska,Anchorage,Anchorage,,,2024-05-01\n",
+        "15,003,0010,21.3,-157.86,NAD27,10,RESIDENTIAL,URBAN AND CENTER CITY,1990-01-01,,,,,,,,-10,Hawaii DOH,Honolulu,,,Hawaii,Honolulu,Honolulu,,,2024-05-01\n");
+    let path = write_temp_csv("sites_nad27_regions", &content);
+
+    let (stations, summary) = read_stations_with_policy(&path, &LoadPolicy::default()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    //alaska uses its own shift, which moves sites roughly 100-250 m
+    let anchorage = &stations[0];
+    assert_eq!(anchorage.datum, Some(Datum::Wgs84));
+    let shift_m = spatial::haversine_km(61.2, -149.8, anchorage.latitude, anchorage.longitude) * 1000.0;
+    assert!(shift_m > 50.0 && shift_m < 300.0, "unexpected datum shift {} m", shift_m);
+    //hawaii has no nad27 shift, so the site keeps its coordinates and datum
+    let honolulu = &stations[1];
+    assert_eq!(honolulu.datum, Some(Datum::Nad27));
+    assert_eq!((honolulu.latitude, honolulu.longitude), (21.3, -157.86));
+    assert_eq!(summary.converted_nad27, 1);
+    assert!(spatial::nad27_to_wgs84(18.4, -66.1, 0.0).is_none());
+}
+
+const ANNUAL_HEADER: &str = "State Code,County Code,Site Num,Parameter Code,POC,Parameter Name,Sample Duration,Pollutant Standard,Method Name,Observation Count,Completeness Indicator,Arithmetic Mean\n";
+
+#[test]
+fn test_read_monitors_keeps_collocated_monitors() {
+    let content = format!("{}{}{}{}{}",
+        ANNUAL_HEADER,
+        "06,037,1103,88101,1,PM2.5 - Local Conditions,24-HR BLK AVG,PM25 Annual 2012,Gravimetric,118,Y,11.9\n",
+        "06,037,1103,88101,3,PM2.5 - Local Conditions,1 HOUR,PM25 Annual 2012,BAM,8410,Y,12.6\n",
+        "06,037,1103,88101,2,PM2.5 - Local Conditions,24-HR BLK AVG,PM25 Annual 2012,Gravimetric,20,N,13.4\n",
+        "06,037,1103,88101,4,PM2.5 - Local Conditions,24-HR BLK AVG,PM25 Annual 2012,Gravimetric,110,,12.1\n");
+    let path = write_temp_csv("annual_monitors", &content);
+
+    let sites = read_monitors(&path).unwrap();
+    let pollution = read_pollution(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let monitors = &sites["06-037-1103"];
+    assert_eq!(monitors.len(), 4);
+    assert_eq!(monitors[1].poc, 3);
+    assert_eq!(monitors[1].observation_count, Some(8410));
+    assert_eq!(monitors[2].complete, Some(false));
+    //a blank indicator is unknown, not incomplete
+    assert_eq!(monitors[3].complete, None);
+
+    // Primary 24-hour monitor is POC 1
+    assert_eq!(pollution["06-037-1103"], 11.9);
+}
+
+#[test]
+fn test_read_monitors_skips_malformed_rows() {
+    let content = format!("{}{}{}{}",
+        ANNUAL_HEADER,
+        "06,037,1103,88101,1,PM2.5 - Local Conditions,24-HR BLK AVG,PM25 Annual 2012,Gravimetric,118,Y,11.9\n",
+        "06,037,1103,88101,one,PM2.5 - Local Conditions,24-HR BLK AVG,PM25 Annual 2012,Gravimetric,118,Y,11.9\n",
+        "06,037,1201,88101,1,PM2.5 - Local Conditions,24-HR BLK AVG,PM25 Annual 2012,Gravimetric,118,Y,high\n");
+    let path = write_temp_csv("annual_malformed", &content);
+
+    let sites = read_monitors(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    //a non-numeric poc or mean skips the row, the file is still read
+    assert_eq!(sites.len(), 1);
+    assert_eq!(sites["06-037-1103"].len(), 1);
+    assert!(read_monitors(std::env::temp_dir().join("aqs_analysis_missing_annual.csv")).is_err());
+}
+
+#[test]
+fn test_read_pollution_years_applies_completeness_filter() {
+    let path_2022 = write_temp_csv("annual_2022", &format!("{}{}",
+        ANNUAL_HEADER,
+        "06,037,1103,88101,1,PM2.5 - Local Conditions,24-HR BLK AVG,PM25 Annual 2012,Gravimetric,118,Y,11.9\n"));
+    //the only 2023 monitor is incomplete, so the site has no 2023 value
+    let path_2023 = write_temp_csv("annual_2023", &format!("{}{}",
+        ANNUAL_HEADER,
+        "06,037,1103,88101,1,PM2.5 - Local Conditions,24-HR BLK AVG,PM25 Annual 2012,Gravimetric,20,N,13.4\n"));
+    let filter = CompletenessFilter { require_complete: true, ..Default::default() };
+
+    let yearly = read_pollution_years(&[(2022, &path_2022), (2023, &path_2023)], &filter).unwrap();
+    std::fs::remove_file(&path_2022).unwrap();
+    std::fs::remove_file(&path_2023).unwrap();
+
+    assert_eq!(yearly[&2022]["06-037-1103"], 11.9);
+    assert!(yearly[&2023].is_empty());
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use aqs_analysis::equity::{read_demographics, stratify_by_decile, DemographicTable};
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::population::CentroidCoverage;
+
+fn coverage(geoid: &str, population: f64, station: &str, distance_km: f64) -> CentroidCoverage {
+    CentroidCoverage {
+        geoid: geoid.to_string(),
+        state_code: geoid[..2].to_string(),
+        population,
+        nearest_station: Some(station.to_string()),
+        distance_km: Some(distance_km),
+    }
+}
+
+#[test]
+fn test_stratify_by_decile() {
+    let network = MonitoringNetwork::new();
+    let mut demographics = DemographicTable {
+        indicators: vec!["P_LWINCPCT".to_string()],
+        values: HashMap::new(),
+    };
+    for (geoid, percentile) in [("01001000100", 5.0), ("01001000200", 95.0), ("01001000300", 99.0)] {
+        let mut row = HashMap::new();
+        row.insert("P_LWINCPCT".to_string(), percentile);
+        demographics.values.insert(geoid.to_string(), row);
+    }
+
+    let records = vec![
+        coverage("01001000100", 1000.0, "A", 10.0),
+        // Block group joins to its tract's demographics
+        coverage("010010002001", 1000.0, "B", 80.0),
+        coverage("01001000300", 3000.0, "A", 40.0),
+        // No demographics for this tract, so it is left out
+        coverage("01001000400", 5000.0, "A", 1.0),
+    ];
+    let mut pollution = HashMap::new();
+    pollution.insert("A".to_string(), 8.0);
+    pollution.insert("B".to_string(), 12.0);
+
+    let deciles = stratify_by_decile(&records, &demographics, "P_LWINCPCT", &network, &pollution, &[50.0]);
+
+    assert_eq!(deciles.len(), 2);
+    assert_eq!(deciles[0].decile, 1);
+    assert_eq!(deciles[0].population, 1000.0);
+    assert!((deciles[0].percent_within[0].1 - 100.0).abs() < 1e-9);
+
+    let top = &deciles[1];
+    assert_eq!(top.decile, 10);
+    assert_eq!(top.area_count, 2);
+    assert!((top.mean_distance_km - 50.0).abs() < 1e-9);
+    assert!((top.percent_within[0].1 - 75.0).abs() < 1e-9);
+    assert!((top.mean_pollution.unwrap() - 9.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_read_demographics_errors_and_blank_cells() {
+    let path = std::env::temp_dir().join("aqs_test_demographics.csv");
+    std::fs::write(&path, "ID,LOWINCPCT,PEOPCOLORPCT\n010010201001,0.4,\n010010201002,n/a,0.7\n").unwrap();
+    let table = read_demographics(&path, "ID").unwrap();
+    //a file without the geoid column is rejected
+    let missing = read_demographics(&path, "GEOID");
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(missing.unwrap_err().to_string().contains("no GEOID column"));
+    assert_eq!(table.get("010010201001", "LOWINCPCT"), Some(0.4));
+    //empty and non-numeric cells are skipped
+    assert_eq!(table.get("010010201001", "PEOPCOLORPCT"), None);
+    assert_eq!(table.get("010010201002", "LOWINCPCT"), None);
+    assert_eq!(table.get("010010201002", "PEOPCOLORPCT"), Some(0.7));
+}
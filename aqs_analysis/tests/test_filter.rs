@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use aqs_analysis::filter::StationFilter;
+use aqs_analysis::metrics::{MetricsTable, Provenance, ISOLATION};
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::station::{LandUse, Station};
+
+mod common;
+
+fn create_test_station(id: &str, state: &str, setting: &str) -> Station {
+    Station {
+        state_code: "06".to_string(),
+        land_use: LandUse::Agricultural,
+        location_setting: setting.into(),
+        state_name: state.to_string(),
+        ..common::station(id, 37.0, -120.0)
+    }
+}
+
+#[test]
+fn test_filter_expressions() {
+    let rural = create_test_station("1", "California", "RURAL");
+    let urban = create_test_station("2", "California", "URBAN AND CENTER CITY");
+    let unknown = create_test_station("3", "Nevada", "RURAL");
+    //the third station has no isolation value
+    let mut metrics = MetricsTable::new();
+    metrics.insert_numbers(ISOLATION, Provenance::new("mean_knn"),
+                           &HashMap::from([("1".to_string(), 80.0), ("2".to_string(), 10.0)]));
+
+    let filter = StationFilter::parse(r#"state_name == "California" && location_setting == "RURAL" && isolation > 50"#).unwrap();
+    assert!(filter.matches(&rural, &metrics));
+    assert!(!filter.matches(&urban, &metrics));
+    assert!(!filter.matches(&unknown, &metrics));
+
+    //case-insensitive text, precedence of && over ||, negation and parentheses
+    let filter = StationFilter::parse(r#"location_setting == "rural" || isolation <= 10 && !(state_name != "california")"#).unwrap();
+    assert!(filter.matches(&rural, &metrics) && filter.matches(&urban, &metrics) && filter.matches(&unknown, &metrics));
+    let filter = StationFilter::parse(r#"!(isolation >= -1.5)"#).unwrap();
+    assert!(!filter.matches(&rural, &metrics) && filter.matches(&unknown, &metrics));
+
+    //a missing value never matches, with either == or !=, for text and numeric fields alike
+    let filter = StationFilter::parse(r#"isolation != 80"#).unwrap();
+    assert!(filter.matches(&urban, &metrics) && !filter.matches(&unknown, &metrics));
+    let with_cbsa = Station { cbsa_name: Some("Fresno, CA".to_string()), ..rural.clone() };
+    let filter = StationFilter::parse(r#"cbsa_name != "Madera, CA""#).unwrap();
+    assert!(filter.matches(&with_cbsa, &metrics) && !filter.matches(&rural, &metrics));
+    let filter = StationFilter::parse(r#"cbsa_name == "Fresno, CA""#).unwrap();
+    assert!(filter.matches(&with_cbsa, &metrics) && !filter.matches(&rural, &metrics));
+    let filter = StationFilter::parse(r#"!(cbsa_name != "")"#).unwrap();
+    assert!(!filter.matches(&with_cbsa, &metrics) && filter.matches(&rural, &metrics));
+
+    //deep nesting is an error rather than a stack overflow
+    let deep = format!("{}isolation > 1{}", "(".repeat(100_000), ")".repeat(100_000));
+    assert!(StationFilter::parse(&deep).unwrap_err().to_string().contains("nests more than"));
+    assert!(StationFilter::parse(&format!("{}isolation > 1", "!".repeat(100_000))).is_err());
+    let long = vec!["isolation > 1"; 2000].join(" && ");
+    assert!(StationFilter::parse(&long).is_err());
+    assert!(StationFilter::parse(&format!("{}isolation > 1{}", "(".repeat(64), ")".repeat(64))).is_ok());
+
+    for invalid in [r#"altitude > 3"#, r#"state_name > "A""#, r#"isolation == "far""#, r#"state_name == 6"#,
+                    r#"(isolation > 3"#, r#"state_name == "Ohio"#, r#"isolation > 3 isolation"#, ""] {
+        assert!(StationFilter::parse(invalid).is_err(), "{} should not parse", invalid);
+    }
+}
+
+#[test]
+fn test_filtered_network() {
+    let mut network = MonitoringNetwork::new();
+    network.add_station(create_test_station("1", "California", "RURAL"));
+    network.add_station(create_test_station("2", "California", "URBAN AND CENTER CITY"));
+    network.add_station(create_test_station("3", "Nevada", "RURAL"));
+    network.build_adjacency_list();
+    network.calculate_isolation(1);
+
+    let filter = StationFilter::parse(r#"location_setting == "RURAL""#).unwrap();
+    let rural = network.filtered(&filter);
+    assert_eq!(rural.stations.len(), 2);
+    assert!(rural.adjacency_list.is_empty());
+    assert!(rural.metrics.is_empty());
+    assert_eq!(filter.to_string(), r#"location_setting == "RURAL""#);
+}
@@ -0,0 +1,60 @@
+use chrono::NaiveDate;
+use aqs_analysis::history::compare_dates;
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::station::Station;
+
+mod common;
+
+fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+}
+
+fn create_test_station(id: &str, lat: f64, lon: f64,
+                       established: Option<&str>, closed: Option<&str>) -> Station {
+    Station {
+        date_established: established.map(date),
+        date_closed: closed.map(date),
+        ..common::station(id, lat, lon)
+    }
+}
+
+fn create_test_network() -> MonitoringNetwork {
+    let mut network = MonitoringNetwork::new();
+    network.add_station(create_test_station("01-001-0001", 40.0, -74.0, Some("1990-01-01"), None));
+    network.add_station(create_test_station("01-001-0002", 40.1, -74.0, Some("1990-01-01"), Some("2015-06-30")));
+    network.add_station(create_test_station("01-001-0003", 40.5, -74.0, None, None));
+    network.add_station(create_test_station("01-001-0004", 40.6, -74.0, Some("2018-03-01"), None));
+    network
+}
+
+#[test]
+fn test_network_as_of_date() {
+    let network = create_test_network();
+
+    let early = network.as_of(date("2010-01-01"));
+    assert_eq!(early.stations.len(), 3);
+    assert!(!early.stations.contains_key("01-001-0004"));
+
+    let late = network.as_of(date("2020-01-01"));
+    assert_eq!(late.stations.len(), 3);
+    assert!(!late.stations.contains_key("01-001-0002"));
+}
+
+#[test]
+fn test_compare_dates() {
+    let network = create_test_network();
+    let diff = compare_dates(&network, date("2010-01-01"), date("2020-01-01"), 1);
+
+    assert_eq!(diff.added, vec!["01-001-0004".to_string()]);
+    assert_eq!(diff.removed, vec!["01-001-0002".to_string()]);
+    assert_eq!(diff.isolation_changes.len(), 2);
+
+    // Station 1 lost its closest neighbor, so it is the most newly isolated
+    let largest = &diff.isolation_changes[0];
+    assert_eq!(largest.station_id, "01-001-0001");
+    assert!(largest.change() > 0.0);
+
+    // Station 3 gained a closer neighbor when station 4 opened
+    let station3 = diff.isolation_changes.iter().find(|c| c.station_id == "01-001-0003").unwrap();
+    assert!(station3.change() < 0.0);
+}
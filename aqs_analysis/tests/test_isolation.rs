@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use aqs_analysis::isolation::{self, IsolationMetric};
+use aqs_analysis::network::MonitoringNetwork;
+
+mod common;
+
+#[test]
+fn test_isolation_metric_values() {
+    //stations along one meridian, 0.1 degree of latitude is about 11.12 km
+    let mut network = MonitoringNetwork::new();
+    for (id, lat) in [("a", 35.0), ("b", 35.1), ("c", 35.2), ("d", 35.4), ("e", 35.8)] {
+        network.add_station(common::station(id, lat, -86.0));
+    }
+    network.build_adjacency_list();
+    network.calculate_isolation(2);
+    let step = network.haversine_distance(35.0, -86.0, 35.1, -86.0);
+
+    for metric in isolation::standard_metrics(2) {
+        network.calculate_isolation_metric(&metric);
+    }
+    network.calculate_isolation_metric(&IsolationMetric::MedianKnn { k: 3 });
+    let value = |name: &str, id: &str| network.metrics.number(name, id).unwrap();
+    //mean knn matches calculate_isolation
+    assert_eq!(Some(value("mean_knn_2", "a")), network.isolation("a"));
+    assert_eq!(network.metrics.metric("median_knn_3").unwrap().provenance.to_string(), "median_knn(k=3)");
+    assert!((value("nearest_neighbor", "a") - step).abs() < 1e-6);
+    assert!((value("median_knn_2", "a") - 1.5 * step).abs() < 1e-6);
+    assert!((value("median_knn_3", "a") - 2.0 * step).abs() < 1e-6);
+    assert!((value("harmonic_knn_2", "a") - 2.0 / (1.0 / step + 1.0 / (2.0 * step))).abs() < 1e-6);
+    assert_eq!(value("neighbors_within_50km", "a"), 3.0);
+    assert_eq!(value("neighbors_within_50km", "e"), 1.0);
+    assert!(value("kernel_sparsity_25km", "e") > value("kernel_sparsity_25km", "b"));
+
+    //the center of a regular 3 x 3 grid gets a cell of one grid spacing squared
+    let mut grid = MonitoringNetwork::new();
+    let lon_step = 0.2 / 35.0_f64.to_radians().cos();
+    for row in -1..=1 {
+        for col in -1..=1 {
+            grid.add_station(common::station(&format!("{}{}", row, col), 35.0 + 0.2 * row as f64,
+                                                 -86.0 + lon_step * col as f64));
+        }
+    }
+    grid.build_adjacency_list();
+    grid.calculate_isolation_metric(&IsolationMetric::VoronoiArea { max_radius_km: 100.0 });
+    let areas = grid.metrics.numbers("voronoi_area_100km");
+    let spacing = 2.0 * step;
+    assert!((areas["00"] / (spacing * spacing) - 1.0).abs() < 0.02);
+    //edge cells are cut off by the bounding circle
+    assert!(areas["-1-1"] > areas["00"] && areas["-1-1"] < std::f64::consts::PI * 100.0 * 100.0);
+    //cells come from station positions, not from whatever edges the adjacency list kept
+    grid.adjacency_list.clear();
+    grid.calculate_isolation_metric(&IsolationMetric::VoronoiArea { max_radius_km: 100.0 });
+    assert_eq!(grid.metrics.numbers("voronoi_area_100km"), areas);
+}
+
+#[test]
+fn test_radius_metrics_beyond_adjacency() {
+    //stations 2.5 and 4 degrees apart have no adjacency edges, but count for large radii
+    let mut network = MonitoringNetwork::new();
+    for (id, lat) in [("a", 35.0), ("b", 37.5), ("c", 39.0)] {
+        network.add_station(common::station(id, lat, -86.0));
+    }
+    network.build_adjacency_list();
+    assert!(network.adjacency_list["a"].is_empty());
+
+    network.calculate_isolation_metric(&IsolationMetric::NeighborsWithin { radius_km: 500.0 });
+    network.calculate_isolation_metric(&IsolationMetric::KernelSparsity { bandwidth_km: 200.0 });
+    assert_eq!(network.metrics.number("neighbors_within_500km", "a"), Some(2.0));
+    assert_eq!(network.metrics.number("neighbors_within_500km", "b"), Some(2.0));
+    //a lone station's sparsity is the area under one kernel, neighbors make it smaller
+    let alone = 2.0 * std::f64::consts::PI * 200.0 * 200.0;
+    assert!(network.metrics.number("kernel_sparsity_200km", "a").unwrap() < alone * 0.9);
+}
+
+#[test]
+fn test_compare_metrics() {
+    //a dense cluster with low pollution and sparse stations with high pollution
+    let mut network = MonitoringNetwork::new();
+    for (i, sparse_lat) in [35.0, 35.6, 36.3, 37.2].into_iter().enumerate() {
+        network.add_station(common::station(&format!("u{}", i), 33.0 + i as f64 * 0.1, -86.0));
+        network.add_station(common::station(&format!("r{}", i), sparse_lat, -86.0));
+    }
+    network.build_adjacency_list();
+    let metrics = [IsolationMetric::MeanKnn { k: 1 }, IsolationMetric::NeighborsWithin { radius_km: 50.0 },
+                   IsolationMetric::VoronoiArea { max_radius_km: 100.0 }];
+    for metric in &metrics {
+        network.calculate_isolation_metric(metric);
+    }
+    let pollution: HashMap<String, f64> = network.stations.keys()
+        .map(|id| (id.clone(), if id.starts_with('r') { 12.0 } else { 8.0 } + id[1..].parse::<f64>().unwrap()))
+        .collect();
+
+    let comparison = isolation::compare_metrics(&network, &pollution, &metrics);
+    assert_eq!(comparison.len(), 3);
+    assert_eq!(comparison[0].metric, "mean_knn_1");
+    assert_eq!(comparison[0].station_count, 8);
+    assert!(comparison[0].correlation.unwrap() > 0.5);
+    //fewer neighbors means more isolated, so counts correlate negatively
+    assert!(comparison[1].correlation.unwrap() < -0.5);
+    assert!(comparison[0].gap_count > 0);
+    assert_eq!(comparison[0].gap_overlap, Some(1.0));
+    assert!(comparison.iter().all(|c| c.gap_overlap.is_some()));
+}
@@ -0,0 +1,43 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use aqs_analysis::kml;
+use aqs_analysis::metrics::{Provenance, ISOLATION};
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::station::Station;
+
+mod common;
+
+fn create_test_station(id: &str, name: &str, state: &str, lat: f64, lon: f64) -> Station {
+    Station { site_name: name.to_string(), state_name: state.to_string(), ..common::station(id, lat, lon) }
+}
+
+#[test]
+fn test_kml_folders_styles_and_kmz() {
+    let mut network = MonitoringNetwork::new();
+    network.add_station(create_test_station("1", "Station1", "Alabama", 33.5, -86.8));
+    network.add_station(create_test_station("2", "Station2", "Alabama", 33.6, -86.7));
+    network.add_station(create_test_station("3", "Mill & Main", "Georgia", 33.7, -84.4));
+    let isolation: HashMap<String, f64> = network.stations.keys().map(|id| (id.clone(), 12.5)).collect();
+    network.metrics.insert_numbers(ISOLATION, Provenance::new("mean_knn"), &isolation);
+    let pollution = HashMap::from([("1".to_string(), 9.5), ("3".to_string(), 12.0)]);
+    let gaps = HashSet::from(["3".to_string()]);
+
+    let document = kml::render_kml(&network, &pollution, &gaps, "Gaps");
+    assert_eq!(document.matches("<Folder>").count(), 2);
+    assert!(document.find("<name>Alabama</name>").unwrap() < document.find("<name>Georgia</name>").unwrap());
+    assert_eq!(document.matches("<Placemark>").count(), 3);
+    assert_eq!(document.matches("<styleUrl>#gap</styleUrl>").count(), 1);
+    assert_eq!(document.matches("<styleUrl>#monitored</styleUrl>").count(), 1);
+    assert_eq!(document.matches("<styleUrl>#no_data</styleUrl>").count(), 1);
+    assert!(document.contains("<name>Mill &amp; Main</name>"));
+    assert!(document.contains("<coordinates>-84.4,33.7,0</coordinates>"));
+    assert!(document.contains("<th align=\"left\">Isolation</th><td>12.50 km</td>"));
+
+    let path = std::env::temp_dir().join(format!("aqs_test_{}.kmz", std::process::id()));
+    kml::write_kmz(&network, &pollution, &gaps, "Gaps", &path).unwrap();
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let mut contents = String::new();
+    archive.by_name("doc.kml").unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, document);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use aqs_analysis::metrics::{self, MetricValue, MetricsTable, Provenance, ISOLATION};
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::station::Station;
+
+mod common;
+
+fn create_test_station(id: &str, lat: f64) -> Station {
+    common::station(id, lat, -86.0)
+}
+
+fn numbers(values: &[(&str, f64)]) -> HashMap<String, f64> {
+    values.iter().map(|(id, value)| (id.to_string(), *value)).collect()
+}
+
+#[test]
+fn test_metrics_table_values_and_export() {
+    let mut table = MetricsTable::new();
+    table.insert_numbers("ozone", Provenance::new("site_values").with("parameter", "44201"),
+                         &numbers(&[("a", 0.041), ("b", 0.038)]));
+    table.insert("setting", Provenance::new("aqs_sites"), HashMap::from([
+        ("a".to_string(), MetricValue::Category("RURAL".to_string())),
+        ("b".to_string(), MetricValue::Category("URBAN".to_string())),
+    ]));
+    table.insert("near_road", Provenance::new("manual"), HashMap::from([
+        ("a".to_string(), MetricValue::Flag(false)),
+        ("b".to_string(), MetricValue::Flag(true)),
+    ]));
+
+    assert_eq!(table.names().collect::<Vec<_>>(), vec!["near_road", "ozone", "setting"]);
+    assert_eq!(table.number("ozone", "a"), Some(0.041));
+    assert_eq!(table.number("ozone", "c"), None);
+    //flags count as 0 and 1, categories have no numeric value
+    assert_eq!(table.numbers("near_road"), numbers(&[("a", 0.0), ("b", 1.0)]));
+    assert!(table.numbers("setting").is_empty());
+    assert_eq!(table.get("setting", "b"), Some(&MetricValue::Category("URBAN".to_string())));
+    assert_eq!(table.metric("ozone").unwrap().provenance.to_string(), "site_values(parameter=44201)");
+
+    table.retain_stations(|id| id == "b");
+    assert_eq!(table.metric("ozone").unwrap().values.len(), 1);
+
+    let path = std::env::temp_dir().join(format!("aqs_test_metrics_{}.csv", std::process::id()));
+    metrics::write_csv(&table, &["ozone", "setting"], &path).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(text, "station_id,metric,value,algorithm,parameters\n\
+                      b,ozone,0.038,site_values,parameter=44201\n\
+                      b,setting,URBAN,aqs_sites,\n");
+}
+
+#[test]
+fn test_correlation_and_gaps_on_any_metric_pair() {
+    let mut network = MonitoringNetwork::new();
+    for (id, lat) in [("a", 35.0), ("b", 35.1), ("c", 35.3), ("d", 35.7)] {
+        network.add_station(create_test_station(id, lat));
+    }
+    network.build_adjacency_list();
+    network.calculate_isolation(1);
+    let isolation = network.metrics.metric(ISOLATION).unwrap();
+    assert_eq!(isolation.provenance, Provenance::new("mean_knn").with("k", 1));
+    assert!(network.isolation("d").unwrap() > network.isolation("c").unwrap());
+
+    network.metrics.insert_numbers("pm25", Provenance::new("site_values"),
+                                   &numbers(&[("a", 8.0), ("b", 9.0), ("c", 11.0), ("d", 14.0)]));
+    network.metrics.insert_numbers("ozone", Provenance::new("site_values"),
+                                   &numbers(&[("a", 0.05), ("b", 0.04), ("c", 0.03)]));
+
+    //the same results as the isolation-specific methods when one side is isolation
+    let pollution = network.metrics.numbers("pm25");
+    let correlation = network.metric_correlation(ISOLATION, "pm25").unwrap();
+    assert!(correlation > 0.5 && (correlation - network.analyze_correlation(&pollution)).abs() < 1e-9);
+    assert!(network.metric_correlation("pm25", "ozone").unwrap() < -0.9);
+    //no stations in common
+    network.metrics.insert_numbers("empty", Provenance::new("manual"), &HashMap::new());
+    assert_eq!(network.metric_correlation("pm25", "empty"), None);
+
+    let gaps = network.find_metric_gaps("pm25", "ozone", 8.5, 0.025);
+    let ids: Vec<&str> = gaps.iter().map(|(station, _, _)| station.id.as_str()).collect();
+    assert_eq!(ids, vec!["c", "b"]);
+    assert_eq!((gaps[0].1, gaps[0].2), (11.0, 0.03));
+    let isolation_gaps = network.find_metric_gaps(ISOLATION, "pm25", 0.0, 10.0);
+    let legacy = network.find_monitoring_gaps(&pollution, 0.0, 10.0);
+    assert_eq!(isolation_gaps.len(), legacy.len());
+    assert_eq!(isolation_gaps[0].0.id, "d");
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use aqs_analysis::monitor::{aggregate_site, aggregate_sites, apply_completeness_filter, AggregationRule,
+                            event_contrast, CompletenessFilter, EventSelection, EventType,
+                            ExclusionReason, Monitor, SiteAggregation};
+
+fn create_test_monitor(poc: u32, standard: &str, mean: f64) -> Monitor {
+    Monitor {
+        site_id: "01-001-0001".to_string(),
+        parameter_code: "88101".to_string(),
+        parameter_name: "PM2.5 - Local Conditions".to_string(),
+        poc,
+        method_code: Some("145".to_string()),
+        method_name: None,
+        sample_duration: "24-HR BLK AVG".to_string(),
+        pollutant_standard: standard.to_string(),
+        event_type: EventType::NoEvents,
+        observation_count: Some(120),
+        observation_percent: Some(100.0),
+        complete: Some(true),
+        arithmetic_mean: Some(mean),
+    }
+}
+
+fn collocated_monitors() -> Vec<Monitor> {
+    vec![
+        create_test_monitor(3, "PM25 24-hour 2012", 9.0),
+        create_test_monitor(3, "PM25 Annual 2012", 8.0),
+        create_test_monitor(1, "PM25 24-hour 2012", 12.5),
+        create_test_monitor(1, "PM25 Annual 2012", 12.0),
+    ]
+}
+
+#[test]
+fn test_aggregation_rules() {
+    let monitors = collocated_monitors();
+    let mut aggregation = SiteAggregation::pm25();
+
+    // Primary monitor is the lowest POC, using its annual standard record
+    assert_eq!(aggregate_site(&monitors, &aggregation), Some(12.0));
+
+    aggregation.rule = AggregationRule::Max;
+    assert_eq!(aggregate_site(&monitors, &aggregation), Some(12.0));
+
+    aggregation.rule = AggregationRule::Mean;
+    assert_eq!(aggregate_site(&monitors, &aggregation), Some(10.0));
+}
+
+#[test]
+fn test_aggregation_skips_other_parameters() {
+    let mut ozone = create_test_monitor(1, "Ozone 8-hour 2015", 0.04);
+    ozone.parameter_code = "44201".to_string();
+    ozone.sample_duration = "8-HR RUN AVG BEGIN HOUR".to_string();
+
+    let mut sites = HashMap::new();
+    sites.insert("01-001-0001".to_string(), collocated_monitors());
+    sites.insert("01-001-0002".to_string(), vec![ozone]);
+
+    let values = aggregate_sites(&sites, &SiteAggregation::pm25());
+    assert_eq!(values.len(), 1);
+    assert_eq!(values["01-001-0001"], 12.0);
+}
+
+#[test]
+fn test_completeness_filter_statistics() {
+    let mut sparse = create_test_monitor(1, "PM25 Annual 2012", 20.0);
+    sparse.site_id = "01-001-0002".to_string();
+    sparse.observation_count = Some(3);
+    sparse.observation_percent = Some(2.0);
+    sparse.complete = Some(false);
+
+    let mut partial = create_test_monitor(1, "PM25 Annual 2012", 15.0);
+    partial.site_id = "01-001-0003".to_string();
+    partial.observation_percent = Some(60.0);
+
+    let mut sites = HashMap::new();
+    sites.insert("01-001-0001".to_string(), collocated_monitors());
+    sites.insert("01-001-0002".to_string(), vec![sparse]);
+    sites.insert("01-001-0003".to_string(), vec![partial]);
+
+    let (kept, statistics) = apply_completeness_filter(&sites, &CompletenessFilter::epa_standard());
+
+    assert_eq!(kept.len(), 1);
+    assert_eq!(kept["01-001-0001"].len(), 4);
+
+    assert_eq!(statistics.len(), 1);
+    let stats = &statistics[0];
+    assert_eq!(stats.total_monitors, 4);
+    assert_eq!(stats.included_monitors, 2);
+    assert_eq!(stats.excluded_monitors(), 2);
+    assert_eq!(stats.excluded[&ExclusionReason::NotComplete], 1);
+    assert_eq!(stats.excluded[&ExclusionReason::LowObservationPercent], 1);
+
+    //records without a completeness indicator are not dropped for it
+    let mut unknown = create_test_monitor(1, "PM25 Annual 2012", 12.0);
+    unknown.complete = None;
+    assert_eq!(CompletenessFilter::epa_standard().check(&unknown), None);
+}
+
+#[test]
+fn test_event_selection_and_contrast() {
+    let mut included = create_test_monitor(1, "PM25 Annual 2012", 14.0);
+    included.event_type = EventType::EventsIncluded;
+    let mut excluded = create_test_monitor(1, "PM25 Annual 2012", 9.5);
+    excluded.event_type = EventType::EventsExcluded;
+    let mut concurred = create_test_monitor(1, "PM25 Annual 2012", 10.5);
+    concurred.event_type = EventType::ConcurredEventsExcluded;
+
+    let mut sites = HashMap::new();
+    sites.insert("01-001-0001".to_string(), vec![included, excluded, concurred]);
+    sites.insert("01-001-0002".to_string(), collocated_monitors());
+
+    let mut aggregation = SiteAggregation::pm25();
+    assert_eq!(aggregate_site(&sites["01-001-0001"], &aggregation), Some(14.0));
+    aggregation.events = EventSelection::ExcludeConcurredEvents;
+    assert_eq!(aggregate_site(&sites["01-001-0001"], &aggregation), Some(10.5));
+    // Sites without events are unaffected by the selection
+    assert_eq!(aggregate_site(&sites["01-001-0002"], &aggregation), Some(12.0));
+
+    let contrasts = event_contrast(&sites, &SiteAggregation::pm25());
+    assert_eq!(contrasts.len(), 1);
+    assert_eq!(contrasts[0].site_id, "01-001-0001");
+    assert!((contrasts[0].difference() - 4.5).abs() < 1e-9);
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use aqs_analysis::monitor::{EventType, Monitor};
+use aqs_analysis::network::{MonitoringNetwork, StationQuery};
+use aqs_analysis::spatial;
+use aqs_analysis::station::{LocationSetting, Station};
+
+mod common;
+
+fn create_test_station(id: &str, lat: f64, lon: f64, land_use: &str, setting: &str) -> Station {
+    Station { land_use: land_use.into(), location_setting: setting.into(), ..common::station(id, lat, lon) }
+}
+
+fn create_test_monitor(site_id: &str, parameter_code: &str) -> Monitor {
+    Monitor {
+        site_id: site_id.to_string(),
+        parameter_code: parameter_code.to_string(),
+        parameter_name: String::new(),
+        poc: 1,
+        method_code: None,
+        method_name: None,
+        sample_duration: "24-HR BLK AVG".to_string(),
+        pollutant_standard: String::new(),
+        event_type: EventType::NoEvents,
+        observation_count: None,
+        observation_percent: None,
+        complete: Some(true),
+        arithmetic_mean: Some(8.0),
+    }
+}
+
+fn create_test_network() -> MonitoringNetwork {
+    let mut network = MonitoringNetwork::new();
+    network.add_station(create_test_station("north", 40.5, -74.0, "RESIDENTIAL", "URBAN"));
+    network.add_station(create_test_station("east", 40.0, -73.4, "INDUSTRIAL", "SUBURBAN"));
+    network.add_station(create_test_station("south", 39.2, -74.0, "AGRICULTURAL", "RURAL"));
+    let mut monitors = HashMap::new();
+    monitors.insert("east".to_string(), vec![create_test_monitor("east", "88101")]);
+    monitors.insert("south".to_string(), vec![create_test_monitor("south", "44201")]);
+    network.attach_monitors(monitors);
+    network
+}
+
+#[test]
+fn test_nearest_stations_distance_and_bearing() {
+    let network = create_test_network();
+    let nearest = network.nearest_stations(40.0, -74.0, 2, &StationQuery::default());
+
+    let ids: Vec<&str> = nearest.iter().map(|n| n.station.id.as_str()).collect();
+    assert_eq!(ids, vec!["east", "north"]);
+    assert!((nearest[1].distance_km - 55.6).abs() < 0.5);
+    assert!(nearest[1].bearing_degrees.abs() < 0.01);
+    assert_eq!(nearest[1].compass_direction(), "N");
+    assert!((nearest[0].bearing_degrees - 90.0).abs() < 1.0);
+    assert_eq!(spatial::compass_direction(182.0), "S");
+}
+
+#[test]
+fn test_point_query_filters() {
+    let network = create_test_network();
+
+    let within = network.stations_within(40.0, -74.0, 100.0, &StationQuery::default());
+    assert_eq!(within.len(), 3);
+
+    let pm25 = StationQuery { parameter_code: Some("88101".to_string()), ..StationQuery::default() };
+    let found = network.nearest_stations(40.0, -74.0, 5, &pm25);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].station.id, "east");
+
+    let rural = StationQuery { location_setting: Some(LocationSetting::Rural), ..StationQuery::default() };
+    let found = network.stations_within(40.0, -74.0, 60.0, &rural);
+    assert!(found.is_empty());
+    let found = network.stations_within(40.0, -74.0, 100.0, &rural);
+    assert_eq!(found[0].station.id, "south");
+    assert!((found[0].bearing_degrees - 180.0).abs() < 0.01);
+    //the only match is outside the nearest grid cells
+    let found = network.nearest_stations(40.0, -74.0, 1, &rural);
+    assert_eq!(found[0].station.id, "south");
+}
+
+#[test]
+fn test_point_queries_see_added_stations() {
+    let mut network = create_test_network();
+    assert_eq!(network.nearest_stations(40.0, -74.0, 1, &StationQuery::default())[0].station.id, "east");
+    network.add_station(create_test_station("center", 40.01, -74.0, "RESIDENTIAL", "URBAN"));
+    assert_eq!(network.nearest_stations(40.0, -74.0, 1, &StationQuery::default())[0].station.id, "center");
+    assert_eq!(network.stations_within(40.0, -74.0, 100.0, &StationQuery::default()).len(), 4);
+}
+
+#[test]
+fn test_point_queries_across_the_antimeridian() {
+    let mut network = MonitoringNetwork::new();
+    network.add_station(create_test_station("adak", 51.88, -176.65, "RESIDENTIAL", "RURAL"));
+    network.add_station(create_test_station("attu", 51.9, 173.0, "RESIDENTIAL", "RURAL"));
+
+    //adak is about 260 km east across the antimeridian, attu about 450 km west
+    let nearest = network.nearest_stations(51.9, 179.5, 1, &StationQuery::default());
+    assert_eq!(nearest[0].station.id, "adak");
+    assert!((nearest[0].distance_km - 262.0).abs() < 5.0);
+    assert_eq!(nearest[0].compass_direction(), "E");
+    let within = network.stations_within(51.9, 179.5, 300.0, &StationQuery::default());
+    assert_eq!(within.len(), 1);
+
+    //longitudes computed along a great circle stay in [-180, 180)
+    let (lat, lon) = spatial::intermediate_point(0.0, 179.0, 0.0, -179.0, 0.5);
+    assert!(lat.abs() < 1e-9 && lon == -180.0);
+    assert_eq!(spatial::normalize_longitude(190.0), -170.0);
+}
@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::population::{coverage_by_state, coverage_statistics, nearest_monitor_distances, read_population_csv,
+                               read_population_geojson, PopulationCentroid};
+use aqs_analysis::spatial::SpatialIndex;
+use aqs_analysis::station::Station;
+
+mod common;
+
+fn create_test_station(id: &str, state_code: &str, state_name: &str, lat: f64, lon: f64) -> Station {
+    Station {
+        state_code: state_code.to_string(),
+        state_name: state_name.to_string(),
+        ..common::station(id, lat, lon)
+    }
+}
+
+fn centroid(geoid: &str, population: f64, lat: f64, lon: f64) -> PopulationCentroid {
+    PopulationCentroid {
+        geoid: geoid.to_string(),
+        state_code: geoid[..2].to_string(),
+        population,
+        latitude: lat,
+        longitude: lon,
+    }
+}
+
+#[test]
+fn test_spatial_index_matches_brute_force() {
+    let mut network = MonitoringNetwork::new();
+    for i in 0..50 {
+        let lat = 30.0 + (i as f64 * 0.37) % 12.0;
+        let lon = -120.0 + (i as f64 * 1.13) % 30.0;
+        network.add_station(create_test_station(&format!("06-001-{:04}", i), "06", "California", lat, lon));
+    }
+    let index = SpatialIndex::from_stations(network.stations.values());
+
+    let (lat, lon) = (36.2, -101.7);
+    let mut brute: Vec<(String, f64)> = network.stations.values()
+        .map(|s| (s.id.clone(), network.haversine_distance(lat, lon, s.latitude, s.longitude)))
+        .collect();
+    brute.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+    let nearest = index.nearest(lat, lon, 5);
+    assert_eq!(nearest.len(), 5);
+    for (found, expected) in nearest.iter().zip(brute.iter()) {
+        assert_eq!(found.0, expected.0);
+    }
+
+    let within = index.within(lat, lon, 300.0);
+    assert_eq!(within.len(), brute.iter().filter(|(_, d)| *d <= 300.0).count());
+}
+
+#[test]
+fn test_population_weighted_coverage() {
+    let mut network = MonitoringNetwork::new();
+    network.add_station(create_test_station("01-001-0001", "01", "Alabama", 33.5, -86.8));
+    network.add_station(create_test_station("01-001-0002", "01", "Alabama", 31.0, -88.0));
+    network.add_station(create_test_station("13-001-0001", "13", "Georgia", 33.7, -84.4));
+
+    let centroids = vec![
+        // About 11 km from the Birmingham station
+        centroid("01073000100", 3000.0, 33.6, -86.8),
+        // About 78 km from the Birmingham station
+        centroid("01117000100", 1000.0, 33.5, -87.64),
+        // Next to the Atlanta station
+        centroid("13121000100", 6000.0, 33.7, -84.41),
+    ];
+
+    // Only the first Alabama site and the Georgia site count as monitors
+    let monitors: HashSet<String> = ["01-001-0001", "13-001-0001"].iter().map(|s| s.to_string()).collect();
+    let coverage = nearest_monitor_distances(&centroids, &network, Some(&monitors));
+    assert_eq!(coverage[1].nearest_station.as_deref(), Some("01-001-0001"));
+
+    let national = coverage_statistics("United States", &coverage, &[25.0, 100.0]);
+    assert_eq!(national.total_population, 10000.0);
+    assert!((national.percent_within[0].1 - 90.0).abs() < 1e-9);
+    assert!((national.percent_within[1].1 - 100.0).abs() < 1e-9);
+
+    let states = coverage_by_state(&coverage, &network, &[25.0]);
+    assert_eq!(states.len(), 2);
+    assert_eq!(states[0].region, "Alabama");
+    assert!((states[0].percent_within[0].1 - 75.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_read_population_malformed_input() {
+    let csv_path = std::env::temp_dir().join("aqs_test_centroids.csv");
+    std::fs::write(&csv_path, "STATEFP,COUNTYFP,TRACTCE,POPULATION,LATITUDE,LONGITUDE\n\
+                               01,001,020100,1912,32.48,-86.49\n\
+                               01,001,020200,,32.47,-86.47\n\
+                               01,001,020300,3373,north,-86.46\n").unwrap();
+    let centroids = read_population_csv(&csv_path).unwrap();
+    std::fs::remove_file(&csv_path).unwrap();
+    //rows with a missing population or a non-numeric coordinate are skipped
+    assert_eq!(centroids.len(), 1);
+    assert_eq!(centroids[0].geoid, "01001020100");
+
+    let json_path = std::env::temp_dir().join("aqs_test_centroids.geojson");
+    std::fs::write(&json_path, r#"{"type": "FeatureCollection", "features": [
+        {"properties": {"GEOID": "01001020100", "POPULATION": 1912}, "geometry": {"type": "Point", "coordinates": [-86.49, 32.48]}},
+        {"properties": {"GEOID": "01001020200"}, "geometry": {"type": "Point", "coordinates": [-86.47, 32.47]}},
+        {"properties": {"GEOID": "01001020300", "POPULATION": 3373}, "geometry": null}]}"#).unwrap();
+    let centroids = read_population_geojson(&json_path).unwrap();
+    assert_eq!(centroids.len(), 1);
+    //not a feature collection, or not json at all
+    std::fs::write(&json_path, r#"{"type": "Feature"}"#).unwrap();
+    assert!(read_population_geojson(&json_path).is_err());
+    std::fs::write(&json_path, "GEOID,POPULATION\n").unwrap();
+    assert!(read_population_geojson(&json_path).is_err());
+    std::fs::remove_file(&json_path).unwrap();
+}
@@ -0,0 +1,65 @@
+use std::collections::{HashMap, HashSet};
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::render::{self, BoundingBox, ColorScale, MapOptions, Projection, StationMetric};
+use aqs_analysis::station::Station;
+
+mod common;
+
+fn create_test_station(id: &str, state: &str, lat: f64, lon: f64) -> Station {
+    Station { state_code: state.to_string(), state_name: format!("State {}", state), ..common::station(id, lat, lon) }
+}
+
+#[test]
+fn test_projections() {
+    let (x, y) = Projection::AlbersConus.project(23.0, -96.0);
+    assert!(x.abs() < 1e-6 && y.abs() < 1e-6);
+    //equal-area: a point east of the central meridian has positive x, north has larger y
+    let (east, _) = Projection::AlbersConus.project(40.0, -80.0);
+    let (_, north) = Projection::AlbersConus.project(45.0, -96.0);
+    assert!(east > 1000.0 && north > 2000.0);
+
+    let (x, y) = Projection::WebMercator.project(0.0, 0.0);
+    assert!(x.abs() < 1e-9 && y.abs() < 1e-9);
+    let (_, top) = Projection::WebMercator.project(90.0, 0.0);
+    assert!(top.is_finite());
+
+    assert_eq!(ColorScale::Sequential.color(0.0, (0.0, 1.0)), "#440154");
+    assert_eq!(ColorScale::Diverging.color(0.0, (-2.0, 1.0)), "#f7f7f7");
+    assert_eq!(ColorScale::Categorical.color(11.0, (0.0, 11.0)), ColorScale::Categorical.color(1.0, (0.0, 1.0)));
+}
+
+#[test]
+fn test_render_svg_filters_edges_and_gaps() {
+    let mut network = MonitoringNetwork::new();
+    network.add_station(create_test_station("a", "01", 40.0, -74.0));
+    network.add_station(create_test_station("b", "01", 40.1, -74.1));
+    network.add_station(create_test_station("c", "02", 40.2, -74.2));
+    network.add_station(create_test_station("far", "02", 35.0, -100.0));
+    network.build_adjacency_list();
+    network.calculate_isolation(1);
+
+    let options = MapOptions {
+        metric: Some(StationMetric::isolation(&network)),
+        show_edges: true,
+        gaps: HashSet::from(["c".to_string()]),
+        bounding_box: Some(BoundingBox { min_lat: 39.0, max_lat: 41.0, min_lon: -75.0, max_lon: -73.0 }),
+        ..MapOptions::default()
+    };
+    let svg = render::render_svg(&network, &options);
+    assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+    //three stations in the box, each undirected edge once, one gap marker
+    assert_eq!(svg.matches("<title>").count(), 3);
+    assert_eq!(svg.matches("<line x1").count(), 3 + 1); //edges plus the legend entry
+    assert_eq!(svg.matches("class=\"gap\"").count(), 1);
+    assert!(svg.contains("Isolation (km)") && svg.contains("monitoring gap"));
+
+    let by_state = MapOptions {
+        metric: Some(StationMetric { label: "cluster".to_string(), values: HashMap::new(), scale: ColorScale::Categorical }),
+        states: Some(HashSet::from(["state 02".to_string()])),
+        legend: false,
+        ..MapOptions::default()
+    };
+    let svg = render::render_svg(&network, &by_state);
+    assert_eq!(svg.matches("<title>").count(), 2);
+    assert!(svg.contains("#bbbbbb"));
+}
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::report;
+use aqs_analysis::station::Station;
+
+mod common;
+
+fn create_test_station(id: &str, name: &str, lat: f64, lon: f64) -> Station {
+    Station { site_name: name.to_string(), ..common::station(id, lat, lon) }
+}
+
+#[test]
+fn test_render_html_report() {
+    let mut network = MonitoringNetwork::new();
+    network.add_station(create_test_station("1", "Station1", 40.0, -74.0));
+    network.add_station(create_test_station("2", "Station2", 40.1, -74.1));
+    network.add_station(create_test_station("3", "Station3", 40.2, -74.2));
+    network.add_station(create_test_station("5", "Station5", 40.3, -74.3));
+    network.add_station(create_test_station("4", "Remote <A&B>", 40.9, -74.9));
+    network.build_adjacency_list();
+    network.calculate_isolation(2);
+
+    let mut pollution = HashMap::new();
+    for (id, value) in [("1", 6.0), ("2", 7.0), ("3", 8.0), ("5", 9.0), ("4", 15.0)] {
+        pollution.insert(id.to_string(), value);
+    }
+    let html = report::render_html_report(&network, &pollution, "PM2.5 (µg/m³)");
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.trim_end().ends_with("</html>"));
+    //histogram, scatter plot and map
+    assert_eq!(html.matches("<svg").count(), 3);
+    //the remote station is the only gap and its name is escaped
+    assert!(html.contains("<table class=\"sortable\" id=\"gaps\">"));
+    assert!(html.contains("<td>Remote &lt;A&amp;B&gt;</td>"));
+    assert_eq!(html.matches("<tbody>").count(), 1);
+    assert_eq!(html.split("<tbody>").nth(1).unwrap().matches("<tr>").count(), 1);
+    assert!(html.contains(&format!("r = {:.4}", network.analyze_correlation(&pollution))));
+}
@@ -0,0 +1,145 @@
+use aqs_analysis::monitor::{EventType, Monitor};
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::server::ApiServer;
+use aqs_analysis::station::Station;
+use serde_json::Value;
+
+mod common;
+
+fn create_test_station(id: &str, name: &str, lat: f64, lon: f64) -> Station {
+    Station { site_name: name.to_string(), ..common::station(id, lat, lon) }
+}
+
+fn create_test_monitor(site_id: &str, mean: f64) -> Monitor {
+    Monitor {
+        site_id: site_id.to_string(),
+        parameter_code: "88101".to_string(),
+        parameter_name: "PM2.5 - Local Conditions".to_string(),
+        poc: 1,
+        method_code: None,
+        method_name: None,
+        sample_duration: "24-HR BLK AVG".to_string(),
+        pollutant_standard: "PM25 Annual 2012".to_string(),
+        event_type: EventType::NoEvents,
+        observation_count: Some(120),
+        observation_percent: Some(100.0),
+        complete: Some(true),
+        arithmetic_mean: Some(mean),
+    }
+}
+
+fn create_test_server() -> ApiServer {
+    let mut network = MonitoringNetwork::new();
+    network.add_station(create_test_station("01-001-0001", "Station1", 40.0, -74.0));
+    network.add_station(create_test_station("01-001-0002", "Station2", 40.1, -74.1));
+    network.add_station(create_test_station("01-001-0003", "Station3", 40.2, -74.2));
+    network.add_station(create_test_station("01-001-0004", "Remote", 40.9, -74.9));
+    network.build_adjacency_list();
+    network.calculate_isolation(2);
+
+    let mut monitors = std::collections::HashMap::new();
+    for (id, mean) in [("01-001-0001", 6.0), ("01-001-0002", 7.0), ("01-001-0003", 8.0), ("01-001-0004", 15.0)] {
+        monitors.insert(id.to_string(), vec![create_test_monitor(id, mean)]);
+    }
+    network.attach_monitors(monitors);
+    ApiServer::new(network)
+}
+
+fn get_json(server: &ApiServer, url: &str) -> (u16, Value) {
+    let response = server.handle("GET", url);
+    (response.status, serde_json::from_str(&response.body).unwrap())
+}
+
+#[test]
+fn test_station_lookup() {
+    let server = create_test_server();
+    let (status, body) = get_json(&server, "/stations/01-001-0002");
+    assert_eq!(status, 200);
+    assert_eq!(body["site_name"], "Station2");
+
+    let (status, _) = get_json(&server, "/stations/99-999-9999");
+    assert_eq!(status, 404);
+    assert_eq!(server.handle("POST", "/isolation").status, 405);
+}
+
+#[test]
+fn test_point_queries() {
+    let server = create_test_server();
+    let (status, body) = get_json(&server, "/nearest?lat=40.01&lon=-74.0&k=2");
+    assert_eq!(status, 200);
+    let ids: Vec<&str> = body.as_array().unwrap().iter().map(|s| s["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, vec!["01-001-0001", "01-001-0002"]);
+
+    let (_, body) = get_json(&server, "/within?lat=40.1&lon=-74.1&radius_km=20");
+    assert_eq!(body.as_array().unwrap().len(), 3);
+
+    let (status, _) = get_json(&server, "/nearest?lat=abc&lon=-74.0");
+    assert_eq!(status, 400);
+}
+
+#[test]
+fn test_nearest_k_validation() {
+    let server = create_test_server();
+    for k in ["0", "-1", "1.5", "NaN", "abc", ""] {
+        let (status, body) = get_json(&server, &format!("/nearest?lat=40.0&lon=-74.0&k={}", k));
+        assert_eq!(status, 400, "k={}", k);
+        assert_eq!(body["error"], "k must be a positive integer");
+    }
+    //a huge k is capped rather than returning an error
+    let (status, body) = get_json(&server, "/nearest?lat=40.0&lon=-74.0&k=18446744073709551615");
+    assert_eq!(status, 200);
+    assert_eq!(body.as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn test_isolation_and_gaps() {
+    let server = create_test_server();
+    let (status, body) = get_json(&server, "/isolation");
+    assert_eq!(status, 200);
+    assert_eq!(body["count"], 4);
+
+    let (status, body) = get_json(&server, "/gaps?isolation_threshold=100&pollution_threshold=10");
+    assert_eq!(status, 200);
+    let gaps = body["gaps"].as_array().unwrap();
+    assert_eq!(gaps.len(), 1);
+    assert_eq!(gaps[0]["id"], "01-001-0004");
+    assert_eq!(gaps[0]["pollution"], 15.0);
+}
+
+#[test]
+fn test_bad_query_parameters() {
+    let server = create_test_server();
+    let bad = [
+        ("/within?lat=40.0&lon=-74.0", "radius_km must be a non-negative number"),
+        ("/within?lat=40.0&lon=-74.0&radius_km=-5", "radius_km must be a non-negative number"),
+        ("/within?lat=40.0&lon=-74.0&radius_km=far", "radius_km must be a number"),
+        ("/nearest?lat=91.0&lon=-74.0", "lat or lon out of range"),
+        ("/nearest?lat=40.0&lon=-181.0", "lat or lon out of range"),
+        ("/nearest?lat=40.0", "lat and lon are required"),
+        ("/gaps?isolation_threshold=high", "isolation_threshold must be a number"),
+        ("/gaps?pollution_threshold=", "pollution_threshold must be a number"),
+    ];
+    for (url, message) in bad {
+        let (status, body) = get_json(&server, url);
+        assert_eq!(status, 400, "{}", url);
+        assert_eq!(body["error"], message, "{}", url);
+    }
+    let (status, _) = get_json(&server, "/stations");
+    assert_eq!(status, 404);
+    let (status, _) = get_json(&server, "/gaps?parameter=99999");
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn test_empty_network() {
+    let server = ApiServer::new(MonitoringNetwork::new());
+    let (status, body) = get_json(&server, "/nearest?lat=40.0&lon=-74.0");
+    assert_eq!(status, 200);
+    assert!(body.as_array().unwrap().is_empty());
+    let (status, body) = get_json(&server, "/within?lat=40.0&lon=-74.0&radius_km=100");
+    assert_eq!(status, 200);
+    assert!(body.as_array().unwrap().is_empty());
+    for url in ["/isolation", "/gaps", "/stations/01-001-0001"] {
+        assert_eq!(get_json(&server, url).0, 404, "{}", url);
+    }
+}
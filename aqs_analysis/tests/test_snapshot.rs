@@ -0,0 +1,74 @@
+use std::io::Write;
+use chrono::NaiveDate;
+use aqs_analysis::data::{CoordinateCheck, LoadPolicy};
+use aqs_analysis::isolation::IsolationMetric;
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::snapshot::{load_snapshot, load_snapshot_json, save_snapshot, save_snapshot_json, SNAPSHOT_VERSION};
+
+mod common;
+
+#[test]
+fn test_snapshot_round_trip() {
+    let dir = std::env::temp_dir();
+    let pid = std::process::id();
+    let source = dir.join(format!("aqs_analysis_snapshot_source_{}.csv", pid));
+    let binary = dir.join(format!("aqs_analysis_snapshot_{}.bin", pid));
+    let json = dir.join(format!("aqs_analysis_snapshot_{}.json", pid));
+    std::fs::write(&source, "State Code,County Code\n01,001\n").unwrap();
+
+    let mut network = MonitoringNetwork::new();
+    network.add_station(common::station("01-001-0001", 40.0, -74.0));
+    network.add_station(common::station("01-001-0002", 40.1, -74.1));
+    network.add_station(common::station("01-001-0003", 40.2, -74.2));
+    network.build_adjacency_list();
+    network.calculate_isolation(2);
+    network.calculate_isolation_metric(&IsolationMetric::NearestNeighbor);
+
+    let policy = LoadPolicy { closed_as_of: NaiveDate::from_ymd_opt(2023, 1, 1), ..LoadPolicy::default() };
+    save_snapshot(&network, &policy, &[&source], &binary).unwrap();
+    save_snapshot_json(&network, &policy, &[&source], &json).unwrap();
+
+    for loaded in [load_snapshot(&binary).unwrap(), load_snapshot_json(&json).unwrap()] {
+        assert!(loaded.is_current(&policy));
+        assert_eq!(loaded.policy, policy);
+        assert_eq!(loaded.network.stations.len(), 3);
+        assert_eq!(loaded.network.adjacency_list["01-001-0002"].len(), 2);
+        assert_eq!(loaded.network.isolation("01-001-0002"), network.isolation("01-001-0002"));
+        assert_eq!(loaded.network.metrics, network.metrics);
+    }
+
+    // A different load policy makes the snapshot stale
+    let us_only = LoadPolicy { coordinates: CoordinateCheck::UsTerritory, ..policy.clone() };
+    assert!(!load_snapshot(&binary).unwrap().is_current(&us_only));
+    assert!(!load_snapshot_json(&json).unwrap().is_current(&LoadPolicy::default()));
+
+    // Changing the source file makes the snapshot stale
+    std::fs::OpenOptions::new().append(true).open(&source).unwrap().write_all(b"01,003\n").unwrap();
+    assert!(!load_snapshot(&binary).unwrap().is_current(&policy));
+
+    // Files that are not snapshots are rejected
+    assert!(load_snapshot(&source).is_err());
+
+    for path in [&source, &binary, &json] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_snapshot_rejects_malformed_files() {
+    let path = std::env::temp_dir().join(format!("aqs_analysis_snapshot_bad_{}", std::process::id()));
+    // Truncated after the magic bytes, or written by another snapshot version
+    std::fs::write(&path, b"AQSN\x06").unwrap();
+    assert!(load_snapshot(&path).is_err());
+    std::fs::write(&path, [b"AQSN".as_slice(), &1u32.to_le_bytes(), &[0u8; 16]].concat()).unwrap();
+    assert!(load_snapshot(&path).is_err_and(|err| err.to_string().contains("version 1")));
+    std::fs::write(&path, [b"AQSN".as_slice(), &SNAPSHOT_VERSION.to_le_bytes(), &[0xff; 16]].concat()).unwrap();
+    assert!(load_snapshot(&path).is_err());
+
+    // Json that is cut off or from another version
+    std::fs::write(&path, r#"{"version": 6, "policy": "#).unwrap();
+    assert!(load_snapshot_json(&path).is_err());
+    std::fs::write(&path, r#"{"version": 1}"#).unwrap();
+    assert!(load_snapshot_json(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use aqs_analysis::isolation::IsolationMetric;
+
+#[cfg(feature = "sqlite")]
+mod common;
+
+// Metric parameters are stored as a json object in the metrics table, this runs without the sqlite feature
+#[test]
+fn test_metric_parameters_json() {
+    for metric in aqs_analysis::isolation::standard_metrics(10) {
+        let provenance = metric.provenance();
+        let stored = serde_json::to_string(&provenance.parameters).unwrap();
+        let parameters: BTreeMap<String, String> = serde_json::from_str(&stored).unwrap();
+        assert_eq!(parameters, provenance.parameters, "{}", stored);
+    }
+    assert_eq!(serde_json::to_string(&IsolationMetric::KernelSparsity { bandwidth_km: 12.5 }.provenance().parameters).unwrap(),
+               r#"{"bandwidth_km":"12.5"}"#);
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::collections::HashMap;
+    use aqs_analysis::isolation::IsolationMetric;
+    use aqs_analysis::metrics::{MetricValue, Provenance};
+    use aqs_analysis::monitor::{EventType, Monitor, PM25_PARAMETER};
+    use aqs_analysis::network::MonitoringNetwork;
+    use aqs_analysis::storage;
+    use super::common;
+
+    fn create_test_monitor(site_id: &str, mean: f64) -> Monitor {
+        Monitor {
+            site_id: site_id.to_string(),
+            parameter_code: PM25_PARAMETER.to_string(),
+            parameter_name: "PM2.5 - Local Conditions".to_string(),
+            poc: 1,
+            method_code: None,
+            method_name: None,
+            sample_duration: "24-HR BLK AVG".to_string(),
+            pollutant_standard: "PM25 Annual 2012".to_string(),
+            event_type: EventType::EventsIncluded,
+            observation_count: Some(120),
+            observation_percent: Some(98.0),
+            complete: Some(true),
+            arithmetic_mean: Some(mean),
+        }
+    }
+
+    #[test]
+    fn test_sqlite_round_trip() {
+        let mut conn = storage::open_database(":memory:").unwrap();
+
+        let mut network = MonitoringNetwork::new();
+        network.add_station(common::station("01-001-0001", 40.0, -74.0));
+        network.add_station(common::station("01-001-0002", 40.1, -74.1));
+        network.add_station(common::station("01-001-0003", 40.2, -74.2));
+        network.build_adjacency_list();
+        network.calculate_isolation(2);
+        network.calculate_isolation_metric(&IsolationMetric::NearestNeighbor);
+        network.metrics.insert("setting", Provenance::new("aqs_sites").with("column", "Location Setting"), HashMap::from([
+            ("01-001-0001".to_string(), MetricValue::Category("URBAN".to_string())),
+            ("01-001-0002".to_string(), MetricValue::Flag(true)),
+        ]));
+        storage::save_network(&mut conn, &network).unwrap();
+
+        let mut monitors = HashMap::new();
+        monitors.insert("01-001-0001".to_string(), vec![create_test_monitor("01-001-0001", 9.5)]);
+        storage::save_monitors(&mut conn, 2023, &monitors).unwrap();
+        // Loading the same year again replaces it instead of duplicating rows
+        storage::save_monitors(&mut conn, 2023, &monitors).unwrap();
+
+        let loaded = storage::load_network(&conn, Some(2023)).unwrap();
+        assert_eq!(loaded.stations.len(), 3);
+        assert_eq!(loaded.adjacency_list["01-001-0001"], network.adjacency_list["01-001-0001"]);
+        assert_eq!(loaded.isolation("01-001-0003"), network.isolation("01-001-0003"));
+        assert_eq!(loaded.monitors["01-001-0001"], monitors["01-001-0001"]);
+        assert_eq!(loaded.metrics, network.metrics);
+    }
+
+    #[test]
+    fn test_sqlite_pollution_years() {
+        let mut conn = storage::open_database(":memory:").unwrap();
+        for year in 2020..=2023 {
+            let mut values = HashMap::new();
+            values.insert("01-001-0001".to_string(), 10.0 - (year - 2020) as f64);
+            storage::save_pollution(&mut conn, year, PM25_PARAMETER, &values).unwrap();
+        }
+
+        let yearly = storage::load_pollution_years(&conn, PM25_PARAMETER).unwrap();
+        assert_eq!(yearly.len(), 4);
+        assert_eq!(yearly[&2023]["01-001-0001"], 7.0);
+    }
+}
@@ -0,0 +1,103 @@
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::station::{LandUse, LocationSetting, Station};
+use aqs_analysis::terrain::{self, TerrainPenalty};
+
+mod common;
+
+fn create_test_station(id: &str, lat: f64, lon: f64) -> Station {
+    Station {
+        state_code: "06".to_string(),
+        land_use: LandUse::Forest,
+        location_setting: LocationSetting::Rural,
+        state_name: "California".to_string(),
+        ..common::station(id, lat, lon)
+    }
+}
+
+//20 x 5 grid of 0.1 degree cells from -120 to -118, 35 to 35.5, with a 2000 m ridge along -119.0 to -118.9
+fn write_test_grid(name: &str) -> std::path::PathBuf {
+    let mut text = String::from("ncols 20\nnrows 5\nxllcorner -120.0\nyllcorner 35.0\ncellsize 0.1\nNODATA_value -9999\n");
+    for row in 0..5 {
+        let values: Vec<&str> = (0..20)
+            .map(|col| if col == 10 { "2000" } else if row == 0 && col == 0 { "-9999" } else { "500" })
+            .collect();
+        text.push_str(&values.join(" "));
+        text.push('\n');
+    }
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn test_ascii_grid_and_ridges() {
+    let path = write_test_grid("aqs_test_grid.asc");
+    let grid = terrain::read_ascii_grid(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!((grid.ncols, grid.nrows), (20, 5));
+    assert_eq!(grid.elevation(35.25, -118.95), Some(2000.0));
+    assert_eq!(grid.elevation(35.25, -119.5), Some(500.0));
+    //top-left cell is nodata, points outside the grid have no elevation
+    assert_eq!(grid.elevation(35.45, -119.95), None);
+    assert_eq!(grid.elevation(36.0, -119.5), None);
+
+    //up and back down counts, a climb onto a plateau or a small bump does not
+    assert_eq!(terrain::ridge_crossings(&[500.0, 1200.0, 2000.0, 900.0, 500.0], 300.0), 1);
+    assert_eq!(terrain::ridge_crossings(&[500.0, 1500.0, 400.0, 1500.0, 400.0], 300.0), 2);
+    assert_eq!(terrain::ridge_crossings(&[500.0, 1500.0, 1400.0, 1500.0], 300.0), 0);
+    assert_eq!(terrain::ridge_crossings(&[500.0, 700.0, 500.0], 300.0), 0);
+
+    let penalty = TerrainPenalty::default();
+    assert_eq!(penalty.ridges(&grid, 35.25, -119.5, 35.25, -118.5).unwrap(), 1);
+    assert_eq!(penalty.ridges(&grid, 35.25, -119.5, 35.25, -119.2).unwrap(), 0);
+    //a sample spacing that is not positive is rejected instead of sampling forever
+    for step_km in [0.0, -1.0, f64::NAN] {
+        assert!(grid.profile(35.25, -119.5, 35.25, -118.5, step_km).is_err());
+    }
+}
+
+#[test]
+fn test_terrain_penalty_in_adjacency() {
+    let path = write_test_grid("aqs_test_grid_network.asc");
+    let grid = terrain::read_ascii_grid(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    //the east station is closer to the center but on the other side of the ridge
+    let mut network = MonitoringNetwork::new();
+    network.add_station(create_test_station("center", 35.25, -119.2));
+    network.add_station(create_test_station("west", 35.25, -119.7));
+    network.add_station(create_test_station("east", 35.25, -118.75));
+    network.build_adjacency_list();
+    assert_eq!(network.adjacency_list["center"][0].0, "east");
+    let plain: f64 = network.adjacency_list["center"][0].1;
+
+    let zero_step = TerrainPenalty { step_km: 0.0, ..TerrainPenalty::default() };
+    assert!(network.apply_terrain_penalty(&grid, &zero_step).is_err());
+    let separated = network.apply_terrain_penalty(&grid, &TerrainPenalty::default()).unwrap();
+    assert_eq!(separated, 2); //center-east and west-east
+    assert_eq!(network.adjacency_list["center"][0].0, "west");
+    let east = network.adjacency_list["center"].iter().find(|(id, _)| id == "east").unwrap().1;
+    assert!((east - plain * 2.0).abs() < 1e-9);
+    //penalties are symmetric
+    let back = network.adjacency_list["east"].iter().find(|(id, _)| id == "center").unwrap().1;
+    assert!((back - east).abs() < 1e-9);
+}
+
+#[test]
+fn test_read_ascii_grid_rejects_malformed_files() {
+    let path = std::env::temp_dir().join("aqs_test_bad_grid.asc");
+    let header = "ncols 2\nnrows 2\nxllcorner -120.0\nyllcorner 35.0\ncellsize 0.1\n";
+    let malformed = [
+        format!("{}500 500 500\n", header), //too few values
+        format!("{}500 500 high 500\n", header), //non-numeric elevation
+        "ncols 2\nnrows 2\ncellsize 0.1\n500 500 500 500\n".to_string(), //no corner
+        format!("{}rotation 15\n500 500 500 500\n", header), //unknown header
+        "ncols\n".to_string(), //missing header value
+    ];
+    for text in malformed {
+        std::fs::write(&path, &text).unwrap();
+        assert!(terrain::read_ascii_grid(&path).is_err(), "{}", text);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,63 @@
+use std::collections::{BTreeMap, HashMap};
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::trend::mann_kendall;
+
+mod common;
+
+#[test]
+fn test_mann_kendall_increasing_series() {
+    let series: Vec<(i32, f64)> = (2013..=2023)
+        .map(|year| (year, 5.0 + 0.5 * (year - 2013) as f64))
+        .collect();
+    let result = mann_kendall(&series).unwrap();
+
+    // Strictly increasing series of 11 points: S = 11 * 10 / 2 = 55
+    assert_eq!(result.s, 55);
+    assert!((result.sens_slope - 0.5).abs() < 1e-9, "Sen's slope error: {}", result.sens_slope);
+    assert!(result.p_value < 0.001, "Expected significant trend, p = {}", result.p_value);
+    assert!(result.is_increasing(0.05));
+}
+
+#[test]
+fn test_mann_kendall_needs_three_years() {
+    assert!(mann_kendall(&[(2022, 8.0), (2023, 9.0)]).is_none());
+
+    // A flat series has no trend at all
+    let flat = mann_kendall(&[(2021, 8.0), (2022, 8.0), (2023, 8.0)]).unwrap();
+    assert_eq!(flat.s, 0);
+    assert_eq!(flat.sens_slope, 0.0);
+    assert!(!flat.is_significant(0.05));
+}
+
+#[test]
+fn test_mann_kendall_averages_repeated_years() {
+    //2022 appears twice and counts once, at the mean of its values
+    let result = mann_kendall(&[(2021, 8.0), (2022, 7.0), (2022, 11.0), (2023, 10.0)]).unwrap();
+    assert_eq!((result.n, result.s), (3, 3));
+    assert!((result.sens_slope - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_calculate_trends_on_network() {
+    let mut network = MonitoringNetwork::new();
+    network.add_station(common::station("01-001-0001", 40.0, -74.0));
+    network.add_station(common::station("01-001-0002", 40.1, -74.1));
+
+    let mut yearly = BTreeMap::new();
+    for year in 2015..=2023 {
+        let mut data = HashMap::new();
+        data.insert("01-001-0001".to_string(), 10.0 - 0.3 * (year - 2015) as f64);
+        // Second station only reported in 2023, so it gets no trend
+        if year == 2023 {
+            data.insert("01-001-0002".to_string(), 12.0);
+        }
+        yearly.insert(year, data);
+    }
+
+    network.calculate_trends(&yearly);
+
+    assert_eq!(network.trends.len(), 1);
+    let trend = &network.trends["01-001-0001"];
+    assert!(trend.is_decreasing(0.05));
+    assert!((trend.sens_slope + 0.3).abs() < 1e-9);
+}
@@ -0,0 +1,79 @@
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::wind::{self, WindRose, WindRoses, WindWeighting};
+
+mod common;
+
+#[test]
+fn test_wind_weighting() {
+    //a center station with neighbors at the same distance to the north, east and south
+    let mut network = MonitoringNetwork::new();
+    network.add_station(common::station("center", 35.0, -86.0));
+    network.add_station(common::station("north", 35.5, -86.0));
+    network.add_station(common::station("east", 35.0, -85.39));
+    network.add_station(common::station("south", 34.5, -86.0));
+    network.build_adjacency_list();
+    let distance_of = |network: &MonitoringNetwork, id: &str| {
+        network.adjacency_list["center"].iter().find(|(neighbor, _)| neighbor == id).unwrap().1
+    };
+    let before: Vec<f64> = ["north", "east", "south"].iter().map(|id| distance_of(&network, id)).collect();
+
+    //wind always from the north, only the center station has a rose
+    let mut roses = WindRoses::default();
+    roses.stations.insert("center".to_string(), WindRose::prevailing(0.0));
+    let weighted = network.apply_wind_weighting(&roses, &WindWeighting::default());
+    assert_eq!(weighted, 1);
+
+    //upwind halves the distance, crosswind keeps it, downwind shortens it less
+    assert!((distance_of(&network, "north") - before[0] / 2.0).abs() < 1e-9);
+    assert!((distance_of(&network, "east") - before[1]).abs() < 0.01 * before[1]);
+    assert!((distance_of(&network, "south") - before[2] / 1.5).abs() < 1e-9);
+    assert_eq!(network.adjacency_list["center"][0].0, "north");
+    //stations without a rose keep plain distances
+    let north_to_center = network.adjacency_list["north"].iter().find(|(id, _)| id == "center").unwrap().1;
+    assert!((north_to_center - before[0]).abs() < 1e-9);
+
+    network.calculate_isolation(1);
+    assert!((network.isolation("center").unwrap() - before[0] / 2.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_read_wind_roses() {
+    let path = std::env::temp_dir().join("aqs_test_wind_roses.csv");
+    std::fs::write(&path, "Site ID,Latitude,Longitude,Direction,Frequency\n\
+                           01-001-0001,,,0,3\n\
+                           01-001-0001,,,180,1\n\
+                           ,40.0,-100.0,270,10\n\
+                           ,,,90,1\n").unwrap();
+    let roses = wind::read_wind_roses(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(roses.stations.len(), 1);
+    assert_eq!(roses.stations["01-001-0001"].sectors(), &[(0.0, 0.75), (180.0, 0.25)]);
+    let (upwind, downwind) = roses.stations["01-001-0001"].alignment(0.0);
+    assert!((upwind - 0.75).abs() < 1e-9 && (downwind - 0.25).abs() < 1e-9);
+
+    //a station without its own rose falls back to the nearest grid point within range
+    assert_eq!(roses.grid.len(), 1);
+    let nearby = common::station("near", 40.5, -100.0);
+    let distant = common::station("far", 45.0, -100.0);
+    assert_eq!(roses.rose_for(&nearby, 150.0), Some(&WindRose::prevailing(270.0)));
+    assert_eq!(roses.rose_for(&distant, 150.0), None);
+}
+
+#[test]
+fn test_read_wind_roses_skips_malformed_rows() {
+    let path = std::env::temp_dir().join("aqs_test_bad_wind_roses.csv");
+    std::fs::write(&path, "Site ID,Latitude,Longitude,Direction,Frequency\n\
+                           01-001-0001,,,north,3\n\
+                           01-001-0002,,,90,\n\
+                           ,40.0,,270,10\n\
+                           01-001-0003,,,45,2\n").unwrap();
+    let roses = wind::read_wind_roses(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    //a non-numeric direction, a missing frequency and a grid point without a longitude are skipped
+    let ids: Vec<&String> = roses.stations.keys().collect();
+    assert_eq!(ids, vec!["01-001-0003"]);
+    assert!(roses.grid.is_empty());
+    assert!(wind::read_wind_roses(std::env::temp_dir().join("aqs_test_missing_wind_roses.csv")).is_err());
+}
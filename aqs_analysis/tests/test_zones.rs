@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use aqs_analysis::network::MonitoringNetwork;
+use aqs_analysis::station::Station;
+use aqs_analysis::zones;
+
+mod common;
+
+fn create_test_station(id: &str, lat: f64, lon: f64) -> Station {
+    Station { state_code: "06".to_string(), state_name: "California".to_string(), ..common::station(id, lat, lon) }
+}
+
+//two basins split at longitude -119, the western one with a hole
+const BASINS_GEOJSON: &str = r#"{"type": "FeatureCollection", "features": [
+    {"type": "Feature", "properties": {"NAME": "West Basin"},
+     "geometry": {"type": "Polygon", "coordinates": [
+        [[-121, 34], [-119, 34], [-119, 36], [-121, 36], [-121, 34]],
+        [[-120.9, 35.8], [-120.7, 35.8], [-120.7, 35.95], [-120.9, 35.95], [-120.9, 35.8]]]}},
+    {"type": "Feature", "properties": {"NAME": "East Basin"},
+     "geometry": {"type": "MultiPolygon", "coordinates": [
+        [[[-119, 34], [-117, 34], [-117, 36], [-119, 36], [-119, 34]]],
+        [[[-116, 34], [-115, 34], [-115, 35], [-116, 35], [-116, 34]]]]}}
+]}"#;
+
+#[test]
+fn test_zone_assignment_and_analysis() {
+    let path = std::env::temp_dir().join("aqs_test_basins.geojson");
+    std::fs::write(&path, BASINS_GEOJSON).unwrap();
+    let basins = zones::read_zones(&path, "NAME").unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(basins.len(), 2);
+    assert!(basins[0].contains(35.0, -120.0));
+    assert!(!basins[0].contains(35.9, -120.8)); //inside the hole
+    assert!(basins[1].contains(34.5, -115.5)); //second polygon
+
+    //three stations west of the boundary, two just east of it, one outside both basins
+    let mut network = MonitoringNetwork::new();
+    for (id, lon) in [("w1", -119.6), ("w2", -119.3), ("w3", -119.1), ("e1", -118.9), ("e2", -118.5)] {
+        network.add_station(create_test_station(id, 35.0, lon));
+    }
+    network.add_station(create_test_station("out", 37.0, -119.0));
+    network.build_adjacency_list();
+    network.calculate_isolation(1);
+
+    let zone_of = zones::assign_zones(&network, &basins);
+    assert_eq!(zone_of.len(), 5);
+    assert_eq!(zone_of["w3"], "West Basin");
+    assert_eq!(zone_of["e1"], "East Basin");
+    //w3 and e1 are nearest neighbors across the boundary until the edges are restricted
+    assert_eq!(network.adjacency_list["w3"][0].0, "e1");
+    let removed = network.restrict_to_zones(&zone_of);
+    assert!(removed > 0);
+    assert_eq!(network.adjacency_list["w3"][0].0, "w2");
+    assert!(network.adjacency_list["out"].is_empty());
+    network.calculate_isolation(1);
+    assert_eq!(network.isolation("out"), None);
+
+    let pollution: HashMap<String, f64> = [("w1", 8.0), ("w2", 9.0), ("w3", 10.0), ("e1", 12.0)].into_iter()
+        .map(|(id, value)| (id.to_string(), value))
+        .collect();
+    let rows = zones::analyze_zones(&network, &pollution, &zone_of, 1);
+    let groups: Vec<&str> = rows.iter().map(|row| row.group.as_str()).collect();
+    assert_eq!(groups, vec!["East Basin", "West Basin"]);
+    assert_eq!((rows[0].station_count, rows[1].station_count), (2, 3));
+    assert_eq!(rows[0].monitored_percent, 50.0);
+    assert!((rows[1].mean_pollution.unwrap() - 9.0).abs() < 1e-9);
+    //isolation inside the east basin only sees e1-e2
+    assert_eq!(rows[0].isolation_min, rows[0].isolation_max);
+}
+
+#[test]
+fn test_zone_gap_thresholds_use_zone_pollution() {
+    //four close stations and one isolated, polluted station in the zone
+    let mut network = MonitoringNetwork::new();
+    let mut pollution = HashMap::new();
+    let mut zone_of = HashMap::new();
+    for (id, lon, value) in [("z1", -120.0, 5.0), ("z2", -119.9, 6.0), ("z3", -119.8, 7.0), ("z4", -119.7, 8.0), ("far", -119.0, 20.0)] {
+        network.add_station(create_test_station(id, 35.0, lon));
+        pollution.insert(id.to_string(), value);
+        zone_of.insert(id.to_string(), "Basin".to_string());
+    }
+    //dirtier stations elsewhere would raise a national pollution threshold above every value in the zone
+    for (i, value) in [30.0, 31.0, 32.0, 33.0, 34.0, 35.0].into_iter().enumerate() {
+        let id = format!("other{}", i);
+        network.add_station(create_test_station(&id, 40.0, -120.0 + i as f64 * 0.1));
+        pollution.insert(id, value);
+    }
+    network.build_adjacency_list();
+
+    let rows = zones::analyze_zones(&network, &pollution, &zone_of, 1);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].gap_count, 1);
+}
+
+//a polygon shapefile record with one ring
+fn polygon_record(number: i32, ring: &[(f64, f64)]) -> Vec<u8> {
+    let mut content = Vec::new();
+    content.extend(5i32.to_le_bytes());
+    content.extend([0u8; 32]); //bounding box, not used when reading
+    content.extend(1i32.to_le_bytes());
+    content.extend((ring.len() as i32).to_le_bytes());
+    content.extend(0i32.to_le_bytes());
+    for (x, y) in ring {
+        content.extend(x.to_le_bytes());
+        content.extend(y.to_le_bytes());
+    }
+    let mut record = Vec::new();
+    record.extend(number.to_be_bytes());
+    record.extend(((content.len() / 2) as i32).to_be_bytes());
+    record.extend(content);
+    record
+}
+
+#[test]
+fn test_read_zones_shapefile() {
+    let square = |x: f64| vec![(x, 34.0), (x, 36.0), (x + 2.0, 36.0), (x + 2.0, 34.0), (x, 34.0)];
+    let mut shp = vec![0u8; 100];
+    shp[0..4].copy_from_slice(&9994i32.to_be_bytes());
+    shp.extend(polygon_record(1, &square(-121.0)));
+    shp.extend(polygon_record(2, &square(-119.0)));
+
+    //dbf with one 12-character NAME field and two records
+    let mut dbf = vec![0u8; 32];
+    dbf[0] = 3;
+    dbf[4..8].copy_from_slice(&2u32.to_le_bytes());
+    dbf[8..10].copy_from_slice(&65u16.to_le_bytes());
+    dbf[10..12].copy_from_slice(&13u16.to_le_bytes());
+    let mut descriptor = [0u8; 32];
+    descriptor[..4].copy_from_slice(b"NAME");
+    descriptor[11] = b'C';
+    descriptor[16] = 12;
+    dbf.extend(descriptor);
+    dbf.push(0x0d);
+    dbf.extend(b" South Coast ");
+    dbf.extend(b" Mojave      ");
+
+    let path = std::env::temp_dir().join("aqs_test_basins.shp");
+    std::fs::write(&path, shp).unwrap();
+    std::fs::write(path.with_extension("dbf"), dbf).unwrap();
+    let basins = zones::read_zones(&path, "name").unwrap();
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(path.with_extension("dbf")).unwrap();
+
+    let names: Vec<&str> = basins.iter().map(|zone| zone.name.as_str()).collect();
+    assert_eq!(names, vec!["South Coast", "Mojave"]);
+    assert!(basins[0].contains(35.0, -120.0));
+    assert!(basins[1].contains(35.0, -118.0));
+    assert!(!basins[1].contains(35.0, -120.0));
+}
+
+#[test]
+fn test_read_zones_shapefile_rejects_corrupt_records() {
+    let square = vec![(-121.0, 34.0), (-121.0, 36.0), (-119.0, 36.0), (-119.0, 34.0), (-121.0, 34.0)];
+    let header = || {
+        let mut shp = vec![0u8; 100];
+        shp[0..4].copy_from_slice(&9994i32.to_be_bytes());
+        shp
+    };
+    //byte offset inside a record of the content length, part count, point count and first part offset
+    let corruptions: [(usize, [u8; 4]); 5] = [
+        (4, (-1i32).to_be_bytes()),
+        (4, i32::MAX.to_be_bytes()),
+        (8 + 36, (-2i32).to_le_bytes()),
+        (8 + 40, i32::MAX.to_le_bytes()),
+        (8 + 44, 9i32.to_le_bytes()),
+    ];
+    let path = std::env::temp_dir().join("aqs_test_corrupt.shp");
+    for (at, bytes) in corruptions {
+        let mut record = polygon_record(1, &square);
+        record[at..at + 4].copy_from_slice(&bytes);
+        let mut shp = header();
+        shp.extend(record);
+        std::fs::write(&path, shp).unwrap();
+        assert!(zones::read_zones(&path, "NAME").is_err(), "corruption at byte {}", at);
+    }
+    std::fs::write(&path, b"not a shapefile").unwrap();
+    assert!(zones::read_zones(&path, "NAME").is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_read_zones_geojson_malformed_input() {
+    let path = std::env::temp_dir().join("aqs_test_bad_basins.geojson");
+    //a point feature is skipped, the polygon is kept
+    std::fs::write(&path, r#"{"type": "FeatureCollection", "features": [
+        {"properties": {"NAME": "Point"}, "geometry": {"type": "Point", "coordinates": [-120, 35]}},
+        {"properties": {}, "geometry": {"type": "Polygon", "coordinates": [[[-121, 34], [-119, 34], [-119, 36], [-121, 34]]]}}]}"#).unwrap();
+    let basins = zones::read_zones(&path, "NAME").unwrap();
+    assert_eq!(basins.len(), 1);
+    assert_eq!(basins[0].name, "zone 2");
+    //truncated json and a file without a features array are errors
+    for text in [r#"{"type": "FeatureCollection", "features": ["#, r#"{"type": "Polygon"}"#] {
+        std::fs::write(&path, text).unwrap();
+        assert!(zones::read_zones(&path, "NAME").is_err(), "{}", text);
+    }
+    std::fs::remove_file(&path).unwrap();
+}